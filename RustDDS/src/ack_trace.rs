@@ -0,0 +1,81 @@
+/// Write-to-acknowledgment times of reliable writes.
+///
+/// RustDDS only tells whether all matched readers have acknowledged all
+/// writes of a DataWriter, not which readers have acknowledged what. So we
+/// keep the writes not yet acknowledged, check without waiting whenever the
+/// publisher loop wakes up, and when a check succeeds, report each outstanding
+/// write as acknowledged by all matched readers. Checking at least every
+/// CHECK_INTERVAL bounds how late a write is seen as acknowledged.
+///
+/// Writes are counted by samples_written of the writer, so every sample
+/// written since the previous record_writes is outstanding.
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+
+pub const CHECK_INTERVAL: Duration = Duration::from_millis(5);
+/// Writes not acknowledged in this time are reported and forgotten.
+pub const TIMEOUT: Duration = Duration::from_secs(1);
+
+pub struct AckTrace {
+  /// (write number, time of write) of each writer, oldest first
+  outstanding: Vec<VecDeque<(u64, Instant)>>,
+  /// samples_written of each writer at the previous record_writes
+  recorded: Vec<u64>,
+}
+
+impl AckTrace {
+  pub fn new(writers: usize) -> AckTrace {
+    AckTrace { outstanding: vec![VecDeque::new(); writers], recorded: vec![0; writers] }
+  }
+
+  /// Takes samples_written of each writer, and records the new writes.
+  pub fn record_writes(&mut self, samples_written: impl Iterator<Item = u64>) {
+    let now = Instant::now();
+    for ((outstanding, recorded), written) in self.outstanding.iter_mut().zip(self.recorded.iter_mut())
+      .zip(samples_written)
+    {
+      outstanding.extend((*recorded + 1 ..= written).map( |write| (write, now) ));
+      *recorded = written;
+    }
+  }
+
+  pub fn is_pending(&self, writer: usize) -> bool {
+    !self.outstanding[writer].is_empty()
+  }
+
+  /// When to check again, if any writes are outstanding
+  pub fn next_check(&self) -> Option<Instant> {
+    self.outstanding.iter().any( |o| !o.is_empty() ).then( || Instant::now() + CHECK_INTERVAL )
+  }
+
+  /// Forget outstanding writes, e.g. when the writers are recreated.
+  pub fn clear(&mut self) {
+    self.outstanding.iter_mut().for_each(VecDeque::clear);
+  }
+
+  /// Reports the outstanding writes of a writer, given whether all its writes
+  /// are acknowledged now.
+  pub fn update<E: Debug>(&mut self, writer: usize, topic_name: &str, acknowledged: Result<bool, E>) {
+    let now = Instant::now();
+    let outstanding = &mut self.outstanding[writer];
+    match acknowledged {
+      Ok(true) => for (write, written) in outstanding.drain(..) {
+        println!("Write {} on {} acknowledged by all matched readers in {:.3} ms", write, topic_name,
+          now.duration_since(written).as_secs_f64() * 1000.0);
+      }
+      Ok(false) => while let Some((write, written)) = outstanding.front().copied() {
+        if now.duration_since(written) < TIMEOUT {
+          break
+        }
+        println!("Write {} on {} not acknowledged by all matched readers within {:?}", write, topic_name,
+          TIMEOUT);
+        outstanding.pop_front();
+      }
+      Err(e) => {
+        println!("Checking acknowledgments on {} failed: {:?}", topic_name, e);
+        outstanding.clear();
+      }
+    }
+  }
+}
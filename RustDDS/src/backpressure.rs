@@ -0,0 +1,61 @@
+/// Watching DataWriter writes for flow control effects.
+///
+/// With RELIABLE and KEEP_ALL, a reader that does not acknowledge eventually
+/// fills up the writer history. Our writers have max_blocking_time zero (see
+/// QosConfig), so writes then fail at once instead of blocking. Instead of
+/// stopping at the first failed write, we report when writes start failing,
+/// and when they succeed again.
+use std::time::Instant;
+
+use rustdds::dds::Error;
+
+use crate::state::PublisherState;
+
+/// An ongoing period of failed writes.
+struct Episode {
+  since: Instant,
+  failed: u64,
+}
+
+#[derive(Default)]
+pub struct WriteMonitor {
+  episode: Option<Episode>,
+}
+
+fn explain(e: &Error) -> &'static str {
+  match e {
+    Error::OutOfResources => "history resources exhausted",
+    Error::Timeout => "blocked longer than max_blocking_time",
+    _ => "write failed",
+  }
+}
+
+impl WriteMonitor {
+  /// Records the outcome of one write. Returns true if the write succeeded.
+  pub fn record(&mut self, topic_name: &str, result: &rustdds::dds::Result<()>, state: &mut PublisherState)
+    -> bool
+  {
+    match result {
+      Ok(()) => {
+        if let Some(e) = self.episode.take() {
+          println!(
+            "Writes on {} succeeding again after {:.3} s: {} failed",
+            topic_name,
+            e.since.elapsed().as_secs_f64(),
+            e.failed
+          );
+        }
+        true
+      }
+      Err(e) => {
+        let episode = self.episode.get_or_insert_with(|| {
+          println!("Write on {} failed: {} ({:?})", topic_name, explain(e), e);
+          Episode { since: Instant::now(), failed: 0 }
+        });
+        episode.failed += 1;
+        state.writes_failed += 1;
+        false
+      }
+    }
+  }
+}
@@ -0,0 +1,91 @@
+/// Writer-side batching of samples.
+///
+/// RustDDS does not support sample batching: every write is sent as its own
+/// DATA submessage. We can still accumulate samples and write them out in a
+/// burst once the batch is full, which is what a batching writer looks like
+/// in timing. The report shows how many samples went out in how many batches,
+/// and that the number of DATA messages stays equal to the sample count.
+use crate::ShapeData;
+
+/// CDR encapsulation header
+const ENCAPSULATION_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BatchLimit {
+  Samples(usize),
+  Bytes(usize),
+}
+
+impl BatchLimit {
+  /// Parses "n" (samples) or "nB" (bytes of serialized data).
+  pub fn parse(s: &str) -> Result<BatchLimit, String> {
+    let (number, bytes) = match s.strip_suffix(|c| c == 'b' || c == 'B') {
+      Some(n) => (n, true),
+      None => (s, false),
+    };
+    match number.parse::<usize>() {
+      Ok(n) if n > 0 && bytes => Ok(BatchLimit::Bytes(n)),
+      Ok(n) if n > 0 => Ok(BatchLimit::Samples(n)),
+      _ => Err(format!("Expected positive sample count n or byte count nB, got \"{}\"", s)),
+    }
+  }
+}
+
+/// Approximate serialized size of a sample: string length and contents padded
+/// to 4 bytes, then the 32-bit integers.
+pub fn cdr_size<T: ShapeData>(sample: &T) -> usize {
+  let color = 4 + (sample.shape().color.len() + 1).div_ceil(4) * 4;
+  let integers = if sample.counter().is_some() { 4 } else { 3 };
+  ENCAPSULATION_SIZE + color + 4 * integers
+}
+
+pub struct Batcher<T> {
+  limit: BatchLimit,
+  /// (writer index, sample)
+  pending: Vec<(usize, T)>,
+  pending_bytes: usize,
+  batches: u64,
+  samples: u64,
+  bytes: u64,
+}
+
+impl<T: ShapeData> Batcher<T> {
+  pub fn new(limit: BatchLimit) -> Batcher<T> {
+    Batcher { limit, pending: Vec::new(), pending_bytes: 0, batches: 0, samples: 0, bytes: 0 }
+  }
+
+  /// Adds a sample. Returns a complete batch, if this filled it.
+  pub fn push(&mut self, writer: usize, sample: T) -> Option<Vec<(usize, T)>> {
+    self.pending_bytes += cdr_size(&sample);
+    self.pending.push((writer, sample));
+    let full = match self.limit {
+      BatchLimit::Samples(n) => self.pending.len() >= n,
+      BatchLimit::Bytes(n) => self.pending_bytes >= n,
+    };
+    if !full {
+      return None
+    }
+    self.batches += 1;
+    self.samples += self.pending.len() as u64;
+    self.bytes += self.pending_bytes as u64;
+    self.pending_bytes = 0;
+    Some(std::mem::take(&mut self.pending))
+  }
+
+  pub fn pending(&self) -> usize {
+    self.pending.len()
+  }
+
+  pub fn report(&self) -> String {
+    format!(
+      "Batching {:?}: {} samples ({} bytes) in {} batches, {:.1} samples per batch, {} pending. \
+       Each sample is still sent as its own DATA submessage.",
+      self.limit,
+      self.samples,
+      self.bytes,
+      self.batches,
+      self.samples as f64 / self.batches.max(1) as f64,
+      self.pending.len()
+    )
+  }
+}
@@ -0,0 +1,86 @@
+/// Built-in topic data as structured records, one JSON object per line.
+///
+/// RustDDS does not give applications DataReaders for the built-in topics.
+/// DCPSParticipant records come from our own SPDP listener, and DCPSTopic
+/// records from the topics RustDDS has discovered. Each poll is compared to
+/// the previous one, and new, updated and gone entries are printed.
+/// DCPSPublication and DCPSSubscription are not available.
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rustdds::dds::DomainParticipant;
+use serde_json::{json, Value};
+
+use crate::spdp::{vendor_name, DiscoveredParticipants};
+use crate::status_report::format_guid;
+use crate::topic_watch::describe_qos;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+pub struct BuiltinTopics {
+  /// (built-in topic name, key) -> last printed data
+  known: BTreeMap<(&'static str, String), Value>,
+  /// None until the first poll
+  next_poll: Option<Instant>,
+}
+
+fn print_record(builtin_topic: &str, change: &str, key: &str, data: &Value) {
+  let timestamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs_f64())
+    .unwrap_or(0.0);
+  let record = json!({
+    "timestamp": timestamp,
+    "builtin_topic": builtin_topic,
+    "change": change,
+    "key": key,
+    "data": data,
+  });
+  println!("{}", record);
+}
+
+impl BuiltinTopics {
+  /// Polls discovery data, if due, and prints the changes.
+  pub fn poll(&mut self, domain_participant: &DomainParticipant, discovered: Option<&DiscoveredParticipants>) {
+    let now = Instant::now();
+    if self.next_poll.is_some_and(|t| now < t) {
+      return
+    }
+    self.next_poll = Some(now + POLL_INTERVAL);
+
+    let mut current: BTreeMap<(&'static str, String), Value> = BTreeMap::new();
+    for (guid_prefix, p) in discovered.map(DiscoveredParticipants::snapshot).unwrap_or_default() {
+      current.insert(("DCPSParticipant", guid_prefix), json!({
+        "vendor_id": format!("{:02x}{:02x}", p.vendor_id[0], p.vendor_id[1]),
+        "vendor": vendor_name(p.vendor_id),
+        "protocol_version": format!("{}.{}", p.protocol_version.0, p.protocol_version.1),
+        "default_unicast": p.default_unicast,
+        "default_multicast": p.default_multicast,
+        "metatraffic_unicast": p.metatraffic_unicast,
+        "metatraffic_multicast": p.metatraffic_multicast,
+      }));
+    }
+    for discovered_topic in domain_participant.get_discovered_topics() {
+      let data = discovered_topic.topic_data;
+      let key = data.key.map_or_else(|| "unknown".to_string(), |guid| format_guid(&guid));
+      current.insert(("DCPSTopic", key), json!({
+        "name": data.name,
+        "type_name": data.type_name,
+        "qos": describe_qos(&data),
+      }));
+    }
+
+    for ((builtin_topic, key), data) in &current {
+      match self.known.get(&(*builtin_topic, key.clone())) {
+        None => print_record(builtin_topic, "new", key, data),
+        Some(old) if old != data => print_record(builtin_topic, "updated", key, data),
+        _ => (),
+      }
+    }
+    for ((builtin_topic, key), data) in self.known.iter().filter(|(k, _)| !current.contains_key(*k)) {
+      print_record(builtin_topic, "gone", key, data);
+    }
+    self.known = current;
+  }
+}
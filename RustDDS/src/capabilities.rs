@@ -0,0 +1,87 @@
+/// Machine-readable description of what this program and RustDDS support,
+/// so that a test runner can skip unsupported cases instead of failing them.
+///
+/// The tables below are shared with cli::check_unsupported, which rejects the
+/// unsupported options, and durability kinds come from the -D values, so the
+/// description and the parser cannot disagree. Keep the rest of the
+/// description in step with the other "not yet implemented" checks there.
+use serde_json::{json, Value};
+
+use crate::console;
+use crate::qos::{durability_from_letter, durability_name, DURABILITY_LETTERS};
+
+/// QoS policies RustDDS does not implement, that have a command line option:
+/// (option, capability name, policy name)
+pub const UNSUPPORTED_QOS_OPTIONS: &[(&str, &str, &str)] = &[
+  ("partition", "partition", "Partition"),
+  ("interval", "time_based_filter", "Time Based Filter"),
+];
+
+/// QoS policies only RustDDS DataWriters implement, so the option is rejected
+/// for a subscriber: (option, capability name, policy name)
+pub const WRITER_ONLY_QOS_OPTIONS: &[(&str, &str, &str)] = &[
+  ("ownership_strength", "ownership", "Exclusive Ownership"),
+];
+
+/// QoS policies with a command line option that both ends implement
+const SUPPORTED_QOS: &[&str] = &["reliability", "durability", "history", "deadline", "liveliness"];
+
+/// Transports: (capability name, implemented). RustDDS has only UDPv4, so
+/// there is no option to choose.
+const TRANSPORTS: &[(&str, bool)] = &[
+  ("udpv4", true),
+  ("udpv6", false),
+  ("tcp", false),
+  ("shm", false),
+];
+
+/// Publish modes: (name, implemented). DataWriter write always queues the
+/// sample for the RustDDS event loop thread, so there is no option to choose.
+const PUBLISH_MODES: &[(&str, bool)] = &[("async", true), ("sync", false)];
+
+pub fn capabilities() -> Value {
+  let unsupported_qos: Vec<&str> = UNSUPPORTED_QOS_OPTIONS.iter().map( |(_, name, _)| *name )
+    .chain(std::iter::once("presentation_coherent_access")).collect();
+  let writer_only_qos: Vec<&str> = WRITER_ONLY_QOS_OPTIONS.iter().map( |(_, name, _)| *name ).collect();
+  let durability_kinds: Vec<&str> = DURABILITY_LETTERS.iter()
+    .map( |letter| durability_name(durability_from_letter(Some(letter))) ).collect();
+  let mut transports: serde_json::Map<String, Value> = TRANSPORTS.iter()
+    .map( |(name, implemented)| (name.to_string(), Value::Bool(*implemented)) ).collect();
+  // RustDDS always uses these, and they cannot be set.
+  transports.insert("multicast_address".to_string(), json!(["239.255.0.1"]));
+  transports.insert("multicast_ttl".to_string(), json!([1]));
+  let publish_modes: Vec<&str> = PUBLISH_MODES.iter().filter( |(_, implemented)| *implemented )
+    .map( |(mode, _)| *mode ).collect();
+  let unsupported_commands: Vec<&str> = console::UNSUPPORTED_COMMANDS.iter().cloned()
+    .chain(console::REJECTED_COMMANDS.iter().map( |(command, _)| *command )).collect();
+  json!({
+    "implementation": "RustDDS",
+    "program_version": env!("CARGO_PKG_VERSION"),
+    "qos_policies": {
+      "supported": SUPPORTED_QOS,
+      "writer_only": writer_only_qos,
+      "unsupported": unsupported_qos,
+      "durability_kinds": durability_kinds,
+      "mutable_qos_changes": false,
+    },
+    "data_representations": ["XCDR1"],
+    "security": false,
+    "content_filtering": "reader_side_only",
+    "transports": transports,
+    "discovery": {
+      "spdp_sedp": true,
+      "static": false,
+      "guid_prefix_override": false,
+    },
+    "publish_modes": publish_modes,
+    "batching": false,
+    // DataReader always deserializes samples into owned values.
+    "loaned_samples": false,
+    // GAPs are sent only by the RustDDS reliability protocol.
+    "forced_gaps": false,
+    "dispose": true,
+    "unregister": !console::UNSUPPORTED_COMMANDS.contains(&"unregister"),
+    "console_commands": { "unsupported": unsupported_commands },
+    "key_fields": ["color", "color,shapesize"],
+  })
+}
@@ -0,0 +1,174 @@
+/// Capture of RTPS traffic into a pcapng file.
+///
+/// RustDDS does not give applications access to the messages it sends or receives,
+/// so we tap the traffic that is visible to any process on this host: the RTPS
+/// multicast groups of our domain. This covers participant discovery and
+/// multicast user traffic, including our own multicast transmissions, which are
+/// looped back to us. Unicast traffic is not captured.
+///
+/// Packets are recorded with LINKTYPE_IPV4, i.e. with a synthesized IPv4 and UDP
+/// header in front of the RTPS message, so that Wireshark dissects them normally.
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{error, info};
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+
+// RTPS spec 9.6.1.1 default port mapping
+pub const PORT_BASE: u16 = 7400;
+pub const DOMAIN_ID_GAIN: u16 = 250;
+pub const OFFSET_D0: u16 = 0; // discovery multicast
+pub const OFFSET_D2: u16 = 1; // user traffic multicast
+pub const DEFAULT_MULTICAST_GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 0, 1);
+
+const LINKTYPE_IPV4: u16 = 228;
+const MAX_UDP_PAYLOAD: usize = 65507;
+
+pub struct PcapngWriter<W: Write> {
+  out: W,
+}
+
+impl<W: Write> PcapngWriter<W> {
+  /// Writes the section header and a single interface description.
+  pub fn new(mut out: W) -> io::Result<PcapngWriter<W>> {
+    // Section Header Block
+    let mut shb = Vec::new();
+    shb.extend_from_slice(&0x1A2B_3C4Du32.to_le_bytes()); // byte-order magic
+    shb.extend_from_slice(&1u16.to_le_bytes()); // major version
+    shb.extend_from_slice(&0u16.to_le_bytes()); // minor version
+    shb.extend_from_slice(&(-1i64).to_le_bytes()); // section length: unspecified
+    write_block(&mut out, 0x0A0D_0D0A, &shb)?;
+
+    // Interface Description Block
+    let mut idb = Vec::new();
+    idb.extend_from_slice(&LINKTYPE_IPV4.to_le_bytes());
+    idb.extend_from_slice(&0u16.to_le_bytes()); // reserved
+    idb.extend_from_slice(&0u32.to_le_bytes()); // snaplen: no limit
+    write_block(&mut out, 0x0000_0001, &idb)?;
+
+    out.flush()?;
+    Ok(PcapngWriter { out })
+  }
+
+  /// Records a UDP datagram as an Enhanced Packet Block.
+  pub fn write_udp(&mut self, src: SocketAddrV4, dst: SocketAddrV4, payload: &[u8], time: SystemTime)
+    -> io::Result<()>
+  {
+    let packet = ipv4_udp_packet(src, dst, payload);
+    let micros = time.duration_since(UNIX_EPOCH).map(|d| d.as_micros() as u64).unwrap_or(0);
+
+    let mut epb = Vec::with_capacity(20 + packet.len() + 3);
+    epb.extend_from_slice(&0u32.to_le_bytes()); // interface id
+    epb.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+    epb.extend_from_slice(&(micros as u32).to_le_bytes());
+    epb.extend_from_slice(&(packet.len() as u32).to_le_bytes()); // captured length
+    epb.extend_from_slice(&(packet.len() as u32).to_le_bytes()); // original length
+    epb.extend_from_slice(&packet);
+    write_block(&mut self.out, 0x0000_0006, &epb)?;
+    // Flush every packet, so that the file is complete whenever we happen to exit.
+    self.out.flush()
+  }
+}
+
+// Block body is padded to 32-bit boundary. Total length is repeated at the end.
+fn write_block<W: Write>(out: &mut W, block_type: u32, body: &[u8]) -> io::Result<()> {
+  let padding = (4 - body.len() % 4) % 4;
+  let total_len = (12 + body.len() + padding) as u32;
+  out.write_all(&block_type.to_le_bytes())?;
+  out.write_all(&total_len.to_le_bytes())?;
+  out.write_all(body)?;
+  out.write_all(&[0u8; 3][..padding])?;
+  out.write_all(&total_len.to_le_bytes())
+}
+
+fn ipv4_udp_packet(src: SocketAddrV4, dst: SocketAddrV4, payload: &[u8]) -> Vec<u8> {
+  let udp_len = 8 + payload.len();
+  let total_len = 20 + udp_len;
+  let mut p = Vec::with_capacity(total_len);
+  // IPv4 header, no options
+  p.push(0x45); // version 4, IHL 5
+  p.push(0); // DSCP/ECN
+  p.extend_from_slice(&(total_len as u16).to_be_bytes());
+  p.extend_from_slice(&[0, 0, 0x40, 0]); // identification, flags: DF
+  p.push(1); // TTL
+  p.push(17); // protocol: UDP
+  p.extend_from_slice(&[0, 0]); // checksum, filled in below
+  p.extend_from_slice(&src.ip().octets());
+  p.extend_from_slice(&dst.ip().octets());
+  let checksum = ipv4_checksum(&p[0..20]);
+  p[10..12].copy_from_slice(&checksum.to_be_bytes());
+  // UDP header. Checksum 0 = not computed, which is legal over IPv4.
+  p.extend_from_slice(&src.port().to_be_bytes());
+  p.extend_from_slice(&dst.port().to_be_bytes());
+  p.extend_from_slice(&(udp_len as u16).to_be_bytes());
+  p.extend_from_slice(&[0, 0]);
+  p.extend_from_slice(payload);
+  p
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+  let mut sum: u32 = header
+    .chunks(2)
+    .map(|w| u32::from(u16::from_be_bytes([w[0], w[1]])))
+    .sum();
+  while sum > 0xFFFF {
+    sum = (sum & 0xFFFF) + (sum >> 16);
+  }
+  !(sum as u16)
+}
+
+/// Start capturing RTPS multicast traffic of the given domain into a pcapng file.
+/// Capture runs in background threads until the process exits.
+pub fn start_multicast_capture(path: &str, domain_id: u16) -> io::Result<()> {
+  let writer = Arc::new(Mutex::new(PcapngWriter::new(BufWriter::new(File::create(path)?))?));
+  let domain_base = PORT_BASE + DOMAIN_ID_GAIN * domain_id;
+  for port in &[domain_base + OFFSET_D0, domain_base + OFFSET_D2] {
+    let dst = SocketAddrV4::new(DEFAULT_MULTICAST_GROUP, *port);
+    let socket = multicast_socket(dst)?;
+    let writer = Arc::clone(&writer);
+    thread::Builder::new()
+      .name(format!("capture-{}", port))
+      .spawn(move || capture_loop(socket, dst, writer))?;
+  }
+  info!("Capturing RTPS multicast traffic to {}", path);
+  Ok(())
+}
+
+pub fn multicast_socket(group: SocketAddrV4) -> io::Result<UdpSocket> {
+  let socket = Socket::new(Domain::ipv4(), Type::dgram(), Some(Protocol::udp()))?;
+  // RustDDS binds the same ports, so we must share them.
+  socket.set_reuse_address(true)?;
+  socket.bind(&SockAddr::from(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, group.port())))?;
+  socket.join_multicast_v4(group.ip(), &Ipv4Addr::UNSPECIFIED)?;
+  Ok(socket.into_udp_socket())
+}
+
+fn capture_loop(socket: UdpSocket, dst: SocketAddrV4,
+    writer: Arc<Mutex<PcapngWriter<BufWriter<File>>>>)
+{
+  let mut buf = vec![0u8; MAX_UDP_PAYLOAD];
+  loop {
+    match socket.recv_from(&mut buf) {
+      Ok((len, SocketAddr::V4(src))) => {
+        let payload = &buf[..len];
+        if !payload.starts_with(b"RTPS") {
+          continue
+        }
+        let result = writer.lock().unwrap().write_udp(src, dst, payload, SystemTime::now());
+        if let Err(e) = result {
+          error!("Capture file write failed, stopping capture: {:?}", e);
+          return
+        }
+      }
+      Ok((_, SocketAddr::V6(_))) => {} // We only join IPv4 groups.
+      Err(e) => {
+        error!("Capture socket receive failed, stopping capture: {:?}", e);
+        return
+      }
+    }
+  }
+}
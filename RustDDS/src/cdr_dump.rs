@@ -0,0 +1,125 @@
+/// Hex dumps of serialized samples, for byte-level comparison with the
+/// encodings of other vendors.
+///
+/// RustDDS does not show the bytes it sends or receives. Outgoing samples are
+/// serialized here with the CDR serializer of RustDDS, little-endian as our
+/// DataWriters use it, after the CDR_LE encapsulation header. Incoming payloads are taken from DATA
+/// submessages in the multicast user traffic of the domain, like
+/// protocol_stats does, so samples sent only to unicast are not seen.
+use std::net::SocketAddrV4;
+use std::thread;
+
+use byteorder::LittleEndian;
+use log::error;
+use rustdds::serialization;
+
+use crate::capture::{multicast_socket, DEFAULT_MULTICAST_GROUP, DOMAIN_ID_GAIN, OFFSET_D2, PORT_BASE};
+use crate::protocol_stats::{hex, u16_at, u32_at, SUBMESSAGE_DATA};
+use crate::ShapeData;
+
+/// Encapsulation header of CDR_LE
+const ENCAPSULATION_CDR_LE: [u8; 4] = [0x00, 0x01, 0x00, 0x00];
+const FLAG_INLINE_QOS: u8 = 0x02;
+const FLAG_DATA: u8 = 0x04;
+const PID_SENTINEL: u16 = 0x0001;
+const BYTES_PER_LINE: usize = 16;
+
+/// Serialized payload of a sample, with encapsulation header
+pub fn serialize<T: ShapeData>(sample: &T) -> Result<Vec<u8>, String> {
+  let data = serialization::to_bytes::<T, LittleEndian>(sample).map_err(|e| format!("{:?}", e))?;
+  let mut bytes = ENCAPSULATION_CDR_LE.to_vec();
+  bytes.extend_from_slice(&data);
+  Ok(bytes)
+}
+
+/// Offset and up to BYTES_PER_LINE bytes in hex per line
+pub fn hex_dump(bytes: &[u8]) -> Vec<String> {
+  bytes.chunks(BYTES_PER_LINE).enumerate().map(|(i, chunk)| {
+    let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("  {:04x}: {}", i * BYTES_PER_LINE, hex.join(" "))
+  }).collect()
+}
+
+pub fn print_outgoing<T: ShapeData>(topic_name: &str, sample: &T) {
+  let bytes = match serialize(sample) {
+    Ok(bytes) => bytes,
+    Err(e) => {
+      println!("CDR out on {}: cannot serialize: {}", topic_name, e);
+      return
+    }
+  };
+  println!("CDR out on {}, color {} ({} bytes):", topic_name, sample.shape().color, bytes.len());
+  for line in hex_dump(&bytes) {
+    println!("{}", line);
+  }
+}
+
+/// Writer GUID in hex, sequence number and serialized payload of each DATA
+/// submessage in an RTPS message.
+fn data_payloads(msg: &[u8]) -> Vec<(String, u64, &[u8])> {
+  let mut payloads = Vec::new();
+  if !msg.starts_with(b"RTPS") || msg.len() < 20 {
+    return payloads
+  }
+  let source_prefix = hex(&msg[8..20]);
+  let mut pos = 20;
+  while pos + 4 <= msg.len() {
+    let id = msg[pos];
+    let flags = msg[pos + 1];
+    let little_endian = flags & 0x01 != 0;
+    let length = u16_at(msg, pos + 2, little_endian) as usize;
+    let body_start = pos + 4;
+    let body_end = if length == 0 { msg.len() } else { (body_start + length).min(msg.len()) };
+    let body = &msg[body_start..body_end];
+    if id == SUBMESSAGE_DATA && flags & FLAG_DATA != 0 && body.len() >= 20 {
+      let writer = format!("{}.{}", source_prefix, hex(&body[8..12]));
+      let sequence_number = (u32_at(body, 12, little_endian) as u64) << 32 | u32_at(body, 16, little_endian) as u64;
+      let mut payload_start = 4 + u16_at(body, 2, little_endian) as usize;
+      if flags & FLAG_INLINE_QOS != 0 {
+        while payload_start + 4 <= body.len() {
+          let pid = u16_at(body, payload_start, little_endian);
+          let parameter_length = u16_at(body, payload_start + 2, little_endian) as usize;
+          payload_start += 4 + parameter_length;
+          if pid == PID_SENTINEL {
+            break
+          }
+        }
+      }
+      if payload_start <= body.len() {
+        payloads.push((writer, sequence_number, &body[payload_start..]));
+      }
+    }
+    pos = body_end;
+  }
+  payloads
+}
+
+/// Dump the payloads of the first `count` DATA submessages in the multicast
+/// user traffic of the domain, in a background thread.
+pub fn start_incoming_dump(domain_id: u16, count: usize) -> std::io::Result<()> {
+  let port = PORT_BASE + DOMAIN_ID_GAIN * domain_id + OFFSET_D2;
+  let socket = multicast_socket(SocketAddrV4::new(DEFAULT_MULTICAST_GROUP, port))?;
+  thread::Builder::new()
+    .name("cdr-dump".to_string())
+    .spawn(move || {
+      let mut buf = vec![0u8; 65536];
+      let mut remaining = count;
+      while remaining > 0 {
+        let len = match socket.recv_from(&mut buf) {
+          Ok((len, _src)) => len,
+          Err(e) => {
+            error!("CDR dump socket receive failed, stopping: {:?}", e);
+            return
+          }
+        };
+        for (writer, sequence_number, payload) in data_payloads(&buf[..len]).into_iter().take(remaining) {
+          remaining -= 1;
+          println!("CDR in from writer {}, sequence number {} ({} bytes):", writer, sequence_number, payload.len());
+          for line in hex_dump(payload) {
+            println!("{}", line);
+          }
+        }
+      }
+    })?;
+  Ok(())
+}
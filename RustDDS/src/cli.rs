@@ -0,0 +1,907 @@
+/// Command line: argument definitions, and parsing them into a Config.
+///
+/// Options that RustDDS cannot support are rejected while parsing, with a
+/// panic message that says so.
+use std::time::Duration;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use log::LevelFilter;
+
+use crate::batch::BatchLimit;
+use crate::capabilities::{UNSUPPORTED_QOS_OPTIONS, WRITER_ONLY_QOS_OPTIONS};
+use crate::config::{Config, Diagnostics, Mode};
+use crate::expect::Expectation;
+use crate::filter::Filter;
+use crate::impairment::Impairment;
+use crate::mutate::TypeMutation;
+use crate::on_error::OnError;
+use crate::pattern::Pattern;
+use crate::publisher::PublisherOptions;
+use crate::qos::{durability_from_letter, QosConfig, DURABILITY_LETTERS};
+use crate::source_time::SourceTimestamps;
+use crate::status_report::LogFormat;
+use crate::subscriber::SubscriberOptions;
+use crate::{capabilities, compare, failover, generic, idl, input, latency, logging, matrix, scenario, script,
+  selftest, shape_color, writer_history, EXIT_SETUP_FAILED};
+
+pub fn app() -> App<'static, 'static> {
+  App::new("RustDDS-interop")
+        .version("0.2.2")
+        .author("Juhana Helovuo <juhe@iki.fi>")
+        .about("Command-line \"shapes\" interoperability test.")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(SubCommand::with_name("matrix")
+          .about("Run publisher/subscriber QoS combinations from a spec file as child processes")
+          .arg(Arg::with_name("spec")
+            .help("Matrix spec file (JSON)")
+            .value_name("file")
+            .required(true)))
+        .subcommand(SubCommand::with_name("selftest")
+          .about("Run a publisher and a subscriber as child processes with the same options, and report pass/fail")
+          .arg(Arg::with_name("topic")
+            .short("t")
+            .value_name("name")
+            .default_value("Square"))
+          .arg(Arg::with_name("domain_id")
+            .short("d")
+            .value_name("id")
+            .default_value("0"))
+          .arg(Arg::with_name("seconds")
+            .help("How long to run")
+            .long("seconds")
+            .value_name("secs")
+            .default_value("5"))
+          .arg(Arg::with_name("options")
+            .help("Options for both publisher and subscriber, after --, e.g. -- -r -D l --counter")
+            .multiple(true)
+            .last(true)))
+        .subcommand(SubCommand::with_name("scenarios")
+          .about("List the canned interop test scenarios, with the options of each role"))
+        .subcommand(SubCommand::with_name("run-scenario")
+          .about("Run one role of a canned interop test scenario, with all its options set")
+          .arg(Arg::with_name("name")
+            .help("Scenario name, as listed by the scenarios subcommand")
+            .required(true))
+          .arg(Arg::with_name("publisher")
+            .help("Act as publisher")
+            .short("P")
+            .conflicts_with("subscriber")
+            .required_unless("subscriber"))
+          .arg(Arg::with_name("subscriber")
+            .help("Act as subscriber")
+            .short("S")
+            .conflicts_with("publisher")
+            .required_unless("publisher"))
+          .arg(Arg::with_name("topic")
+            .short("t")
+            .value_name("name")
+            .default_value("Square"))
+          .arg(Arg::with_name("domain_id")
+            .short("d")
+            .value_name("id")
+            .default_value("0"))
+          .arg(Arg::with_name("options")
+            .help("More options for the role, after --, e.g. -- -c RED --counter")
+            .multiple(true)
+            .last(true)))
+        .arg(Arg::with_name("capabilities")
+          .help("Print supported features as JSON and exit")
+          .long("capabilities"))
+        .arg(Arg::with_name("domain_id")
+          .short("d")
+          .value_name("id")
+          .help("Sets the DDS domain id number")
+          .takes_value(true))
+        .arg(Arg::with_name("topic")
+          .short("t")
+          .value_name("name")
+          .help("Sets the topic name, or a comma-separated list of topic names")
+          .takes_value(true)
+      		.required_unless_one(&["capabilities", "watch_topics"]))
+        .arg(Arg::with_name("color")
+          .short("c")
+          .value_name("color")
+          .help("Color to publish (or filter): one of the Shapes demo colors, in any case")
+          .takes_value(true))
+        .arg(Arg::with_name("any_color")
+          .help("Accept any -c color as given, instead of only the Shapes demo colors in uppercase")
+          .long("any-color"))
+        .arg(Arg::with_name("durability")
+          .short("D")
+          .value_name("durability")
+          .help("Set durability")
+          .takes_value(true)
+          .possible_values(DURABILITY_LETTERS))
+        .arg(Arg::with_name("publisher")
+          .help("Act as publisher")
+          .short("P")
+          .conflicts_with("subscriber")
+          .required_unless_one(&["subscriber", "capabilities", "watch_topics"]))
+        .arg(Arg::with_name("subscriber")
+          .help("Act as subscriber")
+          .short("S")
+          .conflicts_with("publisher")
+          .required_unless_one(&["publisher", "capabilities", "watch_topics"]))
+        .arg(Arg::with_name("watch_topics")
+          .help("Only print topics, with type names and QoS, as remote participants announce them or they \
+                 disappear. Creates no DataReaders or DataWriters.")
+          .long("watch-topics")
+          .conflicts_with_all(&["publisher", "subscriber"]))
+        .arg(Arg::with_name("best_effort")
+          .help("BEST_EFFORT reliability")
+          .short("b")
+          .conflicts_with("reliable"))
+        .arg(Arg::with_name("reliable")
+          .help("RELIABLE reliability")
+          .short("r")
+          .conflicts_with("best_effort"))
+        .arg(Arg::with_name("history_depth")
+          .help("Keep history depth")
+          .short("k")
+          .takes_value(true)
+          .value_name("depth"))
+        .arg(Arg::with_name("deadline")
+          .help("Set a 'deadline' with interval (seconds)")
+          .short("f")
+          .takes_value(true)
+          .value_name("interval"))
+        .arg(Arg::with_name("partition")
+          .help("Set a 'partition' string")
+          .short("p")
+          .takes_value(true)
+          .value_name("partition"))
+        .arg(Arg::with_name("interval")
+          .help("Apply 'time based filter' with interval (seconds)")
+          .short("i")
+          .takes_value(true)
+          .value_name("interval"))
+        .arg(Arg::with_name("ownership_strength")
+          .help("Set ownership strength [-1: SHARED]. Publisher only: RustDDS DataReaders do not implement \
+                 EXCLUSIVE ownership.")
+          .short("s")
+          .takes_value(true)
+          .value_name("strength")
+          .allow_hyphen_values(true))
+        .arg(Arg::with_name("counter")
+          .help("Append a sample counter to ShapeType. Subscriber reports sample loss. Remote end must also use this.")
+          .long("counter"))
+        .arg(Arg::with_name("check_history")
+          .help("On late join, check that exactly the last N samples of each instance were delivered, where N is \
+                 the history depth. Needs -D l, -k N and --counter.")
+          .long("check-history")
+          .requires_all(&["subscriber", "counter", "history_depth"]))
+        .arg(Arg::with_name("verify_writer_history")
+          .help("Write M samples to each of two instances, then check which of them a late-joining reliable \
+                 reader gets, and report the effective writer history. Needs -D l and -k N. \
+                 Samples carry a counter, as with --counter.")
+          .long("verify-writer-history")
+          .value_name("M")
+          .takes_value(true)
+          .requires_all(&["publisher", "history_depth"]))
+        .arg(Arg::with_name("mutate_type")
+          .help("Publish an incompatible variant of ShapeType, for negative tests: \
+                 reordered (key last), retyped (64-bit x and y), no-key (color missing)")
+          .long("mutate-type")
+          .takes_value(true)
+          .value_name("variant")
+          .possible_values(TypeMutation::VARIANTS)
+          .requires("publisher")
+          .conflicts_with_all(&["counter", "ownership_failover"]))
+        .arg(Arg::with_name("key_fields")
+          .help("Key members of ShapeType. With color,shapesize shapes of different size are \
+                 different instances.")
+          .long("key-fields")
+          .takes_value(true)
+          .value_name("members")
+          .possible_values(&["color", "color,shapesize"])
+          .conflicts_with_all(&["counter", "mutate_type", "type_schema"]))
+        .arg(Arg::with_name("long_key")
+          .help("Publish colors longer than the 16 bytes of a key hash, and dispose them now and then. \
+                 Subscriber shows key hashes and checks that disposals match received instances.")
+          .long("long-key"))
+        .arg(Arg::with_name("drop_rate")
+          .help("Drop given percentage of writes at random")
+          .long("drop-rate")
+          .takes_value(true)
+          .value_name("pct")
+          .requires("publisher"))
+        .arg(Arg::with_name("delay_ms")
+          .help("Delay writes by given milliseconds, optionally with random jitter, e.g. 100/20")
+          .long("delay-ms")
+          .takes_value(true)
+          .value_name("ms[/jitter]")
+          .requires("publisher"))
+        .arg(Arg::with_name("skip_sequence")
+          .help("Do not write the samples with these counter values, e.g. 5,10-12. This makes holes in the \
+                 application counters only: it injects no RTPS faults, and sequence numbers stay contiguous.")
+          .long("skip-sequence")
+          .takes_value(true)
+          .value_name("list")
+          .requires_all(&["publisher", "counter"]))
+        .arg(Arg::with_name("ack_trace")
+          .help("Print how long each write took to be acknowledged by all matched reliable readers, checking \
+                 every 5 ms without blocking. RustDDS does not tell which readers acknowledged.")
+          .long("ack-trace")
+          .requires_all(&["publisher", "reliable"]))
+        .arg(Arg::with_name("writers")
+          .help("Number of DataWriters per topic, each publishing its own color")
+          .long("writers")
+          .takes_value(true)
+          .value_name("n")
+          .requires("publisher"))
+        .arg(Arg::with_name("stress_topics")
+          .help("Discovery stress: use n topics named <topic>_1 .. <topic>_n instead of the given topic. \
+                 Time until all endpoints have matched is reported.")
+          .long("stress-topics")
+          .takes_value(true)
+          .value_name("n"))
+        .arg(Arg::with_name("stress_instances")
+          .help("Instance stress: publish n instances with colors <color>_1 .. <color>_n from one DataWriter")
+          .long("stress-instances")
+          .takes_value(true)
+          .value_name("n")
+          .requires("publisher")
+          .conflicts_with_all(&["writers", "mutate_type", "ownership_failover"]))
+        .arg(Arg::with_name("readers")
+          .help("Number of DataReaders per topic. Samples are printed from the first one only, \
+                 and sample counts of all are compared.")
+          .long("readers")
+          .takes_value(true)
+          .value_name("n")
+          .requires("subscriber"))
+        .arg(Arg::with_name("batch")
+          .help("Accumulate samples and write them in batches of n samples, or nB bytes")
+          .long("batch")
+          .value_name("n|nB")
+          .takes_value(true)
+          .requires("publisher"))
+        .arg(Arg::with_name("source_timestamp_offset")
+          .help("Write samples with source timestamp shifted by this many seconds (negative = past)")
+          .long("source-timestamp-offset")
+          .value_name("secs")
+          .takes_value(true)
+          .allow_hyphen_values(true)
+          .requires("publisher"))
+        .arg(Arg::with_name("source_timestamp_mode")
+          .help("Shift every sample (fixed), or every other sample (alternate), to test \
+                 BY_SOURCE_TIMESTAMP destination order")
+          .long("source-timestamp-mode")
+          .value_name("mode")
+          .takes_value(true)
+          .possible_values(&["fixed", "alternate"])
+          .default_value("fixed"))
+        .arg(Arg::with_name("soak")
+          .help("Soak test: run for this many hours (0 = until stopped), recording memory and CPU use every minute")
+          .long("soak")
+          .value_name("hours")
+          .takes_value(true))
+        .arg(Arg::with_name("soak_max_growth")
+          .help("Fail the soak test if memory (RSS) grows faster than this")
+          .long("soak-max-growth")
+          .value_name("kB/h")
+          .takes_value(true)
+          .default_value("1024"))
+        .arg(Arg::with_name("input")
+          .help("Publish the samples listed in a CSV (color,x,y,size[,delay_ms]) or JSON file, instead of \
+                 moving shapes. Each is written by the first writer of each topic.")
+          .long("input")
+          .value_name("file")
+          .takes_value(true)
+          .requires("publisher"))
+        .arg(Arg::with_name("no_writer_filter")
+          .help("Disable writer-side content filtering for remote readers with a content filter, for A/B \
+                 comparison. The publisher reports how samples were filtered at exit.")
+          .long("no-writer-filter")
+          .requires("publisher"))
+        .arg(Arg::with_name("max_bandwidth")
+          .help("Pace writes to this bandwidth (kbit/s). Achieved rate is reported.")
+          .long("max-bandwidth")
+          .value_name("kbps")
+          .takes_value(true)
+          .requires("publisher"))
+        .arg(Arg::with_name("pattern")
+          .help("Trajectory of published shapes")
+          .long("pattern")
+          .value_name("pattern")
+          .takes_value(true)
+          .possible_values(Pattern::VARIANTS)
+          .requires("publisher"))
+        .arg(Arg::with_name("recreate_writer_every")
+          .help("Delete and recreate the DataWriter at this interval (seconds). Remote readers should re-match.")
+          .long("recreate-writer-every")
+          .takes_value(true)
+          .value_name("secs")
+          .requires("publisher"))
+        .arg(Arg::with_name("simulate_hang")
+          .help("Use MANUAL_BY_TOPIC liveliness, and after given seconds stop writing, and so asserting \
+                 liveliness, for duration seconds without unregistering. Remote readers should see \
+                 liveliness lost and recovered.")
+          .long("simulate-hang")
+          .takes_value(true)
+          .number_of_values(2)
+          .value_names(&["secs-after", "duration"])
+          .requires("publisher"))
+        .arg(Arg::with_name("restart_participant")
+          .help("Delete the whole DomainParticipant after given seconds, and create it again after \
+                 down seconds [default: 1]. Repeats. Times to re-match are reported.")
+          .long("restart-participant")
+          .takes_value(true)
+          .value_name("secs[/down]"))
+        .arg(Arg::with_name("on_error")
+          .help("What to do when creating a DDS entity or writing a sample fails. Failed creation \
+                 exits unless retried.")
+          .long("on-error")
+          .value_name("policy")
+          .takes_value(true)
+          .possible_values(OnError::VARIANTS)
+          .default_value("continue"))
+        .arg(Arg::with_name("participant_retries")
+          .help("Retry creating the DomainParticipant n times, doubling the wait each time, before the \
+                 --on-error policy applies. Every attempt is the same: there is no fallback to another participant \
+                 ID, since RustDDS does not let us choose one. It moves on to the next free ID by itself when ports \
+                 are taken.")
+          .long("participant-retries")
+          .value_name("n")
+          .takes_value(true))
+        .arg(Arg::with_name("trace_rtps")
+          .help("Log RTPS submessages (DATA, HEARTBEAT, ACKNACK, GAP) sent and received, for all topics of \
+                 this process. RustDDS logs endpoint GUIDs, not topic names, so the trace cannot be limited to \
+                 a topic.")
+          .long("trace-rtps"))
+        .arg(Arg::with_name("capture")
+          .help("Write RTPS traffic to a pcapng file. Only multicast traffic of the domain \
+                 (discovery and multicast user data) is visible to us.")
+          .long("capture")
+          .value_name("file.pcapng")
+          .takes_value(true))
+        .arg(Arg::with_name("timeline")
+          .help("Record discovery, entity creation, status changes, writes and receptions with monotonic \
+                 timestamps into a JSON file, for merging with the timeline of the remote side")
+          .long("timeline")
+          .value_name("file")
+          .takes_value(true))
+        .arg(Arg::with_name("protocol_stats")
+          .help("Count DATA, retransmissions, HEARTBEATs and ACKNACKs per writer in multicast user traffic, \
+                 periodically and at exit. Unicast traffic is not visible to us.")
+          .long("protocol-stats"))
+        .arg(Arg::with_name("dump_cdr")
+          .help("Hex-dump the serialized payload, with encapsulation header, of the first n samples written \
+                 by each writer, or received in multicast user traffic. Unicast traffic is not visible to us.")
+          .long("dump-cdr")
+          .value_name("n")
+          .takes_value(true))
+        .arg(Arg::with_name("compat_output")
+          .help("Print samples and status events like the reference shape applications of the OMG interop \
+                 test suite, for its verification scripts")
+          .long("compat-output")
+          .conflicts_with("log_format"))
+        .arg(Arg::with_name("no_ctrlc")
+          .help("Do not install a Ctrl-C handler, e.g. under a process manager. SIGTERM still stops gracefully.")
+          .long("no-ctrlc"))
+        .arg(Arg::with_name("log_format")
+          .help("Format of status event output")
+          .long("log-format")
+          .value_name("format")
+          .takes_value(true)
+          .possible_values(&["text", "json"]))
+        .arg(Arg::with_name("ownership_failover")
+          .help("Publish with two EXCLUSIVE ownership writers, and delete the stronger one \
+                 after given number of samples. Shape size is set to writer strength. \
+                 Default: strengths=10,20 switch-after=50")
+          .long("ownership-failover")
+          .value_name("option")
+          .takes_value(true)
+          .min_values(0)
+          .max_values(2)
+          .requires("publisher")
+          .conflicts_with("ownership_strength"))
+        .arg(Arg::with_name("type_schema")
+          .help("Generic mode: use the data type described in a JSON schema file instead of ShapeType")
+          .long("type-schema")
+          .takes_value(true)
+          .value_name("file")
+          .conflicts_with_all(&["counter", "mutate_type", "ownership_failover", "compare_qos", "expect"]))
+        .arg(Arg::with_name("idl")
+          .help("Generic mode: use a struct type defined in an IDL file instead of ShapeType. \
+                 Members must be of primitive or string types.")
+          .long("idl")
+          .takes_value(true)
+          .value_name("file")
+          .conflicts_with_all(&["type_schema", "counter", "mutate_type", "ownership_failover", "compare_qos",
+            "expect"]))
+        .arg(Arg::with_name("idl_type")
+          .help("Name of the struct to use from the IDL file. Not needed if there is only one.")
+          .long("idl-type")
+          .takes_value(true)
+          .value_name("name")
+          .requires("idl"))
+        .arg(Arg::with_name("samples")
+          .help("Generic mode: JSON file of samples to publish, in order and cycling")
+          .long("samples")
+          .takes_value(true)
+          .value_name("file")
+          .requires("publisher"))
+        .arg(Arg::with_name("locators")
+          .help("On match, print the locators remote participants have advertised in discovery")
+          .long("locators"))
+        .arg(Arg::with_name("vendors")
+          .help("Print vendor and RTPS protocol version of discovered participants, and at exit the vendors seen")
+          .long("vendors"))
+        .arg(Arg::with_name("wait_forever_for_match")
+          .help("Wait for matches indefinitely, printing every 5 s how many participants have been discovered \
+                 and the likely cause for each topic without a match")
+          .long("wait-forever-for-match"))
+        .arg(Arg::with_name("builtin_topics")
+          .help("Print discovered participants and topics as JSON records when they appear, change or go away")
+          .long("builtin-topics"))
+        .arg(Arg::with_name("unsupported_qos")
+          .help("Print parameters in remote participant data that RustDDS ignores, and at exit a summary by vendor")
+          .long("unsupported-qos"))
+        .arg(Arg::with_name("compare_qos")
+          .help("Subscribe with two readers. Reader B has QoS changed as given, \
+                 e.g. history=1,reliability=best_effort,durability=l,deadline=0.5. \
+                 Readers are drained once per second and the counts compared.")
+          .long("compare-qos")
+          .value_name("changes")
+          .takes_value(true)
+          .requires("subscriber"))
+        .arg(Arg::with_name("dual_reliability")
+          .help("Subscribe with a RELIABLE reader A and a BEST_EFFORT reader B on the same topic, and report \
+                 the difference in received counts over time. Same as -r --compare-qos reliability=best_effort.")
+          .long("dual-reliability")
+          .requires("subscriber")
+          .conflicts_with_all(&["compare_qos", "best_effort"]))
+        .arg(Arg::with_name("coherent_group")
+          .help("Subscribe to two topics (-t A,B) under one Subscriber with GROUP coherent access, \
+                 and report when only part of a coherent set is visible.")
+          .long("coherent-group")
+          .requires("subscriber")
+          .conflicts_with("compare_qos"))
+        .arg(Arg::with_name("expect")
+          .help("Subscriber fails if a received sample violates these, \
+                 e.g. color=RED,size=30,xmin=0,xmax=120,ymin=0,ymax=250")
+          .long("expect")
+          .value_name("assertions")
+          .takes_value(true))
+        .arg(Arg::with_name("filter")
+          .help("Content filter, e.g. \"x > 100 AND color = 'BLUE'\" or \"x BETWEEN %0 AND %1\". \
+                 RustDDS filters at the reader; rejected samples are counted.")
+          .long("filter")
+          .value_name("expression")
+          .takes_value(true)
+          .requires("subscriber")
+          .conflicts_with("type_schema"))
+        .arg(Arg::with_name("filter_parameters")
+          .help("Values of filter parameters %0, %1, ... Strings in single quotes.")
+          .long("filter-parameters")
+          .value_name("values")
+          .takes_value(true)
+          .use_delimiter(true)
+          .requires("filter"))
+        .arg(Arg::with_name("follow_owner")
+          .help("Report which remote writer each instance is received from, whenever that changes")
+          .long("follow-owner")
+          .requires("subscriber"))
+        .arg(Arg::with_name("visual")
+          .help("Draw received shapes in the terminal instead of printing samples")
+          .long("visual")
+          .requires("subscriber"))
+        .arg(Arg::with_name("latency")
+          .help("Measure one-way latency from the source timestamps of received samples, and report it at exit")
+          .long("latency")
+          .requires("subscriber"))
+        .arg(Arg::with_name("clock_offset")
+          .help("Correct one-way latency by how much the remote clock is ahead of ours, in microseconds, \
+                 or read from a file whose first word is that, e.g. as measured with PTP or NTP")
+          .long("clock-offset")
+          .value_name("us|file")
+          .takes_value(true)
+          .requires("latency"))
+        .arg(Arg::with_name("print_every")
+          .help("Print only every nth received sample. A received sample rate is reported periodically.")
+          .long("print-every")
+          .takes_value(true)
+          .value_name("n")
+          .requires("subscriber"))
+        .arg(Arg::with_name("quiet_samples")
+          .help("Do not print received samples, only status and summary output")
+          .long("quiet-samples")
+          .requires("subscriber")
+          .conflicts_with("print_every"))
+        .arg(Arg::with_name("script")
+          .help("Run timed actions from a script file, e.g. lines like 'at 5s dispose RED'")
+          .long("script")
+          .value_name("file")
+          .takes_value(true))
+        .arg(Arg::with_name("log_level")
+          .help("Override log level. Applies to modules given with --log-module, or globally.")
+          .long("log-level")
+          .value_name("level")
+          .takes_value(true)
+          .possible_values(&["off", "error", "warn", "info", "debug", "trace"]))
+        .arg(Arg::with_name("log_module")
+          .help("Module(s) to apply --log-level to, e.g. rustdds::rtps")
+          .long("log-module")
+          .value_name("module")
+          .takes_value(true)
+          .multiple(true)
+          .number_of_values(1)
+          .requires("log_level"))
+        .arg(Arg::with_name("log_file")
+          .help("Also write log to a file. The file is rotated when it grows large.")
+          .long("log-file")
+          .value_name("file")
+          .takes_value(true))
+}
+
+/// Runs what the command line asks for. Returns the process exit code.
+pub fn run(matches: &ArgMatches) -> i32 {
+  // Before logging setup, so that the output is only JSON
+  if matches.is_present("capabilities") {
+    println!("{}", serde_json::to_string_pretty(&capabilities::capabilities()).unwrap());
+    return 0
+  }
+
+  // initialize logging, preferably from config file
+  let log_level = matches.value_of("log_level").map( |level| level.parse::<LevelFilter>()
+    .unwrap_or_else(|_| panic!("Unknown log level {}", level)) );
+  let log_modules: Vec<String> = matches.values_of("log_module").map_or(Vec::new(), |v| v.map(String::from).collect());
+  logging::init_from_command_line(log_level, log_modules, matches.value_of("log_file"),
+    matches.is_present("trace_rtps"));
+
+  if let Some(matrix_matches) = matches.subcommand_matches("matrix") {
+    return matrix::run_matrix(matrix_matches.value_of("spec").unwrap())
+  }
+  if let Some(selftest_matches) = matches.subcommand_matches("selftest") {
+    let seconds = selftest_matches.value_of("seconds").unwrap();
+    let seconds = match seconds.parse::<f64>() {
+      Ok(s) if s > 0.0 => s,
+      _ => panic!("Expected positive numeric value for --seconds, got \"{}\"", seconds),
+    };
+    let options: Vec<String> = selftest_matches.values_of("options").into_iter().flatten().map(String::from).collect();
+    return selftest::run_selftest(selftest_matches.value_of("topic").unwrap(),
+      selftest_matches.value_of("domain_id").unwrap(), seconds, &options)
+  }
+  if matches.subcommand_matches("scenarios").is_some() {
+    scenario::print_scenarios();
+    return 0
+  }
+  if let Some(scenario_matches) = matches.subcommand_matches("run-scenario") {
+    return run_scenario(scenario_matches)
+  }
+  run_config(parse(matches))
+}
+
+/// Runs the test, and reports a setup error with EXIT_SETUP_FAILED.
+fn run_config(config: Config) -> i32 {
+  match crate::run(config) {
+    Ok(code) => code,
+    Err(e) => {
+      println!("{}", e);
+      EXIT_SETUP_FAILED
+    }
+  }
+}
+
+/// Runs a scenario role as if its options were given on the command line.
+/// Logging is already set up from the options before the subcommand.
+fn run_scenario(matches: &ArgMatches) -> i32 {
+  let options: Vec<String> = matches.values_of("options").into_iter().flatten().map(String::from).collect();
+  let found = scenario::find(matches.value_of("name").unwrap()).and_then( |scenario| scenario
+    .command_args(matches.is_present("publisher"), matches.value_of("topic").unwrap(),
+      matches.value_of("domain_id").unwrap(), &options)
+    .map( |args| (scenario, args) ) );
+  let (scenario, args) = match found {
+    Ok(found) => found,
+    Err(e) => {
+      println!("{}", e);
+      return 1
+    }
+  };
+  println!("Scenario {}: {}", scenario.name, scenario.description);
+  println!("Options: {}", args.join(" "));
+  let role_matches = app().get_matches_from_safe(std::iter::once("rustdds_interop_test".to_string()).chain(args))
+    .unwrap_or_else( |e| e.exit() );
+  run_config(parse(&role_matches))
+}
+
+fn topic_names(matches: &ArgMatches) -> Vec<String> {
+  let topic_names: Vec<String> = matches.value_of("topic").unwrap_or("Square")
+    .split(',').map(str::trim).filter( |t| !t.is_empty() ).map(String::from).collect();
+  match matches.value_of("stress_topics") {
+    None => topic_names,
+    Some(n) => match n.parse::<usize>() {
+      Ok(n) if n > 0 =>
+        topic_names.iter().flat_map( |t| (1..=n).map( move |i| format!("{}_{}", t, i) ) ).collect(),
+      _ => panic!("Expected positive numeric value for topic count, got \"{}\"", n),
+    }
+  }
+}
+
+/// The -c color, normalized to the uppercase Shapes demo color
+fn color(matches: &ArgMatches) -> String {
+  let color = matches.value_of("color").unwrap_or("BLUE");
+  shape_color(color, matches.is_present("any_color")).unwrap_or_else(|e| panic!("Bad -c: {}", e))
+}
+
+fn log_format(matches: &ArgMatches) -> LogFormat {
+  if matches.is_present("compat_output") {
+    LogFormat::Compat
+  } else {
+    LogFormat::from_arg(matches.value_of("log_format"))
+  }
+}
+
+/// Parses the command line, other than subcommands and logging, into a Config.
+/// Panics on bad values and options RustDDS does not support.
+pub fn parse(matches: &ArgMatches) -> Config {
+  check_unsupported(matches);
+  let soak = matches.value_of("soak").map( |h| {
+    let growth = matches.value_of("soak_max_growth").unwrap();
+    match (h.parse::<f64>(), growth.parse::<f64>()) {
+      (Ok(hours), Ok(kb)) if hours >= 0.0 && kb > 0.0 => (hours, kb),
+      _ => panic!("Expected non-negative soak hours and positive growth limit, got \"{}\" and \"{}\"", h, growth),
+    }
+  });
+  let dump_cdr = matches.value_of("dump_cdr").map( |n| n.parse::<usize>()
+    .unwrap_or_else(|_| panic!("Expected number of samples for --dump-cdr, got \"{}\"", n)) );
+  let mut publisher = publisher_options(matches);
+  publisher.soak = soak;
+  publisher.dump_cdr = dump_cdr.unwrap_or(0);
+  let mut subscriber = subscriber_options(matches);
+  subscriber.soak = soak;
+  Config {
+    domain_id: matches.value_of("domain_id").unwrap_or("0").parse::<u16>().unwrap_or(0),
+    topic_names: topic_names(matches),
+    color: color(matches),
+    any_color: matches.is_present("any_color"),
+    mode: mode(matches),
+    qos: qos_config(matches),
+    counter: matches.is_present("counter"),
+    color_size_key: matches.value_of("key_fields") == Some("color,shapesize"),
+    mutation: matches.value_of("mutate_type")
+      .map( |m| TypeMutation::parse(m).unwrap_or_else(|e| panic!("Bad --mutate-type: {}", e)) ),
+    publisher,
+    subscriber,
+    log_format: log_format(matches),
+    on_error: OnError::parse(matches.value_of("on_error").unwrap()).unwrap_or_else(|e| panic!("{}", e)),
+    participant_retries: match matches.value_of("participant_retries").map( |n| n.parse::<usize>() ) {
+      None => 0,
+      Some(Ok(n)) => n,
+      Some(Err(_)) => panic!("Expected number of retries for --participant-retries, got \"{}\"",
+        matches.value_of("participant_retries").unwrap()),
+    },
+    restart: matches.value_of("restart_participant").map( |r| {
+      let secs = |x: &str| match x.parse::<f64>() {
+        Ok(s) if s > 0.0 => Duration::from_secs_f64(s),
+        _ => panic!("Expected positive numeric value for restart time, got \"{}\"", x),
+      };
+      match r.split_once('/') {
+        Some((up, down)) => (secs(up), secs(down)),
+        None => (secs(r), Duration::from_secs(1)),
+      }
+    }),
+    script: matches.value_of("script")
+      .map( |f| script::load_script(f, matches.is_present("any_color")).unwrap_or_else(|e| panic!("{}", e)) ),
+    ctrlc: !matches.is_present("no_ctrlc"),
+    diagnostics: Diagnostics {
+      timeline: matches.value_of("timeline").map(String::from),
+      capture: matches.value_of("capture").map(String::from),
+      protocol_stats: matches.is_present("protocol_stats"),
+      dump_incoming_cdr: dump_cdr.filter( |_| matches.is_present("subscriber") ),
+      locators: matches.is_present("locators"),
+      vendors: matches.is_present("vendors"),
+      unsupported_qos: matches.is_present("unsupported_qos"),
+    },
+  }
+}
+
+fn check_unsupported(matches: &ArgMatches) {
+  for (option, _, policy) in UNSUPPORTED_QOS_OPTIONS {
+    if matches.is_present(option) {
+      panic!("QoS policy {} is not yet implemented.", policy)
+    }
+  }
+  if matches.is_present("subscriber") {
+    for (option, _, policy) in WRITER_ONLY_QOS_OPTIONS {
+      if matches.is_present(option) {
+        panic!("QoS policy {} is not yet implemented in RustDDS DataReaders.", policy)
+      }
+    }
+  }
+}
+
+fn qos_config(matches: &ArgMatches) -> QosConfig {
+  QosConfig {
+    reliable: matches.is_present("reliable") || matches.is_present("dual_reliability"),
+    durability: durability_from_letter(matches.value_of("durability")),
+    history_depth: matches.value_of("history_depth").and_then( |d| d.parse::<i32>().ok() ),
+    deadline: matches.value_of("deadline").map( |dl| match dl.parse::<f64>() {
+      Ok(d) => d,
+      Err(e) => panic!("Expected numeric value for deadline. {:?}",e),
+    }),
+    manual_liveliness: matches.is_present("simulate_hang"),
+    ownership_strength: match matches.value_of("ownership_strength").map( |s| s.parse::<i32>() ) {
+      None | Some(Ok(-1)) => None,
+      Some(Ok(s)) => Some(s),
+      Some(Err(_)) => panic!("Expected numeric value for ownership strength, got \"{}\"",
+        matches.value_of("ownership_strength").unwrap()),
+    },
+  }
+}
+
+fn mode(matches: &ArgMatches) -> Mode {
+  let type_schema = matches.value_of("type_schema")
+    .map( |f| generic::load_schema(f).unwrap_or_else(|e| panic!("Bad --type-schema: {}", e)) )
+    .or_else( || matches.value_of("idl").map( |f| idl::load_idl_type(f, matches.value_of("idl_type"))
+      .unwrap_or_else(|e| panic!("Bad --idl: {}", e)) ) )
+    .map( |s| generic::set_schema(s).unwrap_or_else(|e| panic!("{}", e)) );
+  if type_schema.is_none() && matches.is_present("samples") {
+    panic!("--samples needs --type-schema or --idl")
+  }
+  let compare_qos = if matches.is_present("dual_reliability") {
+    Some((compare::parse_qos_changes("reliability=best_effort").unwrap(), "--dual-reliability"))
+  } else {
+    matches.value_of("compare_qos")
+      .map( |c| (compare::parse_qos_changes(c).unwrap_or_else(|e| panic!("Bad --compare-qos: {}", e)),
+        "--compare-qos") )
+  };
+
+  let failover = matches.is_present("ownership_failover").then( || {
+    let options = matches.values_of("ownership_failover").into_iter().flatten();
+    failover::FailoverConfig::parse(options).unwrap_or_else(|e| panic!("Bad --ownership-failover: {}", e))
+  });
+  let writer_history = matches.value_of("verify_writer_history").map( |m| {
+    if matches.value_of("durability") != Some("l") {
+      panic!("--verify-writer-history needs TRANSIENT_LOCAL durability (-D l).")
+    }
+    let depth = match matches.value_of("history_depth").unwrap().parse::<u32>() {
+      Ok(d) if d > 0 => d,
+      _ => panic!("--verify-writer-history needs KeepLast history with positive depth."),
+    };
+    match m.parse::<u32>() {
+      Ok(samples_per_instance) if samples_per_instance > 0 =>
+        writer_history::WriterHistoryConfig { samples_per_instance, depth },
+      _ => panic!("Expected positive number of samples for --verify-writer-history, got \"{}\"", m),
+    }
+  });
+  let stress_instances = matches.value_of("stress_instances").map( |n| match n.parse::<usize>() {
+    Ok(n) if n > 0 => n,
+    _ => panic!("Expected positive numeric value for instance count, got \"{}\"", n),
+  });
+
+  if matches.is_present("watch_topics") {
+    Mode::WatchTopics
+  } else if let Some(schema) = type_schema {
+    if matches.is_present("publisher") {
+      let samples = matches.value_of("samples")
+        .map( |f| generic::load_samples(f).unwrap_or_else(|e| panic!("Bad --samples: {}", e)) )
+        .unwrap_or_else( || panic!("Generic publisher needs --samples") );
+      Mode::GenericPublisher { schema, samples }
+    } else if matches.is_present("subscriber") {
+      Mode::GenericSubscriber { schema }
+    } else {
+      Mode::Idle
+    }
+  } else if let Some((changes, option)) = compare_qos {
+    Mode::Compare { changes, option }
+  } else if matches.is_present("coherent_group") {
+    Mode::CoherentGroup
+  } else if let Some(failover) = failover {
+    Mode::OwnershipFailover(failover)
+  } else if let Some(config) = writer_history {
+    Mode::WriterHistory(config)
+  } else if let Some(instances) = stress_instances {
+    Mode::InstanceStress { instances }
+  } else if matches.is_present("publisher") {
+    Mode::Publisher
+  } else if matches.is_present("subscriber") {
+    Mode::Subscriber
+  } else {
+    Mode::Idle
+  }
+}
+
+fn publisher_options(matches: &ArgMatches) -> PublisherOptions {
+  let mut options = PublisherOptions::default();
+  if let Some(d) = matches.value_of("drop_rate") {
+    options.impairment.drop_rate = Impairment::parse_drop_rate(d)
+      .unwrap_or_else(|e| panic!("Bad --drop-rate: {}", e));
+  }
+  if let Some(d) = matches.value_of("delay_ms") {
+    let (delay, jitter) = Impairment::parse_delay(d).unwrap_or_else(|e| panic!("Bad --delay-ms: {}", e));
+    options.impairment.delay = delay;
+    options.impairment.jitter = jitter;
+  }
+  if let Some(list) = matches.value_of("skip_sequence") {
+    options.skip_counters = Impairment::parse_counter_list(list)
+      .unwrap_or_else(|e| panic!("Bad --skip-sequence: {}", e));
+  }
+  options.ack_trace = matches.is_present("ack_trace");
+  if let Some(n) = matches.value_of("writers") {
+    match n.parse::<usize>() {
+      Ok(n) if n > 0 => options.writers_per_topic = n,
+      _ => panic!("Expected positive numeric value for writer count, got \"{}\"", n),
+    }
+  }
+  options.long_key = matches.is_present("long_key");
+  if let Some(secs) = matches.value_of("recreate_writer_every") {
+    match secs.parse::<f64>() {
+      Ok(s) if s > 0.0 => options.recreate_writer_every = Some(Duration::from_secs_f64(s)),
+      _ => panic!("Expected positive numeric value for recreate interval, got \"{}\"", secs),
+    }
+  }
+  if let Some(values) = matches.values_of("simulate_hang") {
+    let secs: Vec<Duration> = values.map( |v| match v.parse::<f64>() {
+      Ok(s) if s >= 0.0 => Duration::from_secs_f64(s),
+      _ => panic!("Expected non-negative numeric values for --simulate-hang, got \"{}\"", v),
+    }).collect();
+    options.simulate_hang = Some((secs[0], secs[1]));
+  }
+  options.no_writer_filter = matches.is_present("no_writer_filter");
+  options.wait_for_match = matches.is_present("wait_forever_for_match");
+  options.builtin_topics = matches.is_present("builtin_topics");
+  options.input = matches.value_of("input")
+    .map( |f| input::load_input(f).unwrap_or_else(|e| panic!("Bad --input: {}", e)) );
+  options.batch = matches.value_of("batch")
+    .map( |b| BatchLimit::parse(b).unwrap_or_else(|e| panic!("Bad --batch: {}", e)) );
+  options.max_bandwidth = matches.value_of("max_bandwidth").map( |b| match b.parse::<f64>() {
+    Ok(kbps) if kbps > 0.0 => kbps,
+    _ => panic!("Expected positive numeric value for bandwidth, got \"{}\"", b),
+  });
+  options.source_timestamps = matches.value_of("source_timestamp_offset").map( |o| match o.parse::<f64>() {
+    Ok(offset) => SourceTimestamps::new(offset, matches.value_of("source_timestamp_mode").unwrap())
+      .unwrap_or_else(|e| panic!("{}", e)),
+    Err(_) => panic!("Expected numeric value for source timestamp offset, got \"{}\"", o),
+  });
+  if let Some(p) = matches.value_of("pattern") {
+    options.pattern = Pattern::parse(p).unwrap_or_else(|e| panic!("{}", e));
+  }
+  options
+}
+
+fn subscriber_options(matches: &ArgMatches) -> SubscriberOptions {
+  let expectation = matches.value_of("expect")
+    .map( |e| Expectation::parse(e).unwrap_or_else(|e| panic!("Bad --expect: {}", e)) );
+  let mut options = SubscriberOptions { expectation, ..SubscriberOptions::default() };
+  options.readers_per_topic = match matches.value_of("readers").map( |n| n.parse::<usize>() ) {
+    None => 1,
+    Some(Ok(n)) if n > 0 => n,
+    Some(_) => panic!("Expected positive numeric value for reader count, got \"{}\"",
+      matches.value_of("readers").unwrap()),
+  };
+  options.visual = matches.is_present("visual");
+  options.wait_for_match = matches.is_present("wait_forever_for_match");
+  options.builtin_topics = matches.is_present("builtin_topics");
+  if matches.is_present("latency") {
+    options.latency = Some(matches.value_of("clock_offset").map( |o|
+      latency::parse_clock_offset(o).unwrap_or_else(|e| panic!("Bad --clock-offset: {}", e)) ));
+  }
+  if let Some(n) = matches.value_of("print_every") {
+    options.print_every = match n.parse::<u64>() {
+      Ok(n) if n > 0 => n,
+      _ => panic!("Expected positive numeric value for --print-every, got \"{}\"", n),
+    };
+  }
+  if matches.is_present("quiet_samples") {
+    options.print_every = 0;
+  }
+  options.follow_owner = matches.is_present("follow_owner");
+  options.long_key = matches.is_present("long_key");
+  options.check_disposals = options.long_key || matches.is_present("key_fields");
+  if matches.is_present("check_history") {
+    if matches.value_of("durability") != Some("l") {
+      panic!("--check-history needs TRANSIENT_LOCAL durability (-D l).")
+    }
+    options.history_check = match matches.value_of("history_depth").unwrap().parse::<usize>() {
+      Ok(d) if d > 0 => Some(d),
+      _ => panic!("--check-history needs KeepLast history with positive depth."),
+    };
+  }
+  options.filter = matches.value_of("filter").map( |f| {
+    let parameters: Vec<String> = matches.values_of("filter_parameters").into_iter().flatten()
+      .map(String::from).collect();
+    Filter::parse(f, &parameters).unwrap_or_else(|e| panic!("Bad --filter: {}", e))
+  });
+  options
+}
@@ -0,0 +1,223 @@
+/// Group coherent subscriber check: two DataReaders on different topics under
+/// one Subscriber with Presentation access scope GROUP and coherent access.
+///
+/// A remote publisher with GROUP coherent access writes sets of changes that
+/// span both topics, e.g. one update of each instance on each topic. A
+/// coherent subscriber should make a set available only when all of it has
+/// arrived. Samples are counted as they arrive, and once both readers have
+/// been quiet for SETTLE_TIME, each instance should have the same count on
+/// both topics. A difference is reported as a violation. A set may arrive in
+/// several reader events, so counts are not compared at each event. Counts
+/// start over at each check, so one violation is not reported again at every
+/// later check.
+///
+/// RustDDS does not implement coherent sets, so our readers deliver samples
+/// as they arrive. The check shows whether that breaks group coherence in
+/// practice.
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use log::debug;
+use mio::{Events, PollOpt, Ready, Token};
+use rustdds::dds::qos::policy::{Presentation, PresentationAccessScope};
+use rustdds::dds::qos::{QosPolicies, QosPolicyBuilder};
+use rustdds::dds::statusevents::StatusEvented;
+use rustdds::dds::traits::TopicDescription;
+use rustdds::dds::{DomainParticipant, Topic};
+
+use crate::console::Command;
+use crate::status_report::{self, LogFormat};
+use crate::{Control, ShapeData, COMMAND_READY, STOP_PROGRAM};
+
+const READER_A_READY: Token = Token(22);
+const READER_B_READY: Token = Token(23);
+const STATUS_A_READY: Token = Token(24);
+const STATUS_B_READY: Token = Token(25);
+
+/// How long both readers must be quiet before counts are compared. This is
+/// well below the default write interval, so that checks fall between sets.
+const SETTLE_TIME: Duration = Duration::from_millis(50);
+
+#[derive(Default)]
+struct Totals {
+  coherent: u64,
+  violations: u64,
+}
+
+impl Totals {
+  fn print(&self) {
+    println!("Coherent group check: {} coherent reads, {} violations", self.coherent, self.violations);
+  }
+}
+
+/// Samples taken since the last check
+#[derive(Default)]
+struct PendingCounts {
+  /// color -> samples taken on [topic A, topic B]
+  counts: BTreeMap<String, [u64; 2]>,
+}
+
+impl PendingCounts {
+  fn record(&mut self, side: usize, color: &str) {
+    self.counts.entry(color.to_string()).or_default()[side] += 1;
+  }
+
+  fn is_empty(&self) -> bool {
+    self.counts.is_empty()
+  }
+
+  /// Returns the colors whose counts differ between the topics, and starts over.
+  fn check(&mut self) -> Vec<(String, [u64; 2])> {
+    std::mem::take(&mut self.counts).into_iter().filter( |(_, [a, b])| a != b ).collect()
+  }
+}
+
+pub fn run_coherent_group_subscriber<T: ShapeData>(domain_participant: &DomainParticipant, topic_a: Topic,
+    topic_b: Topic, qos: &QosPolicies, log_format: LogFormat, control: &Control) -> i32
+{
+  debug!("Group coherent subscriber");
+  let presentation = Presentation {
+    access_scope: PresentationAccessScope::Group,
+    coherent_access: true,
+    ordered_access: false,
+  };
+  let qos = qos.modify_by(&QosPolicyBuilder::new().presentation(presentation).build());
+  println!("Subscriber QoS: {:?}", qos);
+  let names = [topic_a.get_name().to_string(), topic_b.get_name().to_string()];
+  let mut events = Events::with_capacity(4);
+  let subscriber = match control.on_error.create("Subscriber", || domain_participant.create_subscriber(&qos)) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let mut reader_a = match control.on_error.create("DataReader", ||
+    subscriber.create_datareader_CDR::<T>(topic_a.clone(), Some(qos.clone()))) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let mut reader_b = match control.on_error.create("DataReader", ||
+    subscriber.create_datareader_CDR::<T>(topic_b.clone(), Some(qos.clone()))) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  control.poll.register(&reader_a, READER_A_READY, Ready::readable(), PollOpt::edge())
+    .unwrap();
+  control.poll.register(&reader_b, READER_B_READY, Ready::readable(), PollOpt::edge())
+    .unwrap();
+  control.poll.register(reader_a.as_status_evented(), STATUS_A_READY, Ready::readable(), PollOpt::edge())
+    .unwrap();
+  control.poll.register(reader_b.as_status_evented(), STATUS_B_READY, Ready::readable(), PollOpt::edge())
+    .unwrap();
+
+  let mut totals = Totals::default();
+  let mut pending = PendingCounts::default();
+  let mut check_at: Option<Instant> = None;
+  loop {
+    control.poll
+      .poll(&mut events, check_at.map( |t| t.saturating_duration_since(Instant::now()) ))
+      .unwrap();
+    let mut data_arrived = false;
+    for event in &events {
+      match event.token() {
+        STOP_PROGRAM => {
+          if control.stop_requested() {
+            totals.print();
+            println!("Done.");
+            return 0
+          }
+        }
+        COMMAND_READY => {
+          while let Ok(command) = control.command_receiver.try_recv() {
+            match command {
+              Command::Exit(code) => {
+                totals.print();
+                println!("Done.");
+                return code
+              }
+              Command::Snapshot => totals.print(),
+              other => status_report::report_unsupported_command(log_format, "group coherent", &other),
+            }
+          }
+        }
+        READER_A_READY | READER_B_READY => data_arrived = true,
+        STATUS_A_READY => {
+          while let Some(status) = reader_a.try_recv_status() {
+            status_report::report_reader_status(log_format, &names[0], &status);
+          }
+        }
+        STATUS_B_READY => {
+          while let Some(status) = reader_b.try_recv_status() {
+            status_report::report_reader_status(log_format, &names[1], &status);
+          }
+        }
+        other_token => {
+          println!("Polled event is {:?}. WTF?", other_token);
+        }
+      }
+    }
+    if data_arrived {
+      let mut record = |side: usize, sample: Result<T, T::K>| {
+        if let Ok(sample) = sample {
+          let s = sample.shape();
+          println!("{:10.10} {:10.10} {:3.3} {:3.3} [{}]", names[side], s.color, s.x, s.y, s.shapesize);
+          pending.record(side, &s.color);
+        }
+      };
+      while let Ok(Some(sample)) = reader_a.take_next_sample() {
+        record(0, sample.into_value());
+      }
+      while let Ok(Some(sample)) = reader_b.take_next_sample() {
+        record(1, sample.into_value());
+      }
+      if !pending.is_empty() {
+        check_at = Some(Instant::now() + SETTLE_TIME);
+      }
+      continue
+    }
+    match check_at {
+      Some(t) if Instant::now() >= t => check_at = None,
+      _ => continue,
+    }
+
+    // Both readers are quiet, so whatever of a coherent set was going to be
+    // visible now is.
+    let incoherent: Vec<String> = pending.check().iter()
+      .map(|(color, [a, b])| format!("{} ({} {}, {} {})", color, names[0], a, names[1], b))
+      .collect();
+    if incoherent.is_empty() {
+      totals.coherent += 1;
+    } else {
+      totals.violations += 1;
+      println!("Group coherence violation: partial set visible for {}", incoherent.join(", "));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn sets_split_across_events() {
+    let mut pending = PendingCounts::default();
+    // One set of RED and BLUE on both topics, arriving as two reader events
+    pending.record(0, "RED");
+    pending.record(0, "BLUE");
+    pending.record(1, "RED");
+    pending.record(1, "BLUE");
+    pending.record(1, "RED");
+    pending.record(0, "RED");
+    assert!(!pending.is_empty());
+    assert_eq!(pending.check(), vec![]);
+    assert!(pending.is_empty());
+
+    // Partial set: only topic A got its sample of RED
+    pending.record(0, "RED");
+    pending.record(0, "BLUE");
+    pending.record(1, "BLUE");
+    assert_eq!(pending.check(), vec![("RED".to_string(), [1, 0])]);
+    // Counts start over, so the violation is not reported again.
+    pending.record(0, "RED");
+    pending.record(1, "RED");
+    assert_eq!(pending.check(), vec![]);
+  }
+}
@@ -0,0 +1,156 @@
+/// Subscriber comparison mode: two DataReaders on the same topic with different
+/// QoS, drained at the same time, to show how the QoS difference affects what
+/// is received.
+///
+/// Readers are drained periodically rather than immediately on data arrival, so
+/// that history depth has a chance to make a difference.
+use std::time::{Duration, Instant};
+
+use log::debug;
+use mio::{Events, PollOpt, Ready, Token};
+use rustdds::dds::data_types::DDSDuration;
+use rustdds::dds::qos::policy::{Deadline, Durability, History, Reliability};
+use rustdds::dds::qos::{QosPolicies, QosPolicyBuilder};
+use rustdds::dds::statusevents::StatusEvented;
+use rustdds::dds::traits::TopicDescription;
+use rustdds::dds::{DomainParticipant, Topic};
+
+use crate::console::Command;
+use crate::status_report::{self, LogFormat};
+use crate::{Control, ShapeData, COMMAND_READY, STOP_PROGRAM};
+
+const READ_INTERVAL: Duration = Duration::from_secs(1);
+const STATUS_A_READY: Token = Token(20);
+const STATUS_B_READY: Token = Token(21);
+
+/// Parses QoS changes like "history=1,reliability=best_effort,durability=l,deadline=0.5".
+/// History depth -1 means KeepAll.
+pub fn parse_qos_changes(spec: &str) -> Result<QosPolicies, String> {
+  let mut b = QosPolicyBuilder::new();
+  for item in spec.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+    b = match item.split_once('=') {
+      Some(("reliability", "reliable")) =>
+        b.reliability(Reliability::Reliable { max_blocking_time: DDSDuration::DURATION_ZERO }),
+      Some(("reliability", "best_effort")) => b.reliability(Reliability::BestEffort),
+      Some(("durability", "v")) => b.durability(Durability::Volatile),
+      Some(("durability", "l")) => b.durability(Durability::TransientLocal),
+      Some(("durability", "t")) => b.durability(Durability::Transient),
+      Some(("durability", "p")) => b.durability(Durability::Persistent),
+      Some(("history", d)) => match d.parse::<i32>() {
+        Ok(d) if d < 0 => b.history(History::KeepAll),
+        Ok(d) => b.history(History::KeepLast { depth: d }),
+        Err(_) => return Err(format!("Bad history depth '{}'", d)),
+      },
+      Some(("deadline", d)) => match d.parse::<f64>() {
+        Ok(d) => b.deadline(Deadline(DDSDuration::from_frac_seconds(d))),
+        Err(_) => return Err(format!("Bad deadline '{}'", d)),
+      },
+      _ => return Err(format!("Unknown QoS setting '{}'", item)),
+    }
+  }
+  Ok(b.build())
+}
+
+fn print_totals(total_a: u64, total_b: u64) {
+  println!("Total: A {}, B {}, delta (B-A) {:+}", total_a, total_b, total_b as i64 - total_a as i64);
+}
+
+pub fn run_comparison<T: ShapeData>(domain_participant: &DomainParticipant, topic: Topic, qos: &QosPolicies,
+    qos_changes: &QosPolicies, log_format: LogFormat, control: &Control) -> i32
+{
+  debug!("Comparison subscriber");
+  let topic_name = topic.get_name().to_string();
+  let qos_b = qos.modify_by(qos_changes);
+  println!("Reader A QoS: {:?}", qos);
+  println!("Reader B QoS: {:?}", qos_b);
+  let mut events = Events::with_capacity(4);
+  let subscriber = match control.on_error.create("Subscriber", || domain_participant.create_subscriber(qos)) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let mut reader_a = match control.on_error.create("DataReader", ||
+    subscriber.create_datareader_CDR::<T>(topic.clone(), Some(qos.clone()))) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let mut reader_b = match control.on_error.create("DataReader", ||
+    subscriber.create_datareader_CDR::<T>(topic.clone(), Some(qos_b.clone()))) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  control.poll.register(reader_a.as_status_evented(), STATUS_A_READY, Ready::readable(), PollOpt::edge())
+    .unwrap();
+  control.poll.register(reader_b.as_status_evented(), STATUS_B_READY, Ready::readable(), PollOpt::edge())
+    .unwrap();
+
+  let (mut total_a, mut total_b) = (0u64, 0u64);
+  let mut next_read = Instant::now() + READ_INTERVAL;
+  loop {
+    control.poll
+      .poll(&mut events, Some(next_read.saturating_duration_since(Instant::now())))
+      .unwrap();
+    for event in &events {
+      match event.token() {
+        STOP_PROGRAM => {
+          if control.stop_requested() {
+            print_totals(total_a, total_b);
+            println!("Done.");
+            return 0
+          }
+        }
+        COMMAND_READY => {
+          while let Ok(command) = control.command_receiver.try_recv() {
+            match command {
+              Command::Exit(code) => {
+                print_totals(total_a, total_b);
+                println!("Done.");
+                return code
+              }
+              other => status_report::report_unsupported_command(log_format, "comparison", &other),
+            }
+          }
+        }
+        STATUS_A_READY => {
+          while let Some(status) = reader_a.try_recv_status() {
+            status_report::report_named_reader_status(log_format, "DataReader A", &topic_name, &status);
+          }
+        }
+        STATUS_B_READY => {
+          while let Some(status) = reader_b.try_recv_status() {
+            status_report::report_named_reader_status(log_format, "DataReader B", &topic_name, &status);
+          }
+        }
+        other_token => {
+          println!("Polled event is {:?}. WTF?", other_token);
+        }
+      }
+    }
+
+    if Instant::now() < next_read {
+      continue
+    }
+    next_read += READ_INTERVAL;
+
+    let mut count_a = 0u64;
+    while let Ok(Some(_)) = reader_a.take_next_sample() {
+      count_a += 1;
+    }
+    let mut count_b = 0u64;
+    while let Ok(Some(_)) = reader_b.take_next_sample() {
+      count_b += 1;
+    }
+    total_a += count_a;
+    total_b += count_b;
+    if count_a != 0 || count_b != 0 {
+      println!(
+        "Read: A {}, B {}, delta (B-A) {:+}. Total: A {}, B {}, delta {:+}",
+        count_a,
+        count_b,
+        count_b as i64 - count_a as i64,
+        total_a,
+        total_b,
+        total_b as i64 - total_a as i64
+      );
+    }
+  }
+}
@@ -0,0 +1,126 @@
+/// Typed configuration of a test run. cli fills this in from the command line,
+/// and other harnesses can build one directly and pass it to run().
+use std::time::Duration;
+
+use rustdds::dds::data_types::TopicKind;
+use rustdds::dds::qos::QosPolicies;
+
+use crate::failover::FailoverConfig;
+use crate::generic::{DynamicSample, TypeSchema};
+use crate::mutate::TypeMutation;
+use crate::on_error::OnError;
+use crate::publisher::PublisherOptions;
+use crate::qos::QosConfig;
+use crate::script::ScriptAction;
+use crate::status_report::LogFormat;
+use crate::subscriber::SubscriberOptions;
+use crate::writer_history::WriterHistoryConfig;
+
+#[derive(Clone)]
+pub struct Config {
+  pub domain_id: u16,
+  pub topic_names: Vec<String>,
+  /// Color to publish
+  pub color: String,
+  /// Take console and script colors as given, not only the Shapes demo colors
+  pub any_color: bool,
+  pub mode: Mode,
+  pub qos: QosConfig,
+  /// Publish and subscribe CountedShape instead of ShapeType
+  pub counter: bool,
+  /// Key on color and shapesize instead of color only
+  pub color_size_key: bool,
+  /// Publish a mutated ShapeType
+  pub mutation: Option<TypeMutation>,
+  pub publisher: PublisherOptions,
+  pub subscriber: SubscriberOptions,
+  pub log_format: LogFormat,
+  pub on_error: OnError,
+  /// DomainParticipant creation attempts after the first one fails
+  pub participant_retries: usize,
+  /// (up, down) times to delete and create the DomainParticipant again
+  pub restart: Option<(Duration, Duration)>,
+  pub script: Option<Vec<ScriptAction>>,
+  /// Stop on Ctrl-C
+  pub ctrlc: bool,
+  pub diagnostics: Diagnostics,
+}
+
+impl Default for Config {
+  fn default() -> Config {
+    Config {
+      domain_id: 0,
+      topic_names: vec!["Square".to_string()],
+      color: "BLUE".to_string(),
+      any_color: false,
+      mode: Mode::Idle,
+      qos: QosConfig::default(),
+      counter: false,
+      color_size_key: false,
+      mutation: None,
+      publisher: PublisherOptions::default(),
+      subscriber: SubscriberOptions::default(),
+      log_format: LogFormat::Text,
+      on_error: OnError::Continue,
+      participant_retries: 0,
+      restart: None,
+      script: None,
+      ctrlc: true,
+      diagnostics: Diagnostics::default(),
+    }
+  }
+}
+
+/// What to run. The test modes other than plain Publisher and Subscriber
+/// create their own entities.
+#[derive(Clone)]
+pub enum Mode {
+  Publisher,
+  Subscriber,
+  /// Create the DomainParticipant and topics only
+  Idle,
+  WatchTopics,
+  GenericPublisher { schema: &'static TypeSchema, samples: Vec<DynamicSample> },
+  GenericSubscriber { schema: &'static TypeSchema },
+  /// Subscribe with two QoS settings side by side. option names the command line option.
+  Compare { changes: QosPolicies, option: &'static str },
+  CoherentGroup,
+  OwnershipFailover(FailoverConfig),
+  WriterHistory(WriterHistoryConfig),
+  InstanceStress { instances: usize },
+}
+
+impl Mode {
+  /// Type name to register the topics with
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      Mode::GenericPublisher { schema, .. } | Mode::GenericSubscriber { schema } => schema.name.as_str(),
+      _ => "ShapeType",
+    }
+  }
+
+  /// Kind to create the topics with. A generic type without key members is
+  /// NO_KEY, so that its GUIDs and discovery data match remote keyless types.
+  pub fn topic_kind(&self) -> TopicKind {
+    match self {
+      Mode::GenericPublisher { schema, .. } | Mode::GenericSubscriber { schema } if !schema.is_keyed() =>
+        TopicKind::NoKey,
+      _ => TopicKind::WithKey,
+    }
+  }
+}
+
+/// Observation of the domain beside the test itself
+#[derive(Clone, Debug, Default)]
+pub struct Diagnostics {
+  /// Chronological event file
+  pub timeline: Option<String>,
+  /// Multicast capture file
+  pub capture: Option<String>,
+  pub protocol_stats: bool,
+  /// Number of incoming samples to hex-dump
+  pub dump_incoming_cdr: Option<usize>,
+  pub locators: bool,
+  pub vendors: bool,
+  pub unsupported_qos: bool,
+}
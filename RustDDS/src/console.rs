@@ -0,0 +1,130 @@
+/// Interactive commands read from stdin while running.
+use std::io::{self, BufRead};
+use std::thread;
+use std::time::Duration;
+
+use log::debug;
+use mio_extras::channel;
+
+use crate::shape_color;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+  Pause,
+  Resume,
+  TogglePause,
+  Snapshot,
+  Dispose(String),
+  Unregister(String),
+  Rate(Duration),
+  Color(String),
+  RestartParticipant,
+  Exit(i32),
+}
+
+pub const HELP: &str =
+  "Commands: pause | resume | dispose <color> | rate <ms> | color <c> | \
+   snapshot | restart | quit | exit <code>. \
+   Not supported by RustDDS: unregister <color> | partition [<name>...] | set-qos <policy>=<value>,...";
+
+/// Commands that are accepted, but only report that RustDDS cannot do them
+pub const UNSUPPORTED_COMMANDS: &[&str] = &["unregister"];
+
+/// Commands that are rejected, so that a script using them fails to load
+/// instead of running on as if they had worked: (command, reason)
+pub const REJECTED_COMMANDS: &[(&str, &str)] = &[
+  ("partition", "QoS policy Partition is not yet implemented in RustDDS"),
+  ("set-qos", "set_qos on existing DataWriters and DataReaders is not yet implemented in RustDDS"),
+];
+
+impl Command {
+  /// Parses a command. Colors are checked and normalized as the -c color,
+  /// so with any_color false only the Shapes demo colors are accepted.
+  pub fn parse(line: &str, any_color: bool) -> Result<Command, String> {
+    let color = |c: &str| shape_color(c, any_color);
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if let Some((command, reason)) = REJECTED_COMMANDS.iter().find( |(c, _)| words.first() == Some(c) ) {
+      return Err(format!("{} is not supported: {}.", command, reason))
+    }
+    match words.as_slice() {
+      ["pause"] => Ok(Command::Pause),
+      ["resume"] => Ok(Command::Resume),
+      ["dispose", c] => color(c).map(Command::Dispose),
+      ["unregister", c] => color(c).map(Command::Unregister),
+      ["rate", ms] => match ms.parse::<u64>() {
+        Ok(ms) if ms > 0 => Ok(Command::Rate(Duration::from_millis(ms))),
+        _ => Err(format!("Expected positive number of milliseconds, got '{}'", ms)),
+      },
+      ["color", c] => color(c).map(Command::Color),
+      ["snapshot"] => Ok(Command::Snapshot),
+      ["restart"] => Ok(Command::RestartParticipant),
+      ["quit"] | ["exit"] => Ok(Command::Exit(0)),
+      ["exit", code] => match code.parse::<i32>() {
+        Ok(code) => Ok(Command::Exit(code)),
+        _ => Err(format!("Expected numeric exit code, got '{}'", code)),
+      },
+      _ => Err(format!("Unknown command '{}'. {}", line.trim(), HELP)),
+    }
+  }
+}
+
+/// Start a thread that reads commands from stdin. Parse errors are reported
+/// directly, and only valid commands are passed on. The thread ends quietly at
+/// end of input, so running with stdin closed is fine.
+pub fn spawn_stdin_reader(command_sender: channel::Sender<Command>, any_color: bool) {
+  thread::Builder::new()
+    .name("stdin-console".to_string())
+    .spawn(move || {
+      for line in io::stdin().lock().lines() {
+        let line = match line {
+          Ok(line) => line,
+          Err(_) => break,
+        };
+        if line.trim().is_empty() {
+          continue
+        }
+        match Command::parse(&line, any_color) {
+          Ok(command) =>
+            if command_sender.send(command).is_err() {
+              break // main loop is gone
+            },
+          Err(msg) => println!("{}", msg),
+        }
+      }
+      debug!("stdin closed");
+    })
+    .expect("Cannot start stdin reader thread");
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn colors_are_checked_like_the_c_option() {
+    let cases = [
+      ("color blue", false, Ok(Command::Color("BLUE".to_string()))),
+      ("dispose Red", false, Ok(Command::Dispose("RED".to_string()))),
+      ("unregister GREEN", false, Ok(Command::Unregister("GREEN".to_string()))),
+      ("color teal", true, Ok(Command::Color("teal".to_string()))),
+      ("dispose red", true, Ok(Command::Dispose("red".to_string()))),
+    ];
+    for (line, any_color, expected) in &cases {
+      assert_eq!(&Command::parse(line, *any_color), expected, "{}", line);
+    }
+    for line in &["color teal", "dispose BLUE2", "unregister ''"] {
+      let e = Command::parse(line, false).unwrap_err();
+      assert!(e.contains("--any-color"), "{}: {}", line, e);
+    }
+  }
+
+  #[test]
+  fn rejected_commands() {
+    for (command, _) in REJECTED_COMMANDS {
+      for line in &[command.to_string(), format!("{} a b", command)] {
+        let e = Command::parse(line, false).unwrap_err();
+        assert!(e.starts_with(&format!("{} is not supported", command)), "{}: {}", line, e);
+      }
+    }
+  }
+}
@@ -0,0 +1,111 @@
+/// Assertions on received samples, for self-checking subscriber runs.
+use crate::Shape;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Expectation {
+  color: Option<String>,
+  size: Option<i32>,
+  xmin: Option<i32>,
+  xmax: Option<i32>,
+  ymin: Option<i32>,
+  ymax: Option<i32>,
+}
+
+impl Expectation {
+  /// Parses a comma-separated list like "color=RED,size=30,xmin=0,xmax=120".
+  pub fn parse(spec: &str) -> Result<Expectation, String> {
+    let mut e = Expectation::default();
+    for item in spec.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+      let (key, value) = match item.find('=') {
+        Some(pos) => (&item[..pos], &item[pos + 1..]),
+        None => return Err(format!("Expected key=value, got '{}'", item)),
+      };
+      let number = || value.parse::<i32>().map_err(|_| format!("Expected number for {}, got '{}'", key, value));
+      match key {
+        "color" => e.color = Some(value.to_string()),
+        "size" => e.size = Some(number()?),
+        "xmin" => e.xmin = Some(number()?),
+        "xmax" => e.xmax = Some(number()?),
+        "ymin" => e.ymin = Some(number()?),
+        "ymax" => e.ymax = Some(number()?),
+        other => return Err(format!("Unknown expectation '{}'. Known: color size xmin xmax ymin ymax", other)),
+      }
+    }
+    Ok(e)
+  }
+
+  /// Returns a description of the first violated expectation, if any.
+  pub fn check(&self, shape: &Shape) -> Result<(), String> {
+    if let Some(c) = &self.color {
+      if &shape.color != c {
+        return Err(format!("color is {}, expected {}", shape.color, c))
+      }
+    }
+    if let Some(s) = self.size {
+      if shape.shapesize != s {
+        return Err(format!("size is {}, expected {}", shape.shapesize, s))
+      }
+    }
+    let bounds = [
+      ("x", shape.x, self.xmin, self.xmax),
+      ("y", shape.y, self.ymin, self.ymax),
+    ];
+    for (name, value, min, max) in bounds.iter() {
+      if let Some(min) = min {
+        if value < min {
+          return Err(format!("{} is {}, expected at least {}", name, value, min))
+        }
+      }
+      if let Some(max) = max {
+        if value > max {
+          return Err(format!("{} is {}, expected at most {}", name, value, max))
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn shape(color: &str, x: i32, y: i32, shapesize: i32) -> Shape {
+    Shape { color: color.to_string(), x, y, shapesize }
+  }
+
+  #[test]
+  fn parse() {
+    let e = Expectation::parse(" color=RED, size=30,xmin=0,xmax=120,ymin=-5,ymax=200,").unwrap();
+    assert_eq!(e, Expectation {
+      color: Some("RED".to_string()),
+      size: Some(30),
+      xmin: Some(0),
+      xmax: Some(120),
+      ymin: Some(-5),
+      ymax: Some(200),
+    });
+    assert_eq!(Expectation::parse("").unwrap(), Expectation::default());
+    for spec in &["color", "size=big", "xmin=1.5", "zmin=0", "color=RED,ymax"] {
+      assert!(Expectation::parse(spec).is_err(), "{}", spec);
+    }
+  }
+
+  #[test]
+  fn check() {
+    let e = Expectation::parse("color=RED,size=30,xmin=0,xmax=120,ymin=10,ymax=200").unwrap();
+    let cases = [
+      (shape("RED", 0, 10, 30), None),
+      (shape("RED", 120, 200, 30), None),
+      (shape("BLUE", 50, 50, 30), Some("color is BLUE, expected RED")),
+      (shape("RED", 50, 50, 25), Some("size is 25, expected 30")),
+      (shape("RED", -1, 50, 30), Some("x is -1, expected at least 0")),
+      (shape("RED", 121, 50, 30), Some("x is 121, expected at most 120")),
+      (shape("RED", 50, 9, 30), Some("y is 9, expected at least 10")),
+      (shape("RED", 50, 201, 30), Some("y is 201, expected at most 200")),
+    ];
+    for (s, expected) in &cases {
+      assert_eq!(e.check(s).err().as_deref(), *expected, "{:?}", s);
+    }
+  }
+}
@@ -0,0 +1,212 @@
+/// Exclusive ownership failover test: two writers of different strength publish
+/// the same instance. The stronger one is deleted mid-run, and a remote
+/// subscriber should then switch over to the weaker one.
+///
+/// Each writer publishes with shapesize equal to its strength, so that the
+/// followed writer is visible in the remote subscriber output.
+use std::time::Instant;
+
+use log::debug;
+use mio::{Events, PollOpt, Ready, Token};
+use rustdds::dds::qos::QosPolicies;
+use rustdds::dds::statusevents::StatusEvented;
+use rustdds::dds::traits::TopicDescription;
+use rustdds::dds::{DomainParticipant, Topic};
+
+use crate::console::Command;
+use crate::on_error::report_write;
+use crate::qos::exclusive_ownership;
+use crate::status_report::{self, LogFormat};
+use crate::{move_shape, random_velocity, Control, Shape, ShapeData, DEFAULT_WRITE_INTERVAL, STOP_PROGRAM,
+  COMMAND_READY};
+
+const WEAK_STATUS_READY: Token = Token(10);
+const STRONG_STATUS_READY: Token = Token(11);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailoverConfig {
+  pub weak_strength: i32,
+  pub strong_strength: i32,
+  /// Number of samples the stronger writer writes before it is deleted.
+  pub switch_after: u64,
+}
+
+impl FailoverConfig {
+  /// Parses values like ["strengths=10,20", "switch-after=50"]. Missing values
+  /// default to strengths 10 and 20, and switch after 50 samples.
+  pub fn parse<'a>(values: impl Iterator<Item = &'a str>) -> Result<FailoverConfig, String> {
+    let mut strengths = (10, 20);
+    let mut switch_after = 50;
+    for value in values {
+      match value.split_once('=') {
+        Some(("strengths", s)) => {
+          let parsed: Vec<i32> = s
+            .split(',')
+            .map(|x| x.trim().parse::<i32>().map_err(|_| format!("Bad strength '{}'", x)))
+            .collect::<Result<_, _>>()?;
+          match parsed.as_slice() {
+            [a, b] if a != b => strengths = (*a.min(b), *a.max(b)),
+            _ => return Err("Expected two different strengths, e.g. strengths=10,20".to_string()),
+          }
+        }
+        Some(("switch-after", n)) =>
+          switch_after = n.parse::<u64>().map_err(|_| format!("Bad sample count '{}'", n))?,
+        _ => return Err(format!("Unknown failover option '{}'", value)),
+      }
+    }
+    Ok(FailoverConfig { weak_strength: strengths.0, strong_strength: strengths.1, switch_after })
+  }
+}
+
+pub fn run_ownership_failover<T: ShapeData>(domain_participant: &DomainParticipant, topic: Topic,
+    qos: &QosPolicies, color: &str, config: FailoverConfig, log_format: LogFormat, control: &Control) -> i32
+{
+  debug!("Ownership failover publisher");
+  let mut events = Events::with_capacity(4);
+  let topic_name = topic.get_name().to_string();
+  let entity = |strength| format!("DataWriter strength {}", strength);
+  let publisher = match control.on_error.create("Publisher", || domain_participant.create_publisher(qos)) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let mut weak_writer = match control.on_error.create("DataWriter", || {
+    publisher.create_datawriter_CDR::<T>(topic.clone(), Some(exclusive_ownership(qos, config.weak_strength)))
+  }) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let mut strong_writer = match control.on_error.create("DataWriter", || {
+    publisher.create_datawriter_CDR::<T>(topic.clone(), Some(exclusive_ownership(qos, config.strong_strength)))
+  }) {
+    Ok(entity) => Some(entity),
+    Err(code) => return code,
+  };
+  control.poll.register(weak_writer.as_status_evented(), WEAK_STATUS_READY, Ready::readable(), PollOpt::edge())
+    .unwrap();
+  if let Some(w) = strong_writer.as_mut() {
+    control.poll.register(w.as_status_evented(), STRONG_STATUS_READY, Ready::readable(), PollOpt::edge())
+      .unwrap();
+  }
+
+  let new_shape = |strength| {
+    (Shape { color: color.to_string(), x: 0, y: 0, shapesize: strength }, random_velocity(), random_velocity())
+  };
+  let mut weak_shape = new_shape(config.weak_strength);
+  let mut strong_shape = new_shape(config.strong_strength);
+  let mut counter: u32 = 0;
+
+  println!(
+    "Phase 1: writers with strength {} (size {}) and {} (size {}) active. \
+     Remote subscriber should follow strength {}.",
+    config.weak_strength, config.weak_strength, config.strong_strength, config.strong_strength,
+    config.strong_strength
+  );
+
+  let mut next_write = Instant::now() + DEFAULT_WRITE_INTERVAL;
+  loop {
+    control.poll
+      .poll(&mut events, Some(next_write.saturating_duration_since(Instant::now())))
+      .unwrap();
+    for event in &events {
+      match event.token() {
+        STOP_PROGRAM => {
+          if control.stop_requested() {
+            println!("Done.");
+            return 0
+          }
+        }
+        COMMAND_READY => {
+          while let Ok(command) = control.command_receiver.try_recv() {
+            match command {
+              Command::Exit(code) => {
+                println!("Done.");
+                return code
+              }
+              other => status_report::report_unsupported_command(log_format, "ownership failover", &other),
+            }
+          }
+        }
+        WEAK_STATUS_READY => {
+          while let Some(status) = weak_writer.try_recv_status() {
+            status_report::report_named_writer_status(log_format, &entity(config.weak_strength), &topic_name,
+              &status);
+          }
+        }
+        STRONG_STATUS_READY => {
+          if let Some(w) = strong_writer.as_ref() {
+            while let Some(status) = w.try_recv_status() {
+              status_report::report_named_writer_status(log_format, &entity(config.strong_strength), &topic_name,
+                &status);
+            }
+          }
+        }
+        other_token => {
+          println!("Polled event is {:?}. WTF?", other_token);
+        }
+      }
+    }
+
+    if Instant::now() < next_write {
+      continue
+    }
+    next_write += DEFAULT_WRITE_INTERVAL;
+
+    weak_shape = move_shape(weak_shape.0, weak_shape.1, weak_shape.2);
+    let sample = T::from_shape(weak_shape.0.clone(), counter);
+    if let Err(code) = control.on_error.write(|| report_write(&topic_name, weak_writer.write(sample.clone(), None))) {
+      return code
+    }
+
+    if let Some(w) = strong_writer.as_mut() {
+      strong_shape = move_shape(strong_shape.0, strong_shape.1, strong_shape.2);
+      let sample = T::from_shape(strong_shape.0.clone(), counter);
+      if let Err(code) = control.on_error.write(|| report_write(&topic_name, w.write(sample.clone(), None))) {
+        return code
+      }
+      // It writes at least one sample, even with switch-after=0.
+      let written = u64::from(counter) + 1;
+      if written >= config.switch_after {
+        strong_writer = None; // deletes the writer
+        println!(
+          "Phase 2: strength {} writer deleted after {} samples. \
+           Remote subscriber should follow strength {}.",
+          config.strong_strength, written, config.weak_strength
+        );
+      }
+    }
+    counter = counter.wrapping_add(1);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse() {
+    let config = |weak_strength, strong_strength, switch_after|
+      Ok(FailoverConfig { weak_strength, strong_strength, switch_after });
+    let cases: &[(&[&str], Result<FailoverConfig, String>)] = &[
+      (&[], config(10, 20, 50)),
+      (&["strengths=5,7"], config(5, 7, 50)),
+      (&["strengths=30, 3"], config(3, 30, 50)),
+      (&["switch-after=0"], config(10, 20, 0)),
+      (&["switch-after=100", "strengths=-1,1"], config(-1, 1, 100)),
+    ];
+    for (values, expected) in cases {
+      assert_eq!(&FailoverConfig::parse(values.iter().cloned()), expected, "{:?}", values);
+    }
+    let errors: &[&[&str]] = &[
+      &["strengths=10"],
+      &["strengths=10,10"],
+      &["strengths=10,20,30"],
+      &["strengths=a,b"],
+      &["switch-after=-1"],
+      &["switch-after"],
+      &["strength=10,20"],
+    ];
+    for values in errors {
+      assert!(FailoverConfig::parse(values.iter().cloned()).is_err(), "{:?}", values);
+    }
+  }
+}
@@ -0,0 +1,536 @@
+/// Content filter expressions on ShapeType, in the SQL subset of the DDS
+/// specification, e.g. "x > 100 AND color = 'BLUE'" or "x BETWEEN %0 AND %1".
+///
+/// RustDDS has no ContentFilteredTopic, and cannot advertise a filter in
+/// discovery, so remote writers cannot filter for us. The filter is applied
+/// on our reader, and we count how many samples it rejects. Any rejected
+/// sample shows that filtering did not happen at the writer.
+use std::fmt;
+
+use crate::Shape;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+  Number(f64),
+  Text(String),
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Value::Number(n) => write!(f, "{}", n),
+      Value::Text(s) => write!(f, "'{}'", s),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+  Color,
+  X,
+  Y,
+  Size,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+  Field(Field),
+  Literal(Value),
+}
+
+impl fmt::Display for Operand {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Operand::Field(Field::Color) => write!(f, "color"),
+      Operand::Field(Field::X) => write!(f, "x"),
+      Operand::Field(Field::Y) => write!(f, "y"),
+      Operand::Field(Field::Size) => write!(f, "shapesize"),
+      Operand::Literal(v) => write!(f, "{}", v),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+  Equal,
+  NotEqual,
+  Less,
+  LessOrEqual,
+  Greater,
+  GreaterOrEqual,
+  Like,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+  Compare(Operand, Comparison, Operand),
+  Between(Operand, Operand, Operand),
+  Not(Box<Condition>),
+  And(Box<Condition>, Box<Condition>),
+  Or(Box<Condition>, Box<Condition>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Word(String),
+  Number(f64),
+  Text(String),
+  Parameter(usize),
+  Symbol(&'static str),
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+  let mut tokens = Vec::new();
+  let mut chars = expression.chars().peekable();
+  while let Some(&c) = chars.peek() {
+    match c {
+      c if c.is_whitespace() => {
+        chars.next();
+      }
+      '\'' => {
+        chars.next();
+        let mut text = String::new();
+        loop {
+          match chars.next() {
+            Some('\'') => break,
+            Some(c) => text.push(c),
+            None => return Err(format!("Unterminated string '{}", text)),
+          }
+        }
+        tokens.push(Token::Text(text))
+      }
+      '%' => {
+        chars.next();
+        let mut digits = String::new();
+        while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+          digits.push(*d);
+          chars.next();
+        }
+        let n = digits.parse().map_err(|_| "Expected parameter number after %".to_string())?;
+        tokens.push(Token::Parameter(n))
+      }
+      c if c.is_ascii_digit() || c == '-' || c == '.' => {
+        let mut number = c.to_string();
+        chars.next();
+        while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit() || **d == '.') {
+          number.push(*d);
+          chars.next();
+        }
+        let n = number.parse().map_err(|_| format!("Bad number {}", number))?;
+        tokens.push(Token::Number(n))
+      }
+      c if c.is_alphabetic() || c == '_' => {
+        let mut word = String::new();
+        while let Some(w) = chars.peek().filter(|w| w.is_alphanumeric() || **w == '_') {
+          word.push(*w);
+          chars.next();
+        }
+        tokens.push(Token::Word(word))
+      }
+      _ => {
+        chars.next();
+        let next = chars.peek().cloned();
+        let symbol = match (c, next) {
+          ('<', Some('=')) => "<=",
+          ('>', Some('=')) => ">=",
+          ('<', Some('>')) => "<>",
+          ('!', Some('=')) => "<>",
+          ('<', _) => "<",
+          ('>', _) => ">",
+          ('=', _) => "=",
+          ('(', _) => "(",
+          (')', _) => ")",
+          (other, _) => return Err(format!("Unexpected character '{}'", other)),
+        };
+        if symbol.len() == 2 {
+          chars.next();
+        }
+        tokens.push(Token::Symbol(symbol))
+      }
+    }
+  }
+  Ok(tokens)
+}
+
+struct Parser<'a> {
+  tokens: Vec<Token>,
+  pos: usize,
+  parameters: &'a [String],
+}
+
+impl<'a> Parser<'a> {
+  fn peek_keyword(&self, keyword: &str) -> bool {
+    matches!(self.tokens.get(self.pos), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+  }
+
+  fn keyword(&mut self, keyword: &str) -> bool {
+    let found = self.peek_keyword(keyword);
+    if found {
+      self.pos += 1;
+    }
+    found
+  }
+
+  fn next(&mut self) -> Result<Token, String> {
+    let t = self.tokens.get(self.pos).cloned().ok_or_else(|| "Unexpected end of filter".to_string())?;
+    self.pos += 1;
+    Ok(t)
+  }
+
+  fn or(&mut self) -> Result<Condition, String> {
+    let mut c = self.and()?;
+    while self.keyword("OR") {
+      c = Condition::Or(Box::new(c), Box::new(self.and()?));
+    }
+    Ok(c)
+  }
+
+  fn and(&mut self) -> Result<Condition, String> {
+    let mut c = self.not()?;
+    while self.keyword("AND") {
+      c = Condition::And(Box::new(c), Box::new(self.not()?));
+    }
+    Ok(c)
+  }
+
+  fn not(&mut self) -> Result<Condition, String> {
+    if self.keyword("NOT") {
+      return Ok(Condition::Not(Box::new(self.not()?)))
+    }
+    if self.tokens.get(self.pos) == Some(&Token::Symbol("(")) {
+      self.pos += 1;
+      let c = self.or()?;
+      match self.next()? {
+        Token::Symbol(")") => return Ok(c),
+        other => return Err(format!("Expected ')', got {:?}", other)),
+      }
+    }
+    let left = self.operand()?;
+    if self.keyword("BETWEEN") {
+      let low = self.operand()?;
+      if !self.keyword("AND") {
+        return Err("Expected AND in BETWEEN".to_string())
+      }
+      let high = self.operand()?;
+      return Ok(Condition::Between(left, low, high))
+    }
+    if self.keyword("NOT") {
+      if !self.keyword("BETWEEN") {
+        return Err("Expected BETWEEN after NOT".to_string())
+      }
+      let low = self.operand()?;
+      if !self.keyword("AND") {
+        return Err("Expected AND in BETWEEN".to_string())
+      }
+      let high = self.operand()?;
+      return Ok(Condition::Not(Box::new(Condition::Between(left, low, high))))
+    }
+    let comparison = match self.next()? {
+      Token::Symbol("=") => Comparison::Equal,
+      Token::Symbol("<>") => Comparison::NotEqual,
+      Token::Symbol("<") => Comparison::Less,
+      Token::Symbol("<=") => Comparison::LessOrEqual,
+      Token::Symbol(">") => Comparison::Greater,
+      Token::Symbol(">=") => Comparison::GreaterOrEqual,
+      Token::Word(w) if w.eq_ignore_ascii_case("LIKE") => Comparison::Like,
+      other => return Err(format!("Expected comparison, got {:?}", other)),
+    };
+    Ok(Condition::Compare(left, comparison, self.operand()?))
+  }
+
+  fn operand(&mut self) -> Result<Operand, String> {
+    Ok(match self.next()? {
+      Token::Word(w) => Operand::Field(match w.to_ascii_lowercase().as_str() {
+        "color" => Field::Color,
+        "x" => Field::X,
+        "y" => Field::Y,
+        "shapesize" => Field::Size,
+        _ => return Err(format!("Unknown field {}. ShapeType has color x y shapesize", w)),
+      }),
+      Token::Number(n) => Operand::Literal(Value::Number(n)),
+      Token::Text(s) => Operand::Literal(Value::Text(s)),
+      Token::Parameter(n) => {
+        let p = self.parameters.get(n).ok_or_else(|| format!("No value given for parameter %{}", n))?;
+        // Parameters are given as in the DDS API: strings quoted, numbers plain.
+        let p = p.trim();
+        Operand::Literal(match p.strip_prefix('\'').and_then(|p| p.strip_suffix('\'')) {
+          Some(text) => Value::Text(text.to_string()),
+          None => match p.parse() {
+            Ok(n) => Value::Number(n),
+            Err(_) => Value::Text(p.to_string()),
+          },
+        })
+      }
+      other => return Err(format!("Expected field or value, got {:?}", other)),
+    })
+  }
+}
+
+/// SQL LIKE: % matches any sequence, _ any single character.
+fn like(text: &[char], pattern: &[char]) -> bool {
+  match pattern.split_first() {
+    None => text.is_empty(),
+    Some(('%', rest)) => (0..=text.len()).any(|i| like(&text[i..], rest)),
+    Some(('_', rest)) => !text.is_empty() && like(&text[1..], rest),
+    Some((c, rest)) => text.first() == Some(c) && like(&text[1..], rest),
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+  expression: String,
+  condition: Condition,
+  pub passed: u64,
+  pub rejected: u64,
+}
+
+impl Filter {
+  pub fn parse(expression: &str, parameters: &[String]) -> Result<Filter, String> {
+    let mut parser = Parser { tokens: tokenize(expression)?, pos: 0, parameters };
+    let condition = parser.or()?;
+    if let Some(t) = parser.tokens.get(parser.pos) {
+      return Err(format!("Unexpected {:?} after complete filter", t))
+    }
+    check(&condition)?;
+    Ok(Filter { expression: expression.to_string(), condition, passed: 0, rejected: 0 })
+  }
+
+  /// Checks a sample, and counts the result.
+  pub fn accept(&mut self, shape: &Shape) -> bool {
+    let accepted = evaluate(&self.condition, shape);
+    if accepted {
+      self.passed += 1;
+    } else {
+      self.rejected += 1;
+    }
+    accepted
+  }
+
+  pub fn report(&self) -> String {
+    let place = if self.rejected > 0 {
+      "writers did not filter"
+    } else if self.passed > 0 {
+      "no sample needed filtering here"
+    } else {
+      "no samples yet"
+    };
+    format!(
+      "Filter \"{}\" applied at reader: {} samples passed, {} rejected ({})",
+      self.expression, self.passed, self.rejected, place
+    )
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+  Number,
+  Text,
+}
+
+fn type_of(operand: &Operand) -> Type {
+  match operand {
+    Operand::Field(Field::Color) | Operand::Literal(Value::Text(_)) => Type::Text,
+    Operand::Field(_) | Operand::Literal(Value::Number(_)) => Type::Number,
+  }
+}
+
+/// Finds type errors, such as comparing x with a string, in every part of
+/// the condition, so that evaluate cannot fail.
+fn check(condition: &Condition) -> Result<(), String> {
+  let same_type = |a: &Operand, b: &Operand| if type_of(a) == type_of(b) {
+    Ok(())
+  } else {
+    Err(format!("Cannot compare {} with {}", a, b))
+  };
+  match condition {
+    Condition::Compare(l, Comparison::Like, r) => {
+      if type_of(l) != Type::Text || type_of(r) != Type::Text {
+        return Err(format!("LIKE needs strings, got {} and {}", l, r))
+      }
+      Ok(())
+    }
+    Condition::Compare(l, _, r) => same_type(l, r),
+    Condition::Between(v, low, high) => same_type(v, low).and(same_type(v, high)),
+    Condition::Not(c) => check(c),
+    Condition::And(a, b) | Condition::Or(a, b) => check(a).and(check(b)),
+  }
+}
+
+fn value(operand: &Operand, shape: &Shape) -> Value {
+  match operand {
+    Operand::Field(Field::Color) => Value::Text(shape.color.clone()),
+    Operand::Field(Field::X) => Value::Number(f64::from(shape.x)),
+    Operand::Field(Field::Y) => Value::Number(f64::from(shape.y)),
+    Operand::Field(Field::Size) => Value::Number(f64::from(shape.shapesize)),
+    Operand::Literal(v) => v.clone(),
+  }
+}
+
+fn compare(left: &Value, comparison: Comparison, right: &Value) -> bool {
+  use std::cmp::Ordering::*;
+  let ordering = match (left, right) {
+    (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+    (Value::Text(a), Value::Text(b)) => {
+      if comparison == Comparison::Like {
+        let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+        return like(&a, &b)
+      }
+      Some(a.cmp(b))
+    }
+    _ => unreachable!("Filter::parse checks types, but {} compared with {}", left, right),
+  };
+  match comparison {
+    Comparison::Equal => ordering == Some(Equal),
+    Comparison::NotEqual => ordering != Some(Equal),
+    Comparison::Less => ordering == Some(Less),
+    Comparison::LessOrEqual => matches!(ordering, Some(Less) | Some(Equal)),
+    Comparison::Greater => ordering == Some(Greater),
+    Comparison::GreaterOrEqual => matches!(ordering, Some(Greater) | Some(Equal)),
+    Comparison::Like => unreachable!("Filter::parse checks that LIKE has strings"),
+  }
+}
+
+fn evaluate(condition: &Condition, shape: &Shape) -> bool {
+  match condition {
+    Condition::Compare(l, c, r) => compare(&value(l, shape), *c, &value(r, shape)),
+    Condition::Between(v, low, high) => {
+      let v = value(v, shape);
+      compare(&v, Comparison::GreaterOrEqual, &value(low, shape))
+        && compare(&v, Comparison::LessOrEqual, &value(high, shape))
+    }
+    Condition::Not(c) => !evaluate(c, shape),
+    Condition::And(a, b) => evaluate(a, shape) && evaluate(b, shape),
+    Condition::Or(a, b) => evaluate(a, shape) || evaluate(b, shape),
+  }
+}
+
+/// Writer side of content filtering. RustDDS does not read content filters
+/// that remote readers advertise in discovery, so a DataWriter sends every
+/// sample to every matched reader.
+pub fn writer_side_report(samples_written: u64, matched_readers: i32, filtering_disabled: bool) -> String {
+  format!(
+    "Writer-side filtering {}: {} samples written, each sent unfiltered to all {} matched readers",
+    if filtering_disabled { "disabled (RustDDS never filters at the writer)" } else { "not performed by RustDDS" },
+    samples_written,
+    matched_readers
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn shape(color: &str, x: i32, y: i32, shapesize: i32) -> Shape {
+    Shape { color: color.to_string(), x, y, shapesize }
+  }
+
+  fn accepts(expression: &str, parameters: &[&str], shape: &Shape) -> bool {
+    let parameters: Vec<String> = parameters.iter().map( |p| p.to_string() ).collect();
+    Filter::parse(expression, &parameters).unwrap().accept(shape)
+  }
+
+  #[test]
+  fn precedence() {
+    let blue = shape("BLUE", 50, 50, 30);
+    let cases = [
+      // AND binds tighter than OR
+      ("x > 100 AND y > 100 OR color = 'BLUE'", true),
+      ("color = 'BLUE' OR x > 100 AND y > 100", true),
+      ("(color = 'BLUE' OR x > 100) AND y > 100", false),
+      // NOT binds tighter than AND
+      ("NOT x > 100 AND y > 100", false),
+      ("NOT (x > 100 AND y > 100)", true),
+      ("NOT NOT color = 'BLUE'", true),
+      ("not x > 100 and color = 'BLUE'", true),
+    ];
+    for (expression, expected) in &cases {
+      assert_eq!(accepts(expression, &[], &blue), *expected, "{}", expression);
+    }
+  }
+
+  #[test]
+  fn between() {
+    let cases = [
+      (99, false),
+      (100, true),
+      (150, true),
+      (200, true),
+      (201, false),
+    ];
+    for (x, expected) in &cases {
+      let s = shape("RED", *x, 0, 30);
+      assert_eq!(accepts("x BETWEEN 100 AND 200", &[], &s), *expected, "x = {}", x);
+      assert_eq!(accepts("x NOT BETWEEN 100 AND 200", &[], &s), !*expected, "x = {}", x);
+    }
+  }
+
+  #[test]
+  fn like() {
+    let cases = [
+      ("B%", "BLUE", true),
+      ("B%", "B", true),
+      ("B%", "RED", false),
+      ("B_%", "B", false),
+      ("B_%", "BLACK", true),
+      ("_ED", "RED", true),
+      ("_ED", "GREEN", false),
+      ("%E%", "GREEN", true),
+      ("%E", "BLUE", true),
+      ("%E", "CYAN", false),
+      ("RED", "RED", true),
+      ("RED", "REDDISH", false),
+    ];
+    for (pattern, color, expected) in &cases {
+      let expression = format!("color LIKE '{}'", pattern);
+      assert_eq!(accepts(&expression, &[], &shape(color, 0, 0, 30)), *expected, "{} on {}", expression, color);
+    }
+  }
+
+  #[test]
+  fn parameters() {
+    let s = shape("BLUE", 150, 20, 30);
+    assert!(accepts("x BETWEEN %0 AND %1", &["100", "200"], &s));
+    assert!(!accepts("x BETWEEN %0 AND %1", &["0", "100"], &s));
+    assert!(accepts("color = %0", &["'BLUE'"], &s));
+    assert!(accepts("color = %0", &["BLUE"], &s));
+    assert!(!accepts("color = %1 AND y < %0", &["10", "'BLUE'"], &s));
+  }
+
+  #[test]
+  fn errors() {
+    let cases = [
+      "",
+      "x >",
+      "x > 100 AND",
+      "(x > 100",
+      "x > 100)",
+      "z > 100",
+      "x BETWEEN 1 OR 2",
+      "x NOT 5",
+      "x > %1",
+      "x > %",
+      "color = 'BLUE",
+      "x > 100 ; y > 0",
+      "x = 'BLUE'",
+      "color > 5",
+      "x LIKE 5",
+      "x BETWEEN 'a' AND 5",
+      // type errors are found after short-circuiting parts too
+      "x > 100 AND color = 5",
+      "x < 1000 OR color = 5",
+      "NOT (x > 0 OR y = 'a')",
+    ];
+    for expression in &cases {
+      assert!(Filter::parse(expression, &["5".to_string()]).is_err(), "{}", expression);
+    }
+  }
+
+  #[test]
+  fn counts_and_report() {
+    let mut filter = Filter::parse("x > 100", &[]).unwrap();
+    assert!(filter.accept(&shape("BLUE", 150, 0, 30)));
+    assert!(!filter.accept(&shape("BLUE", 50, 0, 30)));
+    assert_eq!((filter.passed, filter.rejected), (1, 1));
+    assert!(filter.report().contains("writers did not filter"));
+  }
+}
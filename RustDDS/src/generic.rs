@@ -0,0 +1,416 @@
+/// Generic mode: publish and subscribe a data type described at run time by a
+/// schema file, instead of ShapeType.
+///
+/// The schema is JSON:
+///
+///   { "name": "SensorReading",
+///     "members": [ { "name": "id", "type": "long", "key": true },
+///                  { "name": "label", "type": "string" },
+///                  { "name": "value", "type": "double" } ] }
+///
+/// The type can also be given in IDL, see idl.rs.
+///
+/// Member types are IDL primitive types and "string". Samples to publish are
+/// JSON objects with the member names as fields, either as a JSON array or one
+/// object after another in the samples file. The publisher writes them in order,
+/// cycling, one per write interval.
+///
+/// CDR encodes a struct as just its members in order, so samples are serialized
+/// as a tuple of the member values.
+use std::fmt;
+use std::fs;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use log::debug;
+use mio::{Events, PollOpt, Ready};
+use rustdds::dds::qos::QosPolicies;
+use rustdds::dds::statusevents::StatusEvented;
+use rustdds::dds::traits::{Keyed, TopicDescription};
+use rustdds::dds::{DomainParticipant, Topic};
+use serde::de::{self, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use serde::ser::{SerializeTuple, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::console::Command;
+use crate::on_error::report_write;
+use crate::status_report::{self, LogFormat};
+use crate::{Control, COMMAND_READY, DEFAULT_WRITE_INTERVAL, READER_READY, STATUS_READY, STOP_PROGRAM};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemberType {
+  Boolean,
+  Octet,
+  Short,
+  #[serde(rename = "unsigned short")]
+  UnsignedShort,
+  Long,
+  #[serde(rename = "unsigned long")]
+  UnsignedLong,
+  #[serde(rename = "long long")]
+  LongLong,
+  #[serde(rename = "unsigned long long")]
+  UnsignedLongLong,
+  Float,
+  Double,
+  String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Member {
+  pub name: String,
+  #[serde(rename = "type")]
+  pub member_type: MemberType,
+  #[serde(default)]
+  pub key: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TypeSchema {
+  pub name: String,
+  pub members: Vec<Member>,
+}
+
+impl TypeSchema {
+  pub fn is_keyed(&self) -> bool {
+    self.members.iter().any( |m| m.key )
+  }
+}
+
+// Deserialize and Keyed implementations have no way to receive the schema as
+// a parameter, so it is set once at startup.
+static SCHEMA: OnceLock<TypeSchema> = OnceLock::new();
+
+fn schema() -> &'static TypeSchema {
+  SCHEMA.get().expect("Type schema not loaded")
+}
+
+/// Makes the schema the type of DynamicSample.
+pub fn set_schema(schema: TypeSchema) -> Result<&'static TypeSchema, String> {
+  SCHEMA.set(schema).map_err(|_| "Type schema already loaded".to_string())?;
+  Ok(self::schema())
+}
+
+/// Loads a JSON schema file, see above.
+pub fn load_schema(path: &str) -> Result<TypeSchema, String> {
+  let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+  let schema: TypeSchema = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+  if schema.members.is_empty() {
+    return Err(format!("{}: type {} has no members", path, schema.name))
+  }
+  Ok(schema)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum DynamicValue {
+  Boolean(bool),
+  Octet(u8),
+  Short(i16),
+  UnsignedShort(u16),
+  Long(i32),
+  UnsignedLong(u32),
+  LongLong(i64),
+  UnsignedLongLong(u64),
+  Float(f32),
+  Double(f64),
+  String(String),
+}
+
+impl DynamicValue {
+  fn from_json(member: &Member, value: &Value) -> Result<DynamicValue, String> {
+    use std::convert::TryFrom;
+    let bad = || format!("Member {}: {} is not a valid {:?}", member.name, value, member.member_type);
+    let int = || value.as_i64().ok_or_else(bad);
+    let uint = || value.as_u64().ok_or_else(bad);
+    Ok(match member.member_type {
+      MemberType::Boolean => DynamicValue::Boolean(value.as_bool().ok_or_else(bad)?),
+      MemberType::Octet => DynamicValue::Octet(u8::try_from(uint()?).map_err(|_| bad())?),
+      MemberType::Short => DynamicValue::Short(i16::try_from(int()?).map_err(|_| bad())?),
+      MemberType::UnsignedShort => DynamicValue::UnsignedShort(u16::try_from(uint()?).map_err(|_| bad())?),
+      MemberType::Long => DynamicValue::Long(i32::try_from(int()?).map_err(|_| bad())?),
+      MemberType::UnsignedLong => DynamicValue::UnsignedLong(u32::try_from(uint()?).map_err(|_| bad())?),
+      MemberType::LongLong => DynamicValue::LongLong(int()?),
+      MemberType::UnsignedLongLong => DynamicValue::UnsignedLongLong(uint()?),
+      MemberType::Float => DynamicValue::Float(value.as_f64().ok_or_else(bad)? as f32),
+      MemberType::Double => DynamicValue::Double(value.as_f64().ok_or_else(bad)?),
+      MemberType::String => DynamicValue::String(value.as_str().ok_or_else(bad)?.to_string()),
+    })
+  }
+
+  fn to_json(&self) -> Value {
+    match self {
+      DynamicValue::Boolean(v) => Value::from(*v),
+      DynamicValue::Octet(v) => Value::from(*v),
+      DynamicValue::Short(v) => Value::from(*v),
+      DynamicValue::UnsignedShort(v) => Value::from(*v),
+      DynamicValue::Long(v) => Value::from(*v),
+      DynamicValue::UnsignedLong(v) => Value::from(*v),
+      DynamicValue::LongLong(v) => Value::from(*v),
+      DynamicValue::UnsignedLongLong(v) => Value::from(*v),
+      DynamicValue::Float(v) => Value::from(*v),
+      DynamicValue::Double(v) => Value::from(*v),
+      DynamicValue::String(v) => Value::from(v.as_str()),
+    }
+  }
+}
+
+/// A sample of the schema type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynamicSample {
+  values: Vec<DynamicValue>,
+}
+
+impl DynamicSample {
+  pub fn from_json(value: &Value) -> Result<DynamicSample, String> {
+    let object = value.as_object().ok_or_else(|| format!("Sample must be a JSON object, got {}", value))?;
+    let values = schema()
+      .members
+      .iter()
+      .map(|m| match object.get(&m.name) {
+        Some(v) => DynamicValue::from_json(m, v),
+        None => Err(format!("Sample {} is missing member {}", value, m.name)),
+      })
+      .collect::<Result<_, _>>()?;
+    Ok(DynamicSample { values })
+  }
+
+  pub fn to_json(&self) -> Value {
+    let members = schema().members.iter().zip(&self.values).map(|(m, v)| (m.name.clone(), v.to_json()));
+    Value::Object(members.collect::<Map<_, _>>())
+  }
+}
+
+impl Keyed for DynamicSample {
+  type K = String;
+  /// Values of key members joined with ','. Keyless types have a single instance "".
+  fn get_key(&self) -> String {
+    schema()
+      .members
+      .iter()
+      .zip(&self.values)
+      .filter(|(m, _)| m.key)
+      .map(|(_, v)| v.to_json().to_string())
+      .collect::<Vec<_>>()
+      .join(",")
+  }
+}
+
+impl Serialize for DynamicSample {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut tuple = serializer.serialize_tuple(self.values.len())?;
+    for v in &self.values {
+      tuple.serialize_element(v)?;
+    }
+    tuple.end()
+  }
+}
+
+struct MemberSeed(MemberType);
+
+impl<'de> DeserializeSeed<'de> for MemberSeed {
+  type Value = DynamicValue;
+  fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<DynamicValue, D::Error> {
+    Ok(match self.0 {
+      MemberType::Boolean => DynamicValue::Boolean(bool::deserialize(d)?),
+      MemberType::Octet => DynamicValue::Octet(u8::deserialize(d)?),
+      MemberType::Short => DynamicValue::Short(i16::deserialize(d)?),
+      MemberType::UnsignedShort => DynamicValue::UnsignedShort(u16::deserialize(d)?),
+      MemberType::Long => DynamicValue::Long(i32::deserialize(d)?),
+      MemberType::UnsignedLong => DynamicValue::UnsignedLong(u32::deserialize(d)?),
+      MemberType::LongLong => DynamicValue::LongLong(i64::deserialize(d)?),
+      MemberType::UnsignedLongLong => DynamicValue::UnsignedLongLong(u64::deserialize(d)?),
+      MemberType::Float => DynamicValue::Float(f32::deserialize(d)?),
+      MemberType::Double => DynamicValue::Double(f64::deserialize(d)?),
+      MemberType::String => DynamicValue::String(String::deserialize(d)?),
+    })
+  }
+}
+
+struct SampleVisitor;
+
+impl<'de> Visitor<'de> for SampleVisitor {
+  type Value = DynamicSample;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "struct {}", schema().name)
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DynamicSample, A::Error> {
+    let mut values = Vec::with_capacity(schema().members.len());
+    for (i, m) in schema().members.iter().enumerate() {
+      values.push(seq.next_element_seed(MemberSeed(m.member_type))?.ok_or_else(|| de::Error::invalid_length(i, &self))?);
+    }
+    Ok(DynamicSample { values })
+  }
+}
+
+impl<'de> Deserialize<'de> for DynamicSample {
+  fn deserialize<D: Deserializer<'de>>(d: D) -> Result<DynamicSample, D::Error> {
+    d.deserialize_tuple(schema().members.len(), SampleVisitor)
+  }
+}
+
+/// Reads samples from a file of JSON objects, or a JSON array of them.
+pub fn load_samples(path: &str) -> Result<Vec<DynamicSample>, String> {
+  let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+  let mut samples = Vec::new();
+  for value in serde_json::Deserializer::from_str(&text).into_iter::<Value>() {
+    match value.map_err(|e| format!("{}: {}", path, e))? {
+      Value::Array(items) => {
+        for item in &items {
+          samples.push(DynamicSample::from_json(item)?)
+        }
+      }
+      other => samples.push(DynamicSample::from_json(&other)?),
+    }
+  }
+  if samples.is_empty() {
+    return Err(format!("{}: no samples", path))
+  }
+  Ok(samples)
+}
+
+pub fn run_generic_publisher(domain_participant: &DomainParticipant, topic: Topic, qos: &QosPolicies,
+    samples: Vec<DynamicSample>, log_format: LogFormat, control: &Control) -> i32
+{
+  debug!("Generic publisher");
+  let topic_name = topic.get_name().to_string();
+  let mut events = Events::with_capacity(4);
+  let publisher = match control.on_error.create("Publisher", || domain_participant.create_publisher(qos)) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let mut writer = match control.on_error.create("DataWriter", ||
+    publisher.create_datawriter_CDR::<DynamicSample>(topic.clone(), None))
+  {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  control.poll.register(writer.as_status_evented(), STATUS_READY, Ready::readable(), PollOpt::edge())
+    .unwrap();
+
+  let mut next_sample = samples.iter().cycle();
+  let mut paused = false;
+  let mut write_interval = DEFAULT_WRITE_INTERVAL;
+  let mut next_write = Instant::now() + write_interval;
+  loop {
+    control.poll
+      .poll(&mut events, Some(next_write.saturating_duration_since(Instant::now())))
+      .unwrap();
+    for event in &events {
+      match event.token() {
+        STOP_PROGRAM => {
+          if control.stop_requested() {
+            println!("Done.");
+            return 0
+          }
+        }
+        COMMAND_READY => {
+          while let Ok(command) = control.command_receiver.try_recv() {
+            match command {
+              Command::Pause => paused = true,
+              Command::Resume => paused = false,
+              Command::TogglePause => paused = !paused,
+              Command::Rate(interval) => write_interval = interval,
+              Command::Exit(code) => {
+                println!("Done.");
+                return code
+              }
+              other => status_report::report_unsupported_command(log_format, "generic", &other),
+            }
+          }
+        }
+        STATUS_READY => {
+          while let Some(status) = writer.try_recv_status() {
+            status_report::report_writer_status(log_format, &topic_name, &status);
+          }
+        }
+        other_token => {
+          println!("Polled event is {:?}. WTF?", other_token);
+        }
+      }
+    }
+
+    let now = Instant::now();
+    if now < next_write {
+      continue
+    }
+    next_write = std::cmp::max(next_write + write_interval, now);
+    if paused {
+      continue
+    }
+    if let Some(sample) = next_sample.next() {
+      println!("{:10.10} {}", topic_name, sample.to_json());
+      if let Err(code) = control.on_error.write(|| report_write(&topic_name, writer.write(sample.clone(), None))) {
+        return code
+      }
+    }
+  }
+}
+
+pub fn run_generic_subscriber(domain_participant: &DomainParticipant, topic: Topic, qos: &QosPolicies,
+    log_format: LogFormat, control: &Control) -> i32
+{
+  debug!("Generic subscriber");
+  let topic_name = topic.get_name().to_string();
+  let mut events = Events::with_capacity(4);
+  let subscriber = match control.on_error.create("Subscriber", || domain_participant.create_subscriber(qos)) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let mut reader = match control.on_error.create("DataReader", || {
+    subscriber.create_datareader_CDR::<DynamicSample>(topic.clone(), Some(qos.clone()))
+  }) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  control.poll.register(&reader, READER_READY, Ready::readable(), PollOpt::edge())
+    .unwrap();
+  control.poll.register(reader.as_status_evented(), STATUS_READY, Ready::readable(), PollOpt::edge())
+    .unwrap();
+  loop {
+    control.poll.poll(&mut events, None).unwrap();
+    for event in &events {
+      match event.token() {
+        STOP_PROGRAM => {
+          if control.stop_requested() {
+            println!("Done.");
+            return 0
+          }
+        }
+        COMMAND_READY => {
+          while let Ok(command) = control.command_receiver.try_recv() {
+            match command {
+              Command::Exit(code) => {
+                println!("Done.");
+                return code
+              }
+              other => status_report::report_unsupported_command(log_format, "generic", &other),
+            }
+          }
+        }
+        READER_READY => loop {
+          match reader.take_next_sample() {
+            Ok(Some(sample)) => match sample.into_value() {
+              Ok(sample) => println!("{:10.10} {}", topic_name, sample.to_json()),
+              Err(key) => println!("Disposed key {:?}", key),
+            },
+            Ok(None) => break,
+            Err(e) => println!("DataReader error {:?}", e),
+          }
+        },
+        STATUS_READY => {
+          while let Some(status) = reader.try_recv_status() {
+            status_report::report_reader_status(log_format, &topic_name, &status);
+          }
+        }
+        other_token => {
+          println!("Polled event is {:?}. WTF?", other_token);
+        }
+      }
+    }
+  }
+}
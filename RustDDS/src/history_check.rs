@@ -0,0 +1,66 @@
+/// Checking the KeepLast history delivered to a late-joining reader.
+///
+/// A TRANSIENT_LOCAL writer with KeepLast(N) history keeps the last N samples
+/// of each instance, and a late-joining reader should get exactly those. The
+/// samples of an instance arriving within HISTORY_BURST of its first sample
+/// are taken to be the history, since live samples come at the write interval.
+/// Their counters must be consecutive, and there must be N of them, or fewer
+/// only if the writer had not written more (the first counter is 0).
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+const HISTORY_BURST: Duration = Duration::from_millis(100);
+
+struct Joined {
+  first_arrival: Instant,
+  counters: Vec<u32>,
+}
+
+pub struct HistoryCheck {
+  depth: usize,
+  /// instance -> history received so far, or None when already checked
+  instances: BTreeMap<String, Option<Joined>>,
+}
+
+impl HistoryCheck {
+  pub fn new(depth: usize) -> HistoryCheck {
+    HistoryCheck { depth, instances: BTreeMap::new() }
+  }
+
+  /// Records a received counter. Returns a description of the violation, if
+  /// this sample ended the history of the instance and it was wrong.
+  pub fn record(&mut self, instance: &str, counter: u32, now: Instant) -> Result<(), String> {
+    let joined = match self.instances.get_mut(instance) {
+      None => {
+        self.instances.insert(instance.to_string(), Some(Joined { first_arrival: now, counters: vec![counter] }));
+        return Ok(())
+      }
+      Some(None) => return Ok(()),
+      Some(Some(j)) if now - j.first_arrival <= HISTORY_BURST => {
+        j.counters.push(counter);
+        return Ok(())
+      }
+      Some(joined) => joined.take().unwrap(),
+    };
+    self.verdict(instance, &joined.counters)
+  }
+
+  fn verdict(&self, instance: &str, counters: &[u32]) -> Result<(), String> {
+    let first = counters[0];
+    let last = counters[counters.len() - 1];
+    let description = format!("{} samples (counters {}..{}) of {} on join, history depth {}",
+      counters.len(), first, last, instance, self.depth);
+    if counters.windows(2).any(|w| w[1] != w[0].wrapping_add(1)) {
+      return Err(format!("{}: counters not consecutive: {:?}", description, counters))
+    }
+    if counters.len() > self.depth {
+      return Err(format!("{}: more than history depth", description))
+    }
+    if counters.len() < self.depth && first != 0 {
+      return Err(format!("{}: fewer than history depth, although the writer had written earlier samples",
+        description))
+    }
+    println!("History check: {}: OK", description);
+    Ok(())
+  }
+}
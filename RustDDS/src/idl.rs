@@ -0,0 +1,358 @@
+/// Reading generic mode type definitions from IDL.
+///
+/// Only a small subset of IDL is understood: modules, and structs whose members
+/// are primitive types or (bounded) strings. Members are marked as key with the
+/// `@key` annotation, or the older `//@key` comment after the member. Anything
+/// else, such as typedefs, enums or nested structs, is rejected.
+use std::fs;
+
+use crate::generic::{Member, MemberType, TypeSchema};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Word(String),
+  Annotation(String),
+  KeyComment,
+  Punct(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+  let mut tokens = Vec::new();
+  let mut chars = text.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      c if c.is_whitespace() => (),
+      '/' if chars.peek() == Some(&'/') => {
+        let comment: String = chars.by_ref().take_while(|c| *c != '\n').collect();
+        if comment.trim_start_matches('/').trim() == "@key" {
+          tokens.push(Token::KeyComment)
+        }
+      }
+      '/' if chars.peek() == Some(&'*') => {
+        chars.next();
+        let mut prev = ' ';
+        loop {
+          match chars.next() {
+            Some('/') if prev == '*' => break,
+            Some(c) => prev = c,
+            None => return Err("Unterminated comment".to_string()),
+          }
+        }
+      }
+      '#' => {
+        // preprocessor directive
+        chars.by_ref().take_while(|c| *c != '\n').for_each(drop);
+      }
+      '@' => {
+        let mut name = String::new();
+        while let Some(c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+          name.push(*c);
+          chars.next();
+        }
+        if chars.peek() == Some(&'(') {
+          // annotation parameters are ignored
+          chars.by_ref().take_while(|c| *c != ')').for_each(drop);
+        }
+        tokens.push(Token::Annotation(name))
+      }
+      c if c.is_alphanumeric() || c == '_' => {
+        let mut word = c.to_string();
+        while let Some(c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+          word.push(*c);
+          chars.next();
+        }
+        tokens.push(Token::Word(word))
+      }
+      ':' if chars.peek() == Some(&':') => {
+        chars.next();
+        tokens.push(Token::Punct(':'))
+      }
+      '{' | '}' | ';' | '<' | '>' | ',' | '[' | ']' => tokens.push(Token::Punct(c)),
+      other => return Err(format!("Unexpected character '{}'", other)),
+    }
+  }
+  Ok(tokens)
+}
+
+struct Parser {
+  tokens: Vec<Token>,
+  pos: usize,
+  scope: Vec<String>,
+  structs: Vec<TypeSchema>,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.pos)
+  }
+
+  fn next(&mut self) -> Result<Token, String> {
+    let t = self.tokens.get(self.pos).cloned().ok_or_else(|| "Unexpected end of IDL".to_string())?;
+    self.pos += 1;
+    Ok(t)
+  }
+
+  fn word(&mut self) -> Result<String, String> {
+    match self.next()? {
+      Token::Word(w) => Ok(w),
+      other => Err(format!("Expected a name, got {:?}", other)),
+    }
+  }
+
+  fn expect(&mut self, c: char) -> Result<(), String> {
+    match self.next()? {
+      Token::Punct(p) if p == c => Ok(()),
+      other => Err(format!("Expected '{}', got {:?}", c, other)),
+    }
+  }
+
+  fn skip_annotations(&mut self) {
+    while let Some(Token::Annotation(_)) = self.peek() {
+      self.pos += 1;
+    }
+  }
+
+  /// Parses definitions until end of input or closing brace of a module.
+  fn definitions(&mut self) -> Result<(), String> {
+    loop {
+      self.skip_annotations();
+      match self.peek() {
+        None | Some(Token::Punct('}')) => return Ok(()),
+        Some(Token::Word(w)) if w == "module" => {
+          self.pos += 1;
+          let name = self.word()?;
+          self.expect('{')?;
+          self.scope.push(name);
+          self.definitions()?;
+          self.scope.pop();
+          self.expect('}')?;
+          self.expect(';')?;
+        }
+        Some(Token::Word(w)) if w == "struct" => {
+          self.pos += 1;
+          self.structure()?;
+        }
+        Some(other) => return Err(format!("Unsupported IDL definition starting with {:?}", other)),
+      }
+    }
+  }
+
+  fn structure(&mut self) -> Result<(), String> {
+    let name = self.word()?;
+    let mut qualified = self.scope.clone();
+    qualified.push(name);
+    let name = qualified.join("::");
+    if let Some(Token::Punct(';')) = self.peek() {
+      self.pos += 1; // forward declaration
+      return Ok(())
+    }
+    self.expect('{')?;
+    let mut members = Vec::new();
+    loop {
+      let mut key = false;
+      while let Some(Token::Annotation(a)) = self.peek() {
+        key |= a == "key" || a == "Key";
+        self.pos += 1;
+      }
+      if let Some(Token::Punct('}')) = self.peek() {
+        break
+      }
+      let member_type = self.member_type()?;
+      loop {
+        let member_name = self.word()?;
+        if let Some(Token::Punct('[')) = self.peek() {
+          return Err(format!("{}.{}: arrays are not supported", name, member_name))
+        }
+        members.push(Member { name: member_name, member_type, key });
+        match self.next()? {
+          Token::Punct(',') => (),
+          Token::Punct(';') => break,
+          other => return Err(format!("Expected ',' or ';' in struct {}, got {:?}", name, other)),
+        }
+      }
+      if let Some(Token::KeyComment) = self.peek() {
+        self.pos += 1;
+        if let Some(m) = members.last_mut() {
+          m.key = true;
+        }
+      }
+    }
+    self.expect('}')?;
+    self.expect(';')?;
+    if members.is_empty() {
+      return Err(format!("Struct {} has no members", name))
+    }
+    self.structs.push(TypeSchema { name, members });
+    Ok(())
+  }
+
+  fn member_type(&mut self) -> Result<MemberType, String> {
+    let first = self.word()?;
+    let words = match first.as_str() {
+      "unsigned" => {
+        let second = self.word()?;
+        if second == "long" && self.peek() == Some(&Token::Word("long".to_string())) {
+          self.pos += 1;
+          "unsigned long long".to_string()
+        } else {
+          format!("unsigned {}", second)
+        }
+      }
+      "long" if self.peek() == Some(&Token::Word("long".to_string())) => {
+        self.pos += 1;
+        "long long".to_string()
+      }
+      _ => first,
+    };
+    Ok(match words.as_str() {
+      "boolean" => MemberType::Boolean,
+      "octet" | "uint8" => MemberType::Octet,
+      "short" | "int16" => MemberType::Short,
+      "unsigned short" | "uint16" => MemberType::UnsignedShort,
+      "long" | "int32" => MemberType::Long,
+      "unsigned long" | "uint32" => MemberType::UnsignedLong,
+      "long long" | "int64" => MemberType::LongLong,
+      "unsigned long long" | "uint64" => MemberType::UnsignedLongLong,
+      "float" => MemberType::Float,
+      "double" => MemberType::Double,
+      "string" => {
+        if let Some(Token::Punct('<')) = self.peek() {
+          // The bound does not change the CDR encoding.
+          self.pos += 1;
+          self.word()?;
+          self.expect('>')?;
+        }
+        MemberType::String
+      }
+      other => return Err(format!("Unsupported member type '{}'", other)),
+    })
+  }
+}
+
+/// Parses IDL text and returns all structs defined in it.
+pub fn parse_idl(text: &str) -> Result<Vec<TypeSchema>, String> {
+  let mut parser = Parser { tokens: tokenize(text)?, pos: 0, scope: Vec::new(), structs: Vec::new() };
+  parser.definitions()?;
+  if parser.pos < parser.tokens.len() {
+    return Err(format!("Unexpected {:?}", parser.tokens[parser.pos]))
+  }
+  Ok(parser.structs)
+}
+
+/// Loads the struct `type_name` from an IDL file. The name may be given with or
+/// without module prefix. If no name is given, the file must contain only one struct.
+pub fn load_idl_type(path: &str, type_name: Option<&str>) -> Result<TypeSchema, String> {
+  let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+  let structs = parse_idl(&text).map_err(|e| format!("{}: {}", path, e))?;
+  let names = || structs.iter().map(|s| s.name.as_str()).collect::<Vec<_>>().join(" ");
+  let mut found = structs.iter().filter(|s| match type_name {
+    Some(n) => s.name == n || s.name.rsplit("::").next() == Some(n),
+    None => true,
+  });
+  match (found.next(), found.next(), type_name) {
+    (Some(s), None, _) => Ok(s.clone()),
+    (None, _, Some(n)) => Err(format!("{}: no struct {}. Found: {}", path, n, names())),
+    (None, _, None) => Err(format!("{}: no structs found", path)),
+    (Some(_), Some(_), _) => Err(format!("{}: give one of these with --idl-type: {}", path, names())),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn members(schema: &TypeSchema) -> Vec<(&str, MemberType, bool)> {
+    schema.members.iter().map( |m| (m.name.as_str(), m.member_type, m.key) ).collect()
+  }
+
+  #[test]
+  fn shape_type() {
+    let structs = parse_idl("
+      #include <something.idl>
+      /* Shapes demo type */
+      struct ShapeType {
+        @key string<128> color;
+        long x;
+        long y;
+        long shapesize; // size in pixels
+      };").unwrap();
+    assert_eq!(structs.len(), 1);
+    assert_eq!(structs[0].name, "ShapeType");
+    assert_eq!(members(&structs[0]), vec![
+      ("color", MemberType::String, true),
+      ("x", MemberType::Long, false),
+      ("y", MemberType::Long, false),
+      ("shapesize", MemberType::Long, false),
+    ]);
+  }
+
+  #[test]
+  fn member_types() {
+    let cases = [
+      ("boolean", MemberType::Boolean),
+      ("octet", MemberType::Octet),
+      ("uint8", MemberType::Octet),
+      ("short", MemberType::Short),
+      ("unsigned short", MemberType::UnsignedShort),
+      ("long", MemberType::Long),
+      ("int32", MemberType::Long),
+      ("unsigned long", MemberType::UnsignedLong),
+      ("long long", MemberType::LongLong),
+      ("unsigned long long", MemberType::UnsignedLongLong),
+      ("uint64", MemberType::UnsignedLongLong),
+      ("float", MemberType::Float),
+      ("double", MemberType::Double),
+      ("string", MemberType::String),
+      ("string<16>", MemberType::String),
+    ];
+    for (idl_type, expected) in &cases {
+      let structs = parse_idl(&format!("struct T {{ {} m; }};", idl_type)).unwrap();
+      assert_eq!(structs[0].members[0].member_type, *expected, "{}", idl_type);
+    }
+  }
+
+  #[test]
+  fn modules_and_keys() {
+    let structs = parse_idl("
+      module a { module b {
+        struct Forward;
+        @topic
+        struct T {
+          @key(TRUE) long id;
+          long k2; //@key
+          short p, q;
+        };
+      }; };
+      struct U { double v; };").unwrap();
+    let names: Vec<&str> = structs.iter().map( |s| s.name.as_str() ).collect();
+    assert_eq!(names, vec!["a::b::T", "U"]);
+    assert_eq!(members(&structs[0]), vec![
+      ("id", MemberType::Long, true),
+      ("k2", MemberType::Long, true),
+      ("p", MemberType::Short, false),
+      ("q", MemberType::Short, false),
+    ]);
+    assert!(!structs[1].is_keyed());
+  }
+
+  #[test]
+  fn errors() {
+    let cases = [
+      "struct T { long x[3]; };",
+      "struct T { sequence<long> s; };",
+      "struct T { Inner i; };",
+      "struct T { };",
+      "struct T { long x; }",
+      "struct T { long x }; ",
+      "enum E { A, B };",
+      "typedef long L;",
+      "module m { struct T { long x; };",
+      "/* unterminated",
+      "struct T { long x; }; }",
+      "struct T { long $x; };",
+    ];
+    for idl in &cases {
+      assert!(parse_idl(idl).is_err(), "{}", idl);
+    }
+  }
+}
@@ -0,0 +1,157 @@
+/// Artificial impairment of outgoing writes: random drops and delays.
+///
+/// RustDDS does not let us touch the RTPS messages, so impairment is applied
+/// to application writes. A dropped write never reaches the DataWriter, which
+/// to the remote reader looks like a writer that skipped samples. A delayed
+/// write is done later, possibly after samples written after it, if jitter is
+/// large compared to the write interval. Skipped counters are like drops, but
+/// chosen: the RTPS sequence numbers stay contiguous while the application
+/// counters have holes, which separates application-level loss accounting from
+/// RTPS gap handling in remote readers.
+use std::collections::{BTreeSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::random;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Impairment {
+  /// Probability of dropping a write, 0..1
+  pub drop_rate: f64,
+  pub delay: Duration,
+  pub jitter: Duration,
+}
+
+impl Impairment {
+  /// Parses drop rate in percent.
+  pub fn parse_drop_rate(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+      Ok(pct) if (0.0..=100.0).contains(&pct) => Ok(pct / 100.0),
+      _ => Err(format!("Expected percentage 0..100, got '{}'", s)),
+    }
+  }
+
+  /// Parses "delay" or "delay/jitter" in milliseconds, e.g. "100/20".
+  pub fn parse_delay(s: &str) -> Result<(Duration, Duration), String> {
+    let ms = |x: &str| {
+      x.trim().parse::<u64>().map(Duration::from_millis).map_err(|_| format!("Expected milliseconds, got '{}'", x))
+    };
+    match s.split_once('/') {
+      Some((delay, jitter)) => Ok((ms(delay)?, ms(jitter)?)),
+      None => Ok((ms(s)?, Duration::from_millis(0))),
+    }
+  }
+
+  /// Parses a list of counters and inclusive ranges, e.g. "5,10-12,100".
+  pub fn parse_counter_list(s: &str) -> Result<BTreeSet<u32>, String> {
+    let number = |x: &str| x.trim().parse::<u32>().map_err(|_| format!("Expected counter value, got '{}'", x));
+    let mut counters = BTreeSet::new();
+    for item in s.split(',').filter(|i| !i.trim().is_empty()) {
+      match item.split_once('-') {
+        Some((first, last)) => match (number(first)?, number(last)?) {
+          (first, last) if first <= last => counters.extend(first..=last),
+          _ => return Err(format!("Range '{}' is empty", item)),
+        },
+        None => { counters.insert(number(item)?); }
+      }
+    }
+    Ok(counters)
+  }
+
+  pub fn is_active(&self) -> bool {
+    self.drop_rate > 0.0 || self.delay > Duration::from_millis(0) || self.jitter > Duration::from_millis(0)
+  }
+
+  pub fn should_drop(&self) -> bool {
+    self.drop_rate > 0.0 && random::chance(self.drop_rate)
+  }
+
+  /// Delay for one write: delay plus or minus up to jitter, but not negative.
+  pub fn next_delay(&self) -> Duration {
+    if self.jitter == Duration::from_millis(0) {
+      return self.delay
+    }
+    let jitter = random::float_between(-self.jitter.as_secs_f64(), self.jitter.as_secs_f64());
+    Duration::from_secs_f64((self.delay.as_secs_f64() + jitter).max(0.0))
+  }
+}
+
+/// Writes waiting for their time, in order of due time.
+#[derive(Default)]
+pub struct DelayQueue<T> {
+  queue: VecDeque<(Instant, T)>,
+}
+
+impl<T> DelayQueue<T> {
+  pub fn new() -> DelayQueue<T> {
+    DelayQueue { queue: VecDeque::new() }
+  }
+
+  pub fn push(&mut self, due: Instant, item: T) {
+    let pos = self.queue.iter().position(|(d, _)| *d > due).unwrap_or(self.queue.len());
+    self.queue.insert(pos, (due, item));
+  }
+
+  pub fn len(&self) -> usize {
+    self.queue.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.queue.is_empty()
+  }
+
+  pub fn next_due(&self) -> Option<Instant> {
+    self.queue.front().map(|(due, _)| *due)
+  }
+
+  /// Removes and returns the next item, if it is due.
+  pub fn pop_due(&mut self, now: Instant) -> Option<T> {
+    match self.queue.front() {
+      Some((due, _)) if *due <= now => self.queue.pop_front().map(|(_, item)| item),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn delays() {
+    let ms = Duration::from_millis;
+    let cases = [
+      ("100", Some((ms(100), ms(0)))),
+      ("100/20", Some((ms(100), ms(20)))),
+      (" 0 / 5 ", Some((ms(0), ms(5)))),
+      ("", None),
+      ("100/", None),
+      ("/20", None),
+      ("-5", None),
+      ("1.5", None),
+      ("100/20/3", None),
+    ];
+    for (text, expected) in &cases {
+      assert_eq!(Impairment::parse_delay(text).ok(), *expected, "{}", text);
+    }
+  }
+
+  #[test]
+  fn counter_lists() {
+    let cases: &[(&str, Option<&[u32]>)] = &[
+      ("5", Some(&[5])),
+      ("5,10-12,100", Some(&[5, 10, 11, 12, 100])),
+      (" 3 , 1-2 ,", Some(&[1, 2, 3])),
+      ("7-7,7", Some(&[7])),
+      ("", Some(&[])),
+      ("12-10", None),
+      ("a", None),
+      ("1-", None),
+      ("-1", None),
+      ("1-2-3", None),
+    ];
+    for (text, expected) in cases {
+      let parsed = Impairment::parse_counter_list(text).ok();
+      assert_eq!(parsed.as_ref().map( |c| c.iter().cloned().collect::<Vec<_>>() ).as_deref(), *expected, "{}", text);
+    }
+  }
+}
@@ -0,0 +1,76 @@
+/// Detecting topics that remote participants use with a different type.
+///
+/// A remote endpoint on one of our topic names, but with another type name,
+/// should raise the InconsistentTopic status. RustDDS does not have that
+/// status, and the endpoints just never match. We look at the topics RustDDS
+/// has discovered instead, and report each remote (topic, type, participant)
+/// combination that disagrees with ours once.
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+use rustdds::dds::traits::TopicDescription;
+use rustdds::dds::{DomainParticipant, Topic};
+use serde_json::json;
+
+use crate::status_report::{self, format_guid, LogFormat};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct InconsistentTopics {
+  /// (topic name, our type name)
+  topics: Vec<(String, String)>,
+  /// (topic name, remote type name, remote participant) already reported
+  reported: BTreeSet<(String, String, String)>,
+  next_check: Instant,
+}
+
+impl InconsistentTopics {
+  pub fn new(topics: &[Topic]) -> InconsistentTopics {
+    InconsistentTopics {
+      topics: topics.iter().map(|t| (t.get_name().to_string(), t.get_type().name().to_string())).collect(),
+      reported: BTreeSet::new(),
+      next_check: Instant::now(),
+    }
+  }
+
+  /// Checks discovered topics, if due, and reports new inconsistencies.
+  pub fn check(&mut self, format: LogFormat, domain_participant: &DomainParticipant) {
+    let now = Instant::now();
+    if now < self.next_check {
+      return
+    }
+    self.next_check = now + CHECK_INTERVAL;
+    for discovered in domain_participant.get_discovered_topics() {
+      let data = discovered.topic_data;
+      let (name, remote_type) = match (data.name, data.type_name) {
+        (Some(name), Some(type_name)) => (name, type_name),
+        _ => continue,
+      };
+      let local_type = match self.topics.iter().find(|(t, _)| *t == name) {
+        Some((_, local_type)) if *local_type != remote_type => local_type.clone(),
+        _ => continue,
+      };
+      let participant = data.key
+        .map_or_else(|| "unknown".to_string(), |guid| format_guid(&guid)[..24].to_string());
+      if !self.reported.insert((name.clone(), remote_type.clone(), participant.clone())) {
+        continue
+      }
+      match format {
+        LogFormat::Text | LogFormat::Compat => println!(
+          "Topic {} status: InconsistentTopic: remote type {} from participant {}, local type {}",
+          name, remote_type, participant, local_type
+        ),
+        LogFormat::Json => status_report::print_json_event(
+          "Topic",
+          &name,
+          "inconsistent_topic",
+          json!({
+            "remote_type": remote_type,
+            "remote_participant": participant,
+            "local_type": local_type,
+          }),
+        ),
+      }
+    }
+  }
+}
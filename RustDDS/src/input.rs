@@ -0,0 +1,121 @@
+/// Exact sample sequences to publish, read from a file.
+///
+/// CSV files have one sample per line: color, x, y, size, and optionally a
+/// delay in milliseconds before writing the next sample. Empty lines, lines
+/// starting with '#' and a "color,x,y,size" header line are skipped.
+///
+/// JSON files (name ending in .json) contain an array of objects like
+///
+///   [ { "color": "RED", "x": 10, "y": 20, "size": 30, "delay_ms": 100 } ]
+///
+/// Without a delay, samples are written at the normal write interval.
+use std::fs;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::Shape;
+
+#[derive(Debug, Clone)]
+pub struct InputSample {
+  pub shape: Shape,
+  pub delay: Option<Duration>,
+}
+
+#[derive(Deserialize)]
+struct JsonSample {
+  color: String,
+  x: i32,
+  y: i32,
+  size: i32,
+  delay_ms: Option<u64>,
+}
+
+pub fn load_input(path: &str) -> Result<Vec<InputSample>, String> {
+  let text = fs::read_to_string(path).map_err(|e| format!("Cannot read input {}: {}", path, e))?;
+  let samples = if path.ends_with(".json") { parse_json(&text)? } else { parse_csv(&text)? };
+  if samples.is_empty() {
+    return Err(format!("No samples in input {}", path))
+  }
+  Ok(samples)
+}
+
+fn parse_json(text: &str) -> Result<Vec<InputSample>, String> {
+  let samples: Vec<JsonSample> = serde_json::from_str(text).map_err(|e| format!("Bad JSON input: {}", e))?;
+  Ok(samples.into_iter().map(|s| InputSample {
+    shape: Shape { color: s.color, x: s.x, y: s.y, shapesize: s.size },
+    delay: s.delay_ms.map(Duration::from_millis),
+  }).collect())
+}
+
+fn parse_csv(text: &str) -> Result<Vec<InputSample>, String> {
+  let mut samples = Vec::new();
+  for (i, line) in text.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') || line.starts_with("color,") {
+      continue
+    }
+    samples.push(parse_csv_line(line).map_err(|e| format!("Input line {}: {}", i + 1, e))?);
+  }
+  Ok(samples)
+}
+
+fn parse_csv_line(line: &str) -> Result<InputSample, String> {
+  let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+  let number = |i: usize| fields[i].parse::<i32>().map_err(|_| format!("Expected number, got '{}'", fields[i]));
+  match fields.len() {
+    4 | 5 => Ok(InputSample {
+      shape: Shape { color: fields[0].to_string(), x: number(1)?, y: number(2)?, shapesize: number(3)? },
+      delay: match fields.get(4) {
+        Some(ms) => Some(Duration::from_millis(
+          ms.parse::<u64>().map_err(|_| format!("Expected delay in milliseconds, got '{}'", ms))?)),
+        None => None,
+      },
+    }),
+    _ => Err(format!("Expected color,x,y,size[,delay_ms], got '{}'", line)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn fields(samples: &[InputSample]) -> Vec<(&str, i32, i32, i32, Option<u64>)> {
+    samples.iter().map( |s| (s.shape.color.as_str(), s.shape.x, s.shape.y, s.shape.shapesize,
+      s.delay.map( |d| d.as_millis() as u64 )) ).collect()
+  }
+
+  #[test]
+  fn csv() {
+    let samples = parse_csv("
+      color,x,y,size,delay_ms
+      # comment
+      RED, 10, 20, 30
+      BLUE,-5,0,25,100
+    ").unwrap();
+    assert_eq!(fields(&samples), vec![("RED", 10, 20, 30, None), ("BLUE", -5, 0, 25, Some(100))]);
+    let errors = [
+      ("RED,10,20", "Input line 1"),
+      ("RED,10,20,30,40,50", "Input line 1"),
+      ("\nRED,x,20,30", "Input line 2"),
+      ("RED,10,20,30,-1", "Input line 1"),
+      ("RED,10,20,1.5", "Input line 1"),
+    ];
+    for (text, prefix) in &errors {
+      let e = parse_csv(text).unwrap_err();
+      assert!(e.starts_with(prefix), "{:?}: {}", text, e);
+    }
+  }
+
+  #[test]
+  fn json() {
+    let samples = parse_json(r#"[
+      { "color": "RED", "x": 10, "y": 20, "size": 30, "delay_ms": 100 },
+      { "color": "BLUE", "x": 1, "y": 2, "size": 3 }
+    ]"#).unwrap();
+    assert_eq!(fields(&samples), vec![("RED", 10, 20, 30, Some(100)), ("BLUE", 1, 2, 3, None)]);
+    for text in &[r#"[{ "color": "RED", "x": 10, "y": 20 }]"#, r#"{ "color": "RED" }"#, "[", r#"[{ "x": "a" }]"#] {
+      assert!(parse_json(text).is_err(), "{}", text);
+    }
+  }
+}
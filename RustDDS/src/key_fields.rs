@@ -0,0 +1,42 @@
+/// ShapeType with a key of two members, color and shapesize:
+///
+/// struct ShapeType { @key string color; long x; long y; @key long shapesize; }
+///
+/// Samples serialize exactly like the usual ShapeType, so only the instance
+/// key differs. Shapes of the same color but different size are then separate
+/// instances, and both ends must hash and serialize the two-member key the same
+/// way for disposals to reach the right instance.
+use rustdds::dds::traits::{Key, Keyed};
+use serde::{Deserialize, Serialize};
+
+use crate::{Shape, ShapeData};
+
+/// Key members in declaration order, as they are serialized for the key hash.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ColorSizeKey {
+  color: String,
+  shapesize: i32,
+}
+
+impl Key for ColorSizeKey {}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(transparent)]
+pub struct ColorSizeKeyedShape {
+  shape: Shape,
+}
+
+impl Keyed for ColorSizeKeyedShape {
+  type K = ColorSizeKey;
+  fn get_key(&self) -> ColorSizeKey {
+    ColorSizeKey { color: self.shape.color.clone(), shapesize: self.shape.shapesize }
+  }
+}
+
+impl ShapeData for ColorSizeKeyedShape {
+  fn from_shape(shape: Shape, _counter: u32) -> Self { ColorSizeKeyedShape { shape } }
+  fn shape(&self) -> &Shape { &self.shape }
+  fn counter(&self) -> Option<u32> { None }
+  fn key_color(key: &ColorSizeKey) -> String { key.color.clone() }
+  fn key_name(key: &ColorSizeKey) -> String { format!("{}/{}", key.color, key.shapesize) }
+}
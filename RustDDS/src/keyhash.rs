@@ -0,0 +1,118 @@
+/// Instance key hashes of ShapeType, for checking long keys.
+///
+/// The key hash is the key serialized as big-endian CDR, zero-padded to 16
+/// bytes if the maximum serialized size of the key type fits, or else the MD5
+/// digest of it (RTPS 9.6.3.8). The color key of ShapeType is a string<128>,
+/// so every ShapeType key hash is an MD5 digest, however short the color.
+/// RustDDS does not show received key hashes, so the hashes computed here are
+/// for comparison with captures. What we can check is whether disposals from
+/// the remote writer reach the instances its samples created.
+pub const LONG_KEY_SUFFIX: &str = "_WITH_A_LONG_KEY";
+/// With --long-key, the publisher disposes its instances after this many samples.
+pub const DISPOSE_EVERY: u32 = 50;
+
+/// Serialized key of ShapeType: string length including terminating null, then
+/// the characters and the null.
+fn serialized_key(color: &str) -> Vec<u8> {
+  let mut key = ((color.len() + 1) as u32).to_be_bytes().to_vec();
+  key.extend_from_slice(color.as_bytes());
+  key.push(0);
+  key
+}
+
+pub fn key_hash(color: &str) -> [u8; 16] {
+  md5(&serialized_key(color))
+}
+
+pub fn format_hash(hash: &[u8; 16]) -> String {
+  hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+const S: [u32; 64] = [
+  7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+  4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15,
+  21,
+];
+
+/// MD5 digest (RFC 1321)
+fn md5(data: &[u8]) -> [u8; 16] {
+  let k: Vec<u32> = (1..=64).map(|i: i32| (f64::from(i).sin().abs() * 4294967296.0) as u32).collect();
+  let mut message = data.to_vec();
+  message.push(0x80);
+  while message.len() % 64 != 56 {
+    message.push(0);
+  }
+  message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+  let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+  for chunk in message.chunks(64) {
+    let m: Vec<u32> =
+      chunk.chunks(4).map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]])).collect();
+    let [mut a, mut b, mut c, mut d] = state;
+    for i in 0..64 {
+      let (f, g) = match i / 16 {
+        0 => ((b & c) | (!b & d), i),
+        1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+        2 => (b ^ c ^ d, (3 * i + 5) % 16),
+        _ => (c ^ (b | !d), (7 * i) % 16),
+      };
+      let f = f.wrapping_add(a).wrapping_add(k[i]).wrapping_add(m[g]);
+      a = d;
+      d = c;
+      c = b;
+      b = b.wrapping_add(f.rotate_left(S[i]));
+    }
+    state = [state[0].wrapping_add(a), state[1].wrapping_add(b), state[2].wrapping_add(c), state[3].wrapping_add(d)];
+  }
+  let mut digest = [0u8; 16];
+  for (i, word) in state.iter().enumerate() {
+    digest[4 * i..4 * i + 4].copy_from_slice(&word.to_le_bytes());
+  }
+  digest
+}
+
+/// Counts of received disposals by whether they matched an instance seen in samples.
+#[derive(Default)]
+pub struct DisposeCheck {
+  pub matched: u64,
+  pub unmatched: u64,
+}
+
+impl DisposeCheck {
+  pub fn report(&self) -> String {
+    format!(
+      "Key check: {} disposals matched received instances, {} did not{}",
+      self.matched,
+      self.unmatched,
+      if self.unmatched > 0 { " (KEY HASH MISMATCH)" } else { "" }
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn md5_rfc1321_test_suite() {
+    let cases = [
+      ("", "d41d8cd98f00b204e9800998ecf8427e"),
+      ("a", "0cc175b9c0f1b6a831c399e269772661"),
+      ("abc", "900150983cd24fb0d6963f7d28e17f72"),
+      ("message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+      ("abcdefghijklmnopqrstuvwxyz", "c3fcd3d76192e4007dfb496cca67e13b"),
+      ("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789", "d174ab98d277d9f5a5611c2c9f419d9f"),
+      ("12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+        "57edf4a22be3c955ac49da2e2107b67a"),
+    ];
+    for (input, digest) in cases.iter() {
+      assert_eq!(format_hash(&md5(input.as_bytes())), *digest, "MD5 of {:?}", input);
+    }
+  }
+
+  #[test]
+  fn short_colors_are_hashed_too() {
+    assert_eq!(serialized_key("RED"), vec![0, 0, 0, 4, b'R', b'E', b'D', 0]);
+    assert_eq!(key_hash("RED"), md5(&[0, 0, 0, 4, b'R', b'E', b'D', 0]));
+  }
+}
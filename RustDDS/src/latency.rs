@@ -0,0 +1,67 @@
+/// One-way latency from source timestamps: reception time minus the source
+/// timestamp the remote writer put on the sample.
+///
+/// Between hosts this is only meaningful with the clock offset known, e.g.
+/// from PTP or NTP. The offset is how much the remote clock is ahead of ours,
+/// and it is added to each measured latency.
+use std::fs;
+
+use rustdds::dds::data_types::Timestamp;
+
+pub struct LatencyStats {
+  /// remote clock - local clock, in microseconds
+  clock_offset_us: Option<f64>,
+  count: u64,
+  sum_us: f64,
+  min_us: f64,
+  max_us: f64,
+  without_timestamp: u64,
+}
+
+/// Parses a clock offset in microseconds, or reads it from a file whose first
+/// word is the offset in microseconds.
+pub fn parse_clock_offset(arg: &str) -> Result<f64, String> {
+  if let Ok(us) = arg.parse::<f64>() {
+    return Ok(us)
+  }
+  let text = fs::read_to_string(arg).map_err(|e| format!("Cannot read clock offset file {}: {}", arg, e))?;
+  let word = text.split_whitespace().next().unwrap_or("");
+  word.parse::<f64>().map_err(|_| format!("Expected offset in microseconds in {}, got '{}'", arg, word))
+}
+
+impl LatencyStats {
+  pub fn new(clock_offset_us: Option<f64>) -> LatencyStats {
+    LatencyStats { clock_offset_us, count: 0, sum_us: 0.0, min_us: f64::MAX, max_us: f64::MIN, without_timestamp: 0 }
+  }
+
+  pub fn record(&mut self, source_timestamp: Option<Timestamp>) {
+    let source_timestamp = match source_timestamp {
+      Some(t) => t,
+      None => {
+        self.without_timestamp += 1;
+        return
+      }
+    };
+    let latency_us = Timestamp::now().duration_since(source_timestamp).to_nanoseconds() as f64 / 1000.0
+      + self.clock_offset_us.unwrap_or(0.0);
+    self.count += 1;
+    self.sum_us += latency_us;
+    self.min_us = self.min_us.min(latency_us);
+    self.max_us = self.max_us.max(latency_us);
+  }
+
+  pub fn report(&self) -> String {
+    let correction = match self.clock_offset_us {
+      Some(us) => format!("corrected by clock offset {:+.1} us", us),
+      None => "not corrected for clock offset".to_string(),
+    };
+    let missing = if self.without_timestamp > 0 {
+      format!(", {} samples without source timestamp", self.without_timestamp)
+    } else { String::new() };
+    if self.count == 0 {
+      return format!("One-way latency: no samples, {}{}", correction, missing)
+    }
+    format!("One-way latency: {} samples, min {:.1} us, avg {:.1} us, max {:.1} us, {}{}", self.count, self.min_us,
+      self.sum_us / self.count as f64, self.max_us, correction, missing)
+  }
+}
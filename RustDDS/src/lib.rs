@@ -0,0 +1,499 @@
+/// Interoperability test library for RustDDS: publisher and subscriber roles
+/// and the other test modes, run from a Config. The command line program is a
+/// thin shell around cli, and other harnesses can build a Config themselves.
+use rustdds::dds::{DomainParticipant, Topic};
+use rustdds::dds::traits::TopicDescription;
+use rustdds::dds::traits::Keyed;
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+
+use mio::*; // polling
+use mio_extras::channel; // pollable channel
+
+use std::io;
+use std::time::Duration;
+
+mod ack_trace;
+mod backpressure;
+pub mod batch;
+mod builtin_topics;
+mod capabilities;
+mod capture;
+mod cdr_dump;
+pub mod cli;
+mod coherent;
+mod compare;
+pub mod config;
+pub mod console;
+pub mod expect;
+pub mod failover;
+pub mod filter;
+pub mod generic;
+mod history_check;
+mod idl;
+pub mod impairment;
+mod inconsistent_topic;
+pub mod input;
+mod key_fields;
+mod keyhash;
+mod latency;
+#[cfg_attr(not(feature = "full-logging"), path = "logging_slim.rs")]
+mod logging;
+mod loss_stats;
+mod match_wait;
+mod matrix;
+pub mod mutate;
+pub mod on_error;
+mod ownership;
+mod pacing;
+pub mod pattern;
+mod protocol_stats;
+pub mod publisher;
+pub mod qos;
+mod random;
+mod reorder;
+mod scenario;
+pub mod script;
+mod selftest;
+mod signals;
+mod soak;
+pub mod source_time;
+pub mod spdp;
+mod state;
+pub mod status_report;
+mod stress;
+pub mod subscriber;
+mod timeline;
+mod topic_watch;
+mod visual;
+mod writer_history;
+use config::{Config, Mode};
+use console::Command;
+use mutate::TypeMutation;
+use on_error::OnError;
+use publisher::run_publisher;
+use status_report::LogFormat;
+use subscriber::run_subscriber;
+
+#[derive(Serialize,Deserialize,Clone,Default,Debug)]
+pub struct Shape {
+	pub color: String,
+	pub x: i32,
+	pub y: i32,
+	pub shapesize: i32,
+}
+
+impl Keyed for Shape {
+	type K = String;
+	fn get_key(&self) -> String {
+		self.color.clone()
+	}
+}
+
+/// ShapeType followed by a sample counter. CDR encodes the nested struct inline,
+/// so on the wire this is just ShapeType with one extra trailing field. Both
+/// ends must agree to use it.
+#[derive(Serialize,Deserialize,Clone)]
+pub struct CountedShape {
+	pub shape: Shape,
+	pub counter: u32,
+}
+
+impl Keyed for CountedShape {
+	type K = String;
+	fn get_key(&self) -> String {
+		self.shape.color.clone()
+	}
+}
+
+/// Common interface of the data types we can publish and subscribe as "ShapeType".
+pub trait ShapeData: Keyed + Serialize + DeserializeOwned + Clone + 'static {
+	fn from_shape(shape: Shape, counter: u32) -> Self;
+	fn shape(&self) -> &Shape;
+	fn counter(&self) -> Option<u32>;
+	/// Color in an instance key
+	fn key_color(key: &Self::K) -> String;
+	/// Instance key for output, if there is more to it than the color
+	fn key_name(key: &Self::K) -> String { Self::key_color(key) }
+}
+
+impl ShapeData for Shape {
+	fn from_shape(shape: Shape, _counter: u32) -> Self { shape }
+	fn shape(&self) -> &Shape { self }
+	fn counter(&self) -> Option<u32> { None }
+	fn key_color(key: &String) -> String { key.clone() }
+}
+
+impl ShapeData for CountedShape {
+	fn from_shape(shape: Shape, counter: u32) -> Self { CountedShape { shape, counter } }
+	fn shape(&self) -> &Shape { &self.shape }
+	fn counter(&self) -> Option<u32> { Some(self.counter) }
+	fn key_color(key: &String) -> String { key.clone() }
+}
+
+/// Colors of the shapes demo
+pub const SHAPE_COLORS: &[&str] = &["PURPLE", "BLUE", "RED", "GREEN", "YELLOW", "CYAN", "MAGENTA", "ORANGE"];
+
+/// The color normalized to the uppercase Shapes demo color. Subscribers of
+/// some vendors key on the exact uppercase names, so "blue" would silently be
+/// an instance of its own there. With any_color, any color is taken as given.
+pub fn shape_color(color: &str, any_color: bool) -> Result<String, String> {
+  if any_color {
+    return Ok(color.to_string())
+  }
+  SHAPE_COLORS.iter().find( |c| c.eq_ignore_ascii_case(color) ).map( |c| c.to_string() ).ok_or_else( ||
+    format!("\"{}\" is not one of {}. Use --any-color for other colors.", color, SHAPE_COLORS.join(", ")) )
+}
+
+const DA_WIDTH: i32 = 240;
+const DA_HEIGHT: i32 = 270;
+
+const STOP_PROGRAM: Token = Token(0);
+const READER_READY: Token = Token(1);
+const STATUS_READY: Token = Token(2);
+const COMMAND_READY: Token = Token(3);
+// READER_READY and STATUS_READY are per endpoint, see endpoint_token()
+const ENDPOINT_TOKEN_STRIDE: usize = 1000;
+
+const LOSS_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+pub const EXIT_EXPECTATION_FAILED: i32 = 1;
+/// Diagnostics or the event loop could not be set up, so nothing was tested.
+pub const EXIT_SETUP_FAILED: i32 = 2;
+pub const EXIT_SOAK_FAILED: i32 = 4;
+// Not a process exit code: the run functions return this to have the
+// DomainParticipant deleted and created again.
+const RESTART_PARTICIPANT: i32 = -1;
+
+const DEFAULT_WRITE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Runs the configured test until it is done or stopped. The DomainParticipant
+/// is created again after each restart. Returns the process exit code, or an
+/// error if the test could not be set up.
+pub fn run(config: Config) -> Result<i32, String> {
+  let result = run_participants(&config);
+  timeline::finish();
+  result
+}
+
+fn run_participants(config: &Config) -> Result<i32, String> {
+  let domain_id = config.domain_id;
+  let diagnostics = &config.diagnostics;
+  if let Some(timeline_file) = &diagnostics.timeline {
+    timeline::start(timeline_file)
+      .map_err(|e| format!("Cannot write timeline to {}: {:?}", timeline_file, e))?;
+  }
+  if let Some(capture_file) = &diagnostics.capture {
+    capture::start_multicast_capture(capture_file, domain_id)
+      .map_err(|e| format!("Cannot start capture to {}: {:?}", capture_file, e))?;
+  }
+  let protocol_stats = if diagnostics.protocol_stats {
+    Some(protocol_stats::start_protocol_stats(domain_id)
+      .map_err(|e| format!("Cannot listen to user traffic: {:?}", e))?)
+  } else { None };
+  if let Some(count) = diagnostics.dump_incoming_cdr {
+    cdr_dump::start_incoming_dump(domain_id, count)
+      .map_err(|e| format!("Cannot listen to user traffic: {:?}", e))?;
+  }
+  if config.subscriber.builtin_topics {
+    println!("Built-in topics: DCPSParticipant and DCPSTopic. \
+              DCPSPublication and DCPSSubscription readers are not yet implemented in RustDDS.");
+  }
+
+  let qos = config.qos.policies();
+
+  let on_error = config.on_error;
+  let (mut control, stop_sender, command_sender) = Control::new(on_error)
+    .map_err(|e| format!("Cannot set up event loop: {:?}", e))?;
+
+  // Set Ctrl-C handler. Ctrl-C and SIGTERM both stop through stop_sender.
+  if config.ctrlc {
+    set_ctrlc_handler(stop_sender.clone());
+  } else {
+    println!("No Ctrl-C handler. Send SIGTERM or use exit command to quit.");
+  }
+
+  console::spawn_stdin_reader(command_sender.clone(), config.any_color);
+  signals::spawn_signal_handler(command_sender.clone(), stop_sender)
+    .unwrap_or_else(|e| println!("Cannot handle signals: {:?}", e));
+  if let Some(actions) = config.script.clone() {
+    script::spawn_script_runner(actions, command_sender.clone());
+  }
+
+  control.discovered = if diagnostics.locators || diagnostics.vendors || diagnostics.unsupported_qos
+      || diagnostics.timeline.is_some() || config.subscriber.wait_for_match || config.subscriber.builtin_topics {
+    Some(spdp::start_spdp_watch(domain_id, diagnostics.vendors, diagnostics.unsupported_qos)
+      .map_err(|e| format!("Cannot listen to discovery traffic: {:?}", e))?)
+  } else { None };
+  if let Some((up, down)) = config.restart {
+    spawn_restart_timer(up, down, command_sender);
+  }
+
+  let color = config.color.as_str();
+  let counter = config.counter;
+  loop {
+    let domain_participant = match on_error.create_with_backoff("DomainParticipant", config.participant_retries, ||
+      DomainParticipant::new(domain_id))
+    {
+      Ok(participant) => participant,
+      Err(code) => return Ok(code),
+    };
+    status_report::print_guid("DomainParticipant", &domain_participant.get_guid(), None);
+
+    let topics: Result<Vec<Topic>, i32> = config.topic_names.iter().map( |topic_name| {
+      let topic = on_error.create("Topic", ||
+        domain_participant.create_topic(topic_name, config.mode.type_name(), &qos, config.mode.topic_kind()))?;
+    	println!("Topic name is {}. Type is {}.", topic.get_name(), topic.get_type().name());
+      if config.log_format == LogFormat::Compat {
+        println!("Create topic: {}", topic.get_name());
+      }
+      Ok(topic)
+    }).collect();
+    let topics = match topics {
+      Ok(topics) => topics,
+      Err(code) => return Ok(code),
+    };
+
+    let single_topic = |mode: &str| match topics.as_slice() {
+      [topic] => Ok(topic.clone()),
+      _ => Err(format!("{} supports only a single topic.", mode)),
+    };
+
+    let log_format = config.log_format;
+    let exit_code = match &config.mode {
+      Mode::WatchTopics => topic_watch::run_topic_watch(&domain_participant, log_format, &control),
+      Mode::GenericPublisher { samples, .. } => {
+        let topic = single_topic("Generic mode")?;
+        generic::run_generic_publisher(&domain_participant, topic, &qos, samples.clone(), log_format, &control)
+      }
+      Mode::GenericSubscriber { .. } => {
+        let topic = single_topic("Generic mode")?;
+        generic::run_generic_subscriber(&domain_participant, topic, &qos, log_format, &control)
+      }
+      Mode::Compare { changes, option } => {
+        let topic = single_topic(option)?;
+        if counter {
+          compare::run_comparison::<CountedShape>(&domain_participant, topic, &qos, changes, log_format, &control)
+        } else {
+          compare::run_comparison::<Shape>(&domain_participant, topic, &qos, changes, log_format, &control)
+        }
+      }
+      Mode::CoherentGroup => {
+        let (a, b) = match topics.as_slice() {
+          [a, b] => (a.clone(), b.clone()),
+          _ => return Err("--coherent-group needs two topics, e.g. -t Square,Circle".to_string()),
+        };
+        if counter {
+          coherent::run_coherent_group_subscriber::<CountedShape>(&domain_participant, a, b, &qos, log_format,
+            &control)
+        } else {
+          coherent::run_coherent_group_subscriber::<Shape>(&domain_participant, a, b, &qos, log_format, &control)
+        }
+      }
+      Mode::OwnershipFailover(failover) => {
+        let topic = single_topic("--ownership-failover")?;
+        if counter {
+          failover::run_ownership_failover::<CountedShape>(&domain_participant, topic, &qos, color, *failover,
+            log_format, &control)
+        } else {
+          failover::run_ownership_failover::<Shape>(&domain_participant, topic, &qos, color, *failover, log_format,
+            &control)
+        }
+      }
+      Mode::WriterHistory(check) => {
+        let topic = single_topic("--verify-writer-history")?;
+        writer_history::run_writer_history_check(&domain_participant, topic, &qos, color, *check, log_format,
+          &control)
+      }
+      Mode::InstanceStress { instances } => {
+        let topic = single_topic("--stress-instances")?;
+        if counter {
+          stress::run_instance_stress::<CountedShape>(&domain_participant, topic, &qos, color, *instances,
+            log_format, &control)
+        } else {
+          stress::run_instance_stress::<Shape>(&domain_participant, topic, &qos, color, *instances, log_format,
+            &control)
+        }
+      }
+      Mode::Publisher => {
+        let options = config.publisher.clone();
+        if let Some(mutation) = config.mutation {
+          println!("Publishing mutated ShapeType: {:?}", mutation);
+        }
+        match config.mutation {
+          Some(TypeMutation::Reordered) =>
+            run_publisher::<mutate::ReorderedShape>(&domain_participant, topics, &qos, color, options, log_format,
+              &control),
+          Some(TypeMutation::Retyped) =>
+            run_publisher::<mutate::RetypedShape>(&domain_participant, topics, &qos, color, options, log_format,
+              &control),
+          Some(TypeMutation::NoKey) =>
+            run_publisher::<mutate::NoKeyShape>(&domain_participant, topics, &qos, color, options, log_format,
+              &control),
+          None if config.color_size_key =>
+            run_publisher::<key_fields::ColorSizeKeyedShape>(&domain_participant, topics, &qos, color, options,
+              log_format, &control),
+          None if counter =>
+            run_publisher::<CountedShape>(&domain_participant, topics, &qos, color, options, log_format, &control),
+          None =>
+            run_publisher::<Shape>(&domain_participant, topics, &qos, color, options, log_format, &control),
+        }
+      }
+      Mode::Subscriber => {
+        let options = config.subscriber.clone();
+        if config.color_size_key {
+          run_subscriber::<key_fields::ColorSizeKeyedShape>(&domain_participant, topics, &qos, options, log_format,
+            &control)
+        } else if counter {
+          run_subscriber::<CountedShape>(&domain_participant, topics, &qos, options, log_format, &control)
+        } else {
+          run_subscriber::<Shape>(&domain_participant, topics, &qos, options, log_format, &control)
+        }
+      }
+      Mode::Idle => {
+      	println!("Nothing to do.");
+        0
+      }
+    };
+
+
+    let own_guid_prefix = status_report::format_guid(&domain_participant.get_guid())[..24].to_string();
+    drop(domain_participant);
+    if exit_code != RESTART_PARTICIPANT {
+      if let (true, Some(discovered)) = (diagnostics.vendors, &control.discovered) {
+        println!("{}", discovered.vendor_report(&own_guid_prefix));
+      }
+      if let (true, Some(discovered)) = (diagnostics.unsupported_qos, &control.discovered) {
+        for line in discovered.unsupported_report(&own_guid_prefix) {
+          println!("{}", line);
+        }
+      }
+      for line in protocol_stats.iter().flat_map(protocol_stats::ProtocolStats::report) {
+        println!("{}", line);
+      }
+      return Ok(exit_code)
+    }
+    let down = config.restart.map_or(Duration::from_secs(1), |(_, down)| down);
+    println!("DomainParticipant deleted. Creating it again in {:?}.", down);
+    std::thread::sleep(down);
+  }
+}
+
+#[cfg(feature = "ctrlc")]
+fn set_ctrlc_handler(stop_sender: channel::Sender<()>) {
+  match ctrlc::set_handler(move || {
+        stop_sender.send( () ).unwrap_or( () )
+        // ignore errors, as we are quitting anyway
+    }) {
+    Ok(()) => println!("Press Ctrl-C to quit."),
+    Err(e) => println!("Cannot set Ctrl-C handler: {}. Send SIGTERM or use exit command to quit.", e),
+  }
+}
+
+/// The slim build has no ctrlc. SIGTERM and the exit command still work.
+#[cfg(not(feature = "ctrlc"))]
+fn set_ctrlc_handler(_stop_sender: channel::Sender<()>) {
+  println!("Built without Ctrl-C handler. Send SIGTERM or use exit command to quit.");
+}
+
+/// Sends restart commands: first after up time, then every up + down time,
+/// as the participant is down for the down time in between.
+fn spawn_restart_timer(up: Duration, down: Duration, command_sender: channel::Sender<Command>) {
+  std::thread::Builder::new()
+    .name("restart-timer".to_string())
+    .spawn(move || {
+      std::thread::sleep(up);
+      while command_sender.send(Command::RestartParticipant).is_ok() {
+        std::thread::sleep(up + down);
+      }
+    })
+    .expect("Cannot start restart timer thread");
+}
+
+/// Poll and the pollable channels that control the main loop, other than
+/// the DDS entities themselves.
+pub struct Control {
+  poll: Poll,
+  stop_receiver: channel::Receiver<()>,
+  command_receiver: channel::Receiver<Command>,
+  discovered: Option<spdp::DiscoveredParticipants>,
+  on_error: OnError,
+}
+
+impl Control {
+  /// New Control with its stop and command channels registered. Sending on
+  /// the returned stop sender, or Command::Exit on the command sender, ends
+  /// the run functions, e.g. when a harness runs a role in a thread.
+  pub fn new(on_error: OnError) -> io::Result<(Control, channel::Sender<()>, channel::Sender<Command>)> {
+    let (stop_sender, stop_receiver) = channel::channel();
+    let (command_sender, command_receiver) = channel::channel();
+    let poll = Poll::new()?;
+    poll.register(&stop_receiver, STOP_PROGRAM, Ready::readable(), PollOpt::edge())?;
+    poll.register(&command_receiver, COMMAND_READY, Ready::readable(), PollOpt::edge())?;
+    Ok((Control { poll, stop_receiver, command_receiver, discovered: None, on_error }, stop_sender, command_sender))
+  }
+
+  fn stop_requested(&self) -> bool {
+    self.stop_receiver.try_recv().is_ok()
+  }
+
+  fn report_locators(&self) {
+    if let Some(discovered) = &self.discovered {
+      for line in discovered.locator_report() {
+        println!("{}", line);
+      }
+    }
+  }
+}
+
+/// Token for the endpoint of given index. With index 0 this is the base token itself.
+fn endpoint_token(base: Token, index: usize) -> Token {
+  Token(base.0 + ENDPOINT_TOKEN_STRIDE * index)
+}
+
+/// Inverse of endpoint_token: (base, index)
+fn split_endpoint_token(token: Token) -> (Token, usize) {
+  (Token(token.0 % ENDPOINT_TOKEN_STRIDE), token.0 / ENDPOINT_TOKEN_STRIDE)
+}
+
+/// Sample fields for the timeline
+fn shape_json<T: ShapeData>(sample: &T) -> serde_json::Value {
+  let shape = sample.shape();
+  serde_json::json!({
+    "color": shape.color,
+    "x": shape.x,
+    "y": shape.y,
+    "size": shape.shapesize,
+    "counter": sample.counter(),
+  })
+}
+
+fn random_velocity() -> i32 {
+  // a bit complicated lottery to ensure we do not end up with zero velocity.
+  if random::chance(0.5) { random::int_between(1, 4) } else { random::int_between(-5, -2) }
+}
+
+fn move_shape(shape:Shape, xv:i32, yv:i32) -> (Shape,i32,i32) {
+  let half_size = shape.shapesize/2 + 1;
+  let mut x = shape.x + xv;
+  let mut y = shape.y + yv;
+
+  let mut xv_new = xv;
+  let mut yv_new = yv;
+
+  if x < half_size {
+    x = half_size;
+    xv_new = -xv;
+  }
+  if x > DA_WIDTH - half_size {
+    x = DA_WIDTH - half_size;
+    xv_new = -xv;
+  }
+  if y < half_size {
+    y = half_size;
+    yv_new = -yv;
+  }
+  if y > DA_HEIGHT - half_size {
+    y = DA_HEIGHT - half_size;
+    yv_new = -yv;
+  }
+  ( Shape { color: shape.color, x, y, shapesize: shape.shapesize } , xv_new , yv_new)
+}
\ No newline at end of file
@@ -0,0 +1,177 @@
+/// Logging setup: logging-config.yaml, supplemented by command line options.
+use std::io;
+
+use log::LevelFilter;
+use log4rs::{
+  append::{
+    console::ConsoleAppender,
+    rolling_file::{
+      policy::compound::{roll::fixed_window::FixedWindowRoller, trigger::size::SizeTrigger, CompoundPolicy},
+      RollingFileAppender,
+    },
+  },
+  config::{Appender, Config, Deserializers, Logger, RawConfig, Root},
+};
+
+const CONFIG_FILE: &str = "logging-config.yaml";
+
+const LOG_FILE_ROTATE_SIZE: u64 = 10 * 1024 * 1024;
+const LOG_FILE_ROTATE_COUNT: u32 = 5;
+
+/// Changes to logging configuration from command line.
+#[derive(Default)]
+struct LogOverrides {
+  pub root_level: Option<LevelFilter>,
+  /// Names of appenders to attach to root, in addition to those from config file.
+  pub root_appenders: Vec<String>,
+  pub appenders: Vec<Appender>,
+  /// A logger given here replaces a logger of the same name from the file.
+  pub loggers: Vec<Logger>,
+}
+
+impl LogOverrides {
+  fn is_empty(&self) -> bool {
+    self.root_level.is_none()
+      && self.root_appenders.is_empty()
+      && self.appenders.is_empty()
+      && self.loggers.is_empty()
+  }
+}
+
+/// Initialize logging from CONFIG_FILE and the logging options of the command
+/// line: --log-level, optionally limited to --log-module modules, --log-file
+/// and --trace-rtps.
+pub fn init_from_command_line(level: Option<LevelFilter>, modules: Vec<String>, log_file: Option<&str>,
+    trace_rtps: bool)
+{
+  let mut overrides = LogOverrides::default();
+  if let Some(level) = level {
+    if modules.is_empty() {
+      overrides.root_level = Some(level);
+    } else {
+      overrides.loggers.extend(modules.iter().map(|m| Logger::builder().build(m, level)));
+    }
+  }
+  if let Some(log_file) = log_file {
+    overrides.appenders.push(rotating_file_appender("log_file", log_file));
+    overrides.root_appenders.push("log_file".to_string());
+  }
+  if trace_rtps {
+    let (appender, loggers) = rtps_trace_config();
+    overrides.appenders.push(appender);
+    overrides.loggers.extend(loggers);
+  }
+  init_logging(overrides);
+}
+
+/// Initialize logging from CONFIG_FILE, or built-in defaults if there is no
+/// such file. Command line overrides are applied on top.
+fn init_logging(overrides: LogOverrides) {
+  if overrides.is_empty() {
+    // Plain config file use. This also gives us the refresh_rate behaviour.
+    log4rs::init_file(CONFIG_FILE, Default::default()).unwrap_or_else(|e| {
+      match e.downcast_ref::<io::Error>() {
+        // Config file did not work. If it is a simple "No such file or directory", then
+        // substitute some default config.
+        Some(os_err) if os_err.kind() == io::ErrorKind::NotFound => {
+          println!("No config file.");
+          log4rs::init_config(default_config()).unwrap();
+        }
+        // Give up.
+        other_error => panic!("Config problem: {:?}", other_error),
+      }
+    });
+    return
+  }
+
+  let raw_config = match std::fs::read_to_string(CONFIG_FILE) {
+    Ok(s) => serde_yaml::from_str::<RawConfig>(&s)
+      .unwrap_or_else(|e| panic!("Config problem: {:?}", e)),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+      println!("No config file.");
+      default_raw_config()
+    }
+    Err(e) => panic!("Config problem: {:?}", e),
+  };
+
+  let LogOverrides { root_level, root_appenders, appenders: extra_appenders, loggers: extra_loggers } =
+    overrides;
+
+  let (mut appenders, mut errors) = raw_config.appenders_lossy(&Deserializers::default());
+  errors.handle();
+  appenders.extend(extra_appenders);
+
+  let mut loggers: Vec<Logger> = raw_config
+    .loggers()
+    .into_iter()
+    .filter(|l| !extra_loggers.iter().any(|x| x.name() == l.name()))
+    .collect();
+  loggers.extend(extra_loggers);
+
+  let file_root = raw_config.root();
+  let root = Root::builder()
+    .appenders(file_root.appenders().iter().cloned().chain(root_appenders))
+    .build(root_level.unwrap_or_else(|| file_root.level()));
+
+  let config = Config::builder()
+    .appenders(appenders)
+    .loggers(loggers)
+    .build(root)
+    .unwrap_or_else(|e| panic!("Logging config problem: {:?}", e));
+  log4rs::init_config(config).unwrap();
+}
+
+fn default_config() -> Config {
+  let stdout = ConsoleAppender::builder().build();
+  Config::builder()
+    .appender(Appender::builder().build("stdout", Box::new(stdout)))
+    .build(Root::builder().appender("stdout").build(LevelFilter::Error))
+    .unwrap()
+}
+
+fn default_raw_config() -> RawConfig {
+  serde_yaml::from_str(
+    "appenders:\n  stdout:\n    kind: console\nroot:\n  level: error\n  appenders:\n    - stdout\n",
+  )
+  .unwrap()
+}
+
+/// Appender writing to the given file, rotating it when it grows too large.
+/// Rotated files are named <path>.1 ... <path>.N, with .1 being the most recent.
+fn rotating_file_appender(name: &str, path: &str) -> Appender {
+  let roller = FixedWindowRoller::builder()
+    .build(&format!("{}.{{}}", path), LOG_FILE_ROTATE_COUNT)
+    .unwrap_or_else(|e| panic!("Bad log file name {}: {:?}", path, e));
+  let policy = CompoundPolicy::new(Box::new(SizeTrigger::new(LOG_FILE_ROTATE_SIZE)), Box::new(roller));
+  let file = RollingFileAppender::builder()
+    .build(path, Box::new(policy))
+    .unwrap_or_else(|e| panic!("Cannot open log file {}: {:?}", path, e));
+  Appender::builder().build(name, Box::new(file))
+}
+
+// RustDDS modules that process RTPS submessages. Writer side sends DATA, HEARTBEAT
+// and GAP, and receives ACKNACK. Reader side is the opposite. message_receiver
+// parses all incoming messages.
+const RTPS_TRACE_MODULES: &[&str] = &[
+  "rustdds::rtps::writer",
+  "rustdds::rtps::reader",
+  "rustdds::rtps::message_receiver",
+];
+
+/// Appender and loggers for --trace-rtps. The trace covers every endpoint of
+/// this process: RustDDS identifies endpoints in these log lines by GUID, not
+/// by topic name, so there is nothing to filter topics by.
+fn rtps_trace_config() -> (Appender, Vec<Logger>) {
+  let stdout = ConsoleAppender::builder().build();
+  let appender = Appender::builder().build("rtps_trace", Box::new(stdout));
+  let loggers = RTPS_TRACE_MODULES
+    .iter()
+    .map(|m| {
+      Logger::builder()
+        .appender("rtps_trace")
+        .additive(false)
+        .build(*m, LevelFilter::Trace)
+    })
+    .collect();
+  (appender, loggers)
+}
@@ -0,0 +1,52 @@
+/// Logging setup of the slim build: env_logger instead of log4rs.
+///
+/// The level comes from RUST_LOG, or --log-level, with errors only by default.
+/// logging-config.yaml is not read, and --log-file writes all log output to the
+/// file without rotation.
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use env_logger::{Builder, Target};
+use log::LevelFilter;
+
+const CONFIG_FILE: &str = "logging-config.yaml";
+
+// Same modules as --trace-rtps uses with log4rs
+const RTPS_TRACE_MODULES: &[&str] = &[
+  "rustdds::rtps::writer",
+  "rustdds::rtps::reader",
+  "rustdds::rtps::message_receiver",
+];
+
+/// Initialize logging from RUST_LOG and the logging options of the command
+/// line: --log-level, optionally limited to --log-module modules, --log-file
+/// and --trace-rtps.
+pub fn init_from_command_line(level: Option<LevelFilter>, modules: Vec<String>, log_file: Option<&str>,
+    trace_rtps: bool)
+{
+  if Path::new(CONFIG_FILE).exists() {
+    println!("Slim build does not read {}. Use RUST_LOG or --log-level.", CONFIG_FILE);
+  }
+  let mut builder = Builder::new();
+  builder.filter_level(LevelFilter::Error);
+  builder.parse_env("RUST_LOG");
+  match level {
+    Some(level) if modules.is_empty() => { builder.filter_level(level); }
+    Some(level) => for m in &modules {
+      builder.filter_module(m, level);
+    }
+    None => (),
+  }
+  if let Some(path) = log_file {
+    let file = File::create(path).unwrap_or_else(|e| panic!("Cannot open log file {}: {:?}", path, e));
+    builder.target(Target::Pipe(Box::new(file)));
+  }
+  if trace_rtps {
+    for m in RTPS_TRACE_MODULES {
+      builder.filter_module(m, LevelFilter::Trace);
+    }
+  }
+  builder.format(|buf, record| writeln!(buf, "{} {} - {}", record.level(), record.target(), record.args()));
+  builder.init();
+}
@@ -0,0 +1,81 @@
+/// Sample loss accounting based on the counter embedded in CountedShape.
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Default, Clone, Copy)]
+struct Counts {
+  received: u64,
+  lost: u64,
+}
+
+impl Counts {
+  fn loss_percent(&self) -> f64 {
+    let expected = self.received + self.lost;
+    if expected == 0 {
+      0.0
+    } else {
+      100.0 * self.lost as f64 / expected as f64
+    }
+  }
+}
+
+impl std::fmt::Display for Counts {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(f, "lost {}/{} ({:.2}%)", self.lost, self.received + self.lost, self.loss_percent())
+  }
+}
+
+pub struct LossStats {
+  // last counter value seen, per instance (color)
+  last_counter: HashMap<String, u32>,
+  interval: Counts,
+  total: Counts,
+  last_report: Instant,
+}
+
+impl LossStats {
+  pub fn new() -> LossStats {
+    LossStats {
+      last_counter: HashMap::new(),
+      interval: Counts::default(),
+      total: Counts::default(),
+      last_report: Instant::now(),
+    }
+  }
+
+  /// True once any counted samples have been received.
+  pub fn is_active(&self) -> bool {
+    !self.last_counter.is_empty()
+  }
+
+  pub fn record(&mut self, instance: &str, counter: u32) {
+    let lost = match self.last_counter.get(instance) {
+      // Counter went backwards or did not advance: writer restarted, or we got
+      // a duplicate. Either way, start counting again from here.
+      Some(&prev) if counter <= prev => 0,
+      Some(&prev) => (counter - prev - 1) as u64,
+      None => 0,
+    };
+    self.last_counter.insert(instance.to_string(), counter);
+    self.interval.received += 1;
+    self.interval.lost += lost;
+    self.total.received += 1;
+    self.total.lost += lost;
+  }
+
+  pub fn report_due(&self, interval: Duration) -> bool {
+    self.last_report.elapsed() >= interval
+  }
+
+  /// Report loss since previous interval report, and start a new interval.
+  pub fn interval_report(&mut self) -> String {
+    let report = format!("Loss: interval {}, total {}", self.interval, self.total);
+    self.interval = Counts::default();
+    self.last_report = Instant::now();
+    report
+  }
+
+  pub fn total_report(&self) -> String {
+    format!("Loss: total {} over {} instance(s)", self.total, self.last_counter.len())
+  }
+}
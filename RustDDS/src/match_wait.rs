@@ -0,0 +1,86 @@
+/// Progress reports while endpoints wait for their first match, with hints
+/// on why the match may not be happening.
+///
+/// The hints come from comparing our topics to the topics RustDDS has
+/// discovered: the same name with another type, the same name and type (so
+/// likely a QoS or partition mismatch), or a name differing only in case.
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+use rustdds::dds::traits::TopicDescription;
+use rustdds::dds::{DomainParticipant, Topic};
+
+use crate::spdp::DiscoveredParticipants;
+use crate::status_report::format_guid;
+use crate::topic_watch::describe_qos;
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct MatchWait {
+  /// (topic name, our type name)
+  topics: Vec<(String, String)>,
+  started: Instant,
+  next_report: Instant,
+}
+
+impl MatchWait {
+  pub fn new(topics: &[Topic]) -> MatchWait {
+    let now = Instant::now();
+    MatchWait {
+      topics: topics.iter().map(|t| (t.get_name().to_string(), t.get_type().name().to_string())).collect(),
+      started: now,
+      next_report: now + REPORT_INTERVAL,
+    }
+  }
+
+  /// Prints a progress line, if due, for each of our topics that has an
+  /// endpoint in `unmatched`.
+  pub fn report(&mut self, unmatched: &[&str], domain_participant: &DomainParticipant,
+      discovered: Option<&DiscoveredParticipants>)
+  {
+    let now = Instant::now();
+    if now < self.next_report {
+      return
+    }
+    self.next_report = now + REPORT_INTERVAL;
+    let own_guid_prefix = format_guid(&domain_participant.get_guid())[..24].to_string();
+    let remote_topics: Vec<_> = domain_participant.get_discovered_topics().into_iter()
+      .map(|d| d.topic_data)
+      .filter(|t| t.key.is_none_or(|guid| format_guid(&guid)[..24] != own_guid_prefix))
+      .collect();
+    let participants = match discovered {
+      Some(d) => d.snapshot().keys().filter(|prefix| **prefix != own_guid_prefix).count(),
+      None => remote_topics.iter().filter_map(|t| t.key.map(|guid| format_guid(&guid)[..24].to_string()))
+        .collect::<BTreeSet<_>>().len(),
+    };
+    for (name, type_name) in self.topics.iter().filter(|(name, _)| unmatched.contains(&name.as_str())) {
+      println!("Still waiting after {:.0} s: {} participants discovered, 0 endpoints matched on topic {}",
+        self.started.elapsed().as_secs_f64(), participants, name);
+      let same_name: Vec<_> = remote_topics.iter().filter(|t| t.name.as_ref() == Some(name)).collect();
+      let mut hints = BTreeSet::new();
+      for t in same_name.iter().filter(|t| t.type_name.as_ref() != Some(type_name)) {
+        hints.insert(format!("type mismatch: remote type is {}, ours is {}",
+          t.type_name.as_deref().unwrap_or("unknown"), type_name));
+      }
+      for t in same_name.iter().filter(|t| t.type_name.as_ref() == Some(type_name)) {
+        hints.insert(format!("QoS mismatch: remote announced {}. Or partition mismatch: RustDDS uses only the \
+          default partition.", describe_qos(t)));
+      }
+      for t in remote_topics.iter().filter(|t| t.name.as_ref() != Some(name)) {
+        if let Some(other) = t.name.as_ref().filter(|other| other.eq_ignore_ascii_case(name)) {
+          hints.insert(format!("topic name mismatch: remote topic is {}", other));
+        }
+      }
+      if hints.is_empty() {
+        hints.insert(if participants == 0 {
+          "no remote participants: check domain id, network interfaces and multicast".to_string()
+        } else {
+          format!("no remote endpoints announced on topic {}", name)
+        });
+      }
+      for hint in hints {
+        println!("  Likely cause: {}", hint);
+      }
+    }
+  }
+}
@@ -0,0 +1,333 @@
+/// Interoperability matrix runner. Runs every publisher configuration against
+/// every subscriber configuration from a spec file, each pair as two child
+/// processes of this same program, and prints a pass/fail table.
+///
+/// Spec file is JSON:
+///
+///   {
+///     "topic": "Square",
+///     "run_seconds": 5,
+///     "publishers":  [ { "reliability": "reliable", "durability": "l", "history": 1 } ],
+///     "subscribers": [ { "reliability": "best_effort" },
+///                      { "reliability": "reliable", "durability": "l", "args": ["-c", "RED"] } ]
+///   }
+///
+/// A pair passes if the subscriber receives samples exactly when the QoS
+/// policies are compatible, and both children exit with status 0.
+use std::fs;
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+pub const SUBSCRIBER_HEAD_START: Duration = Duration::from_millis(500);
+/// How long a child has to exit after the exit command before it is killed
+const EXIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+struct MatrixSpec {
+  #[serde(default = "default_topic")]
+  topic: String,
+  #[serde(default)]
+  domain_id: u16,
+  #[serde(default = "default_run_seconds")]
+  run_seconds: f64,
+  publishers: Vec<QosConfig>,
+  subscribers: Vec<QosConfig>,
+}
+
+fn default_topic() -> String {
+  "Square".to_string()
+}
+
+fn default_run_seconds() -> f64 {
+  5.0
+}
+
+#[derive(Deserialize)]
+struct QosConfig {
+  // "reliable" or "best_effort". Program default is best effort.
+  reliability: Option<String>,
+  // "v", "l", "t" or "p", as with -D
+  durability: Option<String>,
+  // as with -k
+  history: Option<i32>,
+  // any other command line arguments
+  #[serde(default)]
+  args: Vec<String>,
+}
+
+impl QosConfig {
+  fn reliable(&self) -> bool {
+    self.reliability.as_deref() == Some("reliable")
+  }
+
+  fn durability_rank(&self) -> u8 {
+    match self.durability.as_deref() {
+      Some("l") => 1,
+      Some("t") => 2,
+      Some("p") => 3,
+      _ => 0,
+    }
+  }
+
+  fn label(&self) -> String {
+    let mut label = format!(
+      "{}/{}/{}",
+      if self.reliable() { "REL" } else { "BE" },
+      self.durability.as_deref().unwrap_or("v"),
+      match self.history {
+        Some(d) if d >= 0 => format!("KL{}", d),
+        _ => "KA".to_string(),
+      }
+    );
+    if !self.args.is_empty() {
+      label += " ";
+      label += &self.args.join(" ");
+    }
+    label
+  }
+
+  fn command_args(&self, topic: &str, domain_id: u16) -> Vec<String> {
+    let mut args = vec!["-t".to_string(), topic.to_string(), "-d".to_string(), domain_id.to_string()];
+    match self.reliability.as_deref() {
+      Some("reliable") => args.push("-r".to_string()),
+      Some("best_effort") => args.push("-b".to_string()),
+      _ => (),
+    }
+    if let Some(d) = &self.durability {
+      args.push("-D".to_string());
+      args.push(d.clone());
+    }
+    if let Some(h) = self.history {
+      args.push("-k".to_string());
+      args.push(h.to_string());
+    }
+    args.extend(self.args.iter().cloned());
+    args
+  }
+}
+
+/// Requested/offered compatibility of the policies we vary.
+fn compatible(publisher: &QosConfig, subscriber: &QosConfig) -> bool {
+  (publisher.reliable() || !subscriber.reliable())
+    && publisher.durability_rank() >= subscriber.durability_rank()
+}
+
+/// A child process of this program. Its stdout and stderr are read in
+/// background threads, so that a long run cannot block on a full pipe.
+/// Dropping it kills the child if it is still running, and reaps it, so that
+/// no error path leaves a child behind.
+pub struct ChildProcess {
+  child: Child,
+  stdout: Option<thread::JoinHandle<String>>,
+  stderr: Option<thread::JoinHandle<String>>,
+}
+
+/// What a child printed, and how it ended
+pub struct ChildOutput {
+  pub stdout: String,
+  pub stderr: String,
+  /// Exit status, None if killed by a signal
+  pub status: Option<i32>,
+  /// Killed after not exiting within EXIT_TIMEOUT
+  pub timed_out: bool,
+}
+
+fn read_all(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<String> {
+  thread::spawn(move || {
+    let mut bytes = Vec::new();
+    pipe.read_to_end(&mut bytes).unwrap_or(0);
+    String::from_utf8_lossy(&bytes).into_owned()
+  })
+}
+
+pub fn spawn_child(role_flag: &str, args: &[String]) -> std::io::Result<ChildProcess> {
+  let mut child = Command::new(std::env::current_exe()?)
+    .arg(role_flag)
+    .args(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()?;
+  let stdout = read_all(child.stdout.take().unwrap());
+  let stderr = read_all(child.stderr.take().unwrap());
+  Ok(ChildProcess { child, stdout: Some(stdout), stderr: Some(stderr) })
+}
+
+impl ChildProcess {
+  // Child processes accept console commands on stdin.
+  pub fn stop(&mut self) {
+    if let Some(stdin) = self.child.stdin.as_mut() {
+      stdin.write_all(b"exit 0\n").unwrap_or(());
+    }
+  }
+
+  /// Waits for the child to exit, killing it if it has not exited within
+  /// EXIT_TIMEOUT, and collects its output.
+  pub fn finish(mut self) -> std::io::Result<ChildOutput> {
+    let deadline = Instant::now() + EXIT_TIMEOUT;
+    let mut timed_out = false;
+    let status = loop {
+      if let Some(status) = self.child.try_wait()? {
+        break status
+      }
+      if Instant::now() >= deadline {
+        timed_out = true;
+        self.child.kill().unwrap_or(());
+        break self.child.wait()?
+      }
+      thread::sleep(Duration::from_millis(100));
+    };
+    let join = |pipe: Option<thread::JoinHandle<String>>| pipe.and_then( |t| t.join().ok() ).unwrap_or_default();
+    Ok(ChildOutput {
+      stdout: join(self.stdout.take()),
+      stderr: join(self.stderr.take()),
+      status: status.code(),
+      timed_out,
+    })
+  }
+}
+
+impl Drop for ChildProcess {
+  fn drop(&mut self) {
+    if let Ok(None) = self.child.try_wait() {
+      self.child.kill().unwrap_or(());
+      self.child.wait().map(|_| ()).unwrap_or(());
+    }
+  }
+}
+
+impl ChildOutput {
+  /// Number of sample lines printed on the topic. Sample lines start with the
+  /// topic name padded to 10 characters, and truncated to 10 characters unless
+  /// in the compat format.
+  pub fn sample_count(&self, topic: &str) -> usize {
+    let prefixes = [format!("{:10.10} ", topic), format!("{:<10} ", topic)];
+    self.stdout.lines().filter(|l| prefixes.iter().any(|p| l.starts_with(p))).count()
+  }
+
+  /// Why the child failed, with the panic message or last line of its
+  /// stderr, or None if it exited with status 0.
+  pub fn failure(&self) -> Option<String> {
+    if self.timed_out {
+      return Some(format!("did not exit within {} s", EXIT_TIMEOUT.as_secs()))
+    }
+    if self.status == Some(0) {
+      return None
+    }
+    let lines: Vec<&str> = self.stderr.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    // The panic message is on the line after "thread 'main' panicked at <location>:".
+    // Command line errors start with "error:".
+    let message = match lines.iter().position(|l| l.contains("panicked at")) {
+      Some(i) if lines[i].ends_with(':') => lines.get(i + 1).copied(),
+      Some(i) => Some(lines[i]),
+      None => lines.iter().find(|l| l.starts_with("error:")).or(lines.last()).copied(),
+    };
+    let status = self.status.map_or("killed by signal".to_string(), |s| format!("exit status {}", s));
+    Some(match message {
+      Some(m) => format!("{}: {}", status, m),
+      None => status,
+    })
+  }
+}
+
+/// Returns number of samples the subscriber printed, or error message.
+fn run_pair(spec: &MatrixSpec, publisher: &QosConfig, subscriber: &QosConfig) -> Result<usize, String> {
+  let mut sub = spawn_child("-S", &subscriber.command_args(&spec.topic, spec.domain_id))
+    .map_err(|e| format!("Cannot start subscriber: {}", e))?;
+  thread::sleep(SUBSCRIBER_HEAD_START);
+  let mut publ = match spawn_child("-P", &publisher.command_args(&spec.topic, spec.domain_id)) {
+    Ok(p) => p,
+    Err(e) => return Err(format!("Cannot start publisher: {}", e)),
+  };
+  thread::sleep(Duration::from_secs_f64(spec.run_seconds));
+  publ.stop();
+  sub.stop();
+  let publ = publ.finish().map_err(|e| format!("Publisher: {}", e))?;
+  let sub = sub.finish().map_err(|e| format!("Subscriber: {}", e))?;
+  if let Some(failure) = publ.failure() {
+    return Err(format!("Publisher {}", failure))
+  }
+  if let Some(failure) = sub.failure() {
+    return Err(format!("Subscriber {}", failure))
+  }
+  Ok(sub.sample_count(&spec.topic))
+}
+
+fn parse_spec(json: &str) -> Result<MatrixSpec, String> {
+  let spec: MatrixSpec = serde_json::from_str(json).map_err(|e| e.to_string())?;
+  if !(spec.run_seconds.is_finite() && spec.run_seconds >= 0.0) {
+    return Err(format!("Expected non-negative run_seconds, got {}", spec.run_seconds))
+  }
+  Ok(spec)
+}
+
+/// Runs the matrix and prints a result table. Returns exit code: 0 if all passed.
+pub fn run_matrix(spec_file: &str) -> i32 {
+  let spec: MatrixSpec = fs::read_to_string(spec_file)
+    .map_err(|e| e.to_string())
+    .and_then(|s| parse_spec(&s))
+    .unwrap_or_else(|e| panic!("Cannot read matrix spec {}: {}", spec_file, e));
+
+  let mut failures = 0;
+  println!("{:24} {:24} {:8} {:>8} RESULT", "PUBLISHER", "SUBSCRIBER", "EXPECT", "RECEIVED");
+  for publisher in &spec.publishers {
+    for subscriber in &spec.subscribers {
+      let expect_data = compatible(publisher, subscriber);
+      let (received, result) = match run_pair(&spec, publisher, subscriber) {
+        Ok(n) if (n > 0) == expect_data => (n.to_string(), "PASS".to_string()),
+        Ok(n) => (n.to_string(), "FAIL".to_string()),
+        Err(e) => ("-".to_string(), format!("ERROR {}", e)),
+      };
+      if result != "PASS" {
+        failures += 1;
+      }
+      println!(
+        "{:24} {:24} {:8} {:>8} {}",
+        publisher.label(),
+        subscriber.label(),
+        if expect_data { "data" } else { "no data" },
+        received,
+        result
+      );
+    }
+  }
+  let total = spec.publishers.len() * spec.subscribers.len();
+  println!("{} of {} pairs passed.", total - failures, total);
+  if failures == 0 { 0 } else { 1 }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn run_seconds() {
+    let spec = |run_seconds: &str|
+      parse_spec(&format!(r#"{{"run_seconds": {}, "publishers": [], "subscribers": []}}"#, run_seconds))
+        .map(|s| s.run_seconds);
+    assert_eq!(spec("0"), Ok(0.0));
+    assert_eq!(spec("2.5"), Ok(2.5));
+    assert!(spec("-1").is_err());
+    assert!(spec("\"5\"").is_err());
+  }
+
+  #[test]
+  fn sample_count() {
+    let output = |stdout: &str| ChildOutput { stdout: stdout.to_string(), stderr: String::new(), status: Some(0),
+      timed_out: false };
+    let lines = "Square     BLUE       010 020 [30]\n\
+                 Square     BLUE         10  20 [30]\n\
+                 Squares    BLUE         10  20 [30]\n\
+                 LongTopicName BLUE       010 020 [30]\n\
+                 LongTopicN BLUE         10  20 [30]\n\
+                 Topic name is Square. Type is ShapeType.\n";
+    let cases = [("Square", 2), ("Squares", 1), ("LongTopicName", 2), ("Circle", 0)];
+    for (topic, expected) in cases.iter() {
+      assert_eq!(output(lines).sample_count(topic), *expected, "{}", topic);
+    }
+  }
+}
@@ -0,0 +1,125 @@
+/// Deliberately incompatible variants of ShapeType, for negative interop tests.
+///
+/// These are published with type name "ShapeType", so a remote reader will
+/// match them. RustDDS does not send XTypes type information, so the remote end
+/// can only notice the mismatch when deserializing the data. A correct reader
+/// should reject or drop such samples rather than show garbage shapes.
+///
+/// Each variant keeps the original Shape alongside (not serialized), so that it
+/// can be used with the same publisher loop as the real ShapeType.
+use rustdds::dds::traits::Keyed;
+use serde::{Deserialize, Serialize};
+
+use crate::{Shape, ShapeData};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeMutation {
+  /// Same members, but key member "color" is moved last.
+  Reordered,
+  /// "x" and "y" are 64-bit instead of 32-bit integers.
+  Retyped,
+  /// Key member "color" is missing.
+  NoKey,
+}
+
+impl TypeMutation {
+  pub const VARIANTS: &'static [&'static str] = &["reordered", "retyped", "no-key"];
+
+  pub fn parse(s: &str) -> Result<TypeMutation, String> {
+    match s {
+      "reordered" => Ok(TypeMutation::Reordered),
+      "retyped" => Ok(TypeMutation::Retyped),
+      "no-key" => Ok(TypeMutation::NoKey),
+      other => Err(format!("Unknown type mutation '{}'. Known: {}", other, Self::VARIANTS.join(" "))),
+    }
+  }
+}
+
+/// struct ShapeType { long x; long y; long shapesize; @key string color; }
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReorderedShape {
+  x: i32,
+  y: i32,
+  shapesize: i32,
+  color: String,
+  #[serde(skip)]
+  shape: Shape,
+}
+
+/// struct ShapeType { @key string color; long long x; long long y; long shapesize; }
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RetypedShape {
+  color: String,
+  x: i64,
+  y: i64,
+  shapesize: i32,
+  #[serde(skip)]
+  shape: Shape,
+}
+
+/// struct ShapeType { long x; long y; long shapesize; }
+///
+/// The instance key (hash) is still derived from the color, as RustDDS needs
+/// one for a keyed topic.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NoKeyShape {
+  x: i32,
+  y: i32,
+  shapesize: i32,
+  #[serde(skip)]
+  shape: Shape,
+}
+
+impl Keyed for ReorderedShape {
+  type K = String;
+  fn get_key(&self) -> String {
+    self.color.clone()
+  }
+}
+
+impl Keyed for RetypedShape {
+  type K = String;
+  fn get_key(&self) -> String {
+    self.color.clone()
+  }
+}
+
+impl Keyed for NoKeyShape {
+  type K = String;
+  fn get_key(&self) -> String {
+    self.shape.color.clone()
+  }
+}
+
+impl ShapeData for ReorderedShape {
+  fn from_shape(shape: Shape, _counter: u32) -> Self {
+    ReorderedShape { x: shape.x, y: shape.y, shapesize: shape.shapesize, color: shape.color.clone(), shape }
+  }
+  fn shape(&self) -> &Shape { &self.shape }
+  fn counter(&self) -> Option<u32> { None }
+  fn key_color(key: &String) -> String { key.clone() }
+}
+
+impl ShapeData for RetypedShape {
+  fn from_shape(shape: Shape, _counter: u32) -> Self {
+    RetypedShape {
+      color: shape.color.clone(),
+      x: i64::from(shape.x),
+      y: i64::from(shape.y),
+      shapesize: shape.shapesize,
+      shape,
+    }
+  }
+  fn shape(&self) -> &Shape { &self.shape }
+  fn counter(&self) -> Option<u32> { None }
+  fn key_color(key: &String) -> String { key.clone() }
+}
+
+impl ShapeData for NoKeyShape {
+  fn from_shape(shape: Shape, _counter: u32) -> Self {
+    NoKeyShape { x: shape.x, y: shape.y, shapesize: shape.shapesize, shape }
+  }
+  fn shape(&self) -> &Shape { &self.shape }
+  fn counter(&self) -> Option<u32> { None }
+  fn key_color(key: &String) -> String { key.clone() }
+}
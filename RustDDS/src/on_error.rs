@@ -0,0 +1,117 @@
+/// What to do when creating a DDS entity or writing a sample fails.
+///
+/// Creating the DomainParticipant, a Topic, a Publisher or Subscriber, or an
+/// endpoint can be retried until it succeeds, or the run ends with
+/// EXIT_DDS_ERROR. There is nothing to continue with without the entity, so
+/// "continue" ends the run as well. A failed write can be retried a few times, can
+/// end the program, or can just be reported and the next sample written.
+use std::fmt::Debug;
+use std::thread;
+use std::time::Duration;
+
+/// Exit code when giving up because of a DDS error.
+pub const EXIT_DDS_ERROR: i32 = 3;
+
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+/// Longest wait between attempts in create_with_backoff
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Further attempts after a failed write, with policy Retry.
+const WRITE_RETRIES: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnError {
+  Retry,
+  Exit,
+  /// Skip failed writes. Failed entity creation ends the run as with Exit.
+  Continue,
+}
+
+impl OnError {
+  pub const VARIANTS: &'static [&'static str] = &["retry", "exit", "continue"];
+
+  pub fn parse(s: &str) -> Result<OnError, String> {
+    match s {
+      "retry" => Ok(OnError::Retry),
+      "exit" => Ok(OnError::Exit),
+      "continue" => Ok(OnError::Continue),
+      other => Err(format!("Unknown error policy \"{}\". Expected one of {}", other, OnError::VARIANTS.join(" "))),
+    }
+  }
+
+  /// Calls `create` until it succeeds, or returns Err(exit code) if the run
+  /// should end.
+  pub fn create<E, R: Debug>(self, what: &str, mut create: impl FnMut() -> Result<E, R>) -> Result<E, i32> {
+    loop {
+      match create() {
+        Ok(entity) => return Ok(entity),
+        Err(e) => {
+          println!("Creating {} failed: {:?}", what, e);
+          if self != OnError::Retry {
+            println!("Cannot run without {}. Exiting.", what);
+            return Err(EXIT_DDS_ERROR)
+          }
+          println!("Retrying in {:?}", RETRY_INTERVAL);
+          thread::sleep(RETRY_INTERVAL);
+        }
+      }
+    }
+  }
+
+  /// Like create, but first makes up to `retries` further attempts, waiting
+  /// twice as long before each, starting from RETRY_INTERVAL. If all of them
+  /// fail, the policy decides. Each attempt is the same call: nothing here
+  /// falls back to other settings, such as another participant ID.
+  pub fn create_with_backoff<E, R: Debug>(self, what: &str, retries: usize, mut create: impl FnMut() -> Result<E, R>)
+    -> Result<E, i32>
+  {
+    let mut backoff = RETRY_INTERVAL;
+    for attempt in 1..=retries {
+      match create() {
+        Ok(entity) => return Ok(entity),
+        Err(e) => {
+          println!("Creating {} failed: {:?}", what, e);
+          println!("Attempt {} of {} in {:?}", attempt + 1, retries + 1, backoff);
+          thread::sleep(backoff);
+          backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+      }
+    }
+    self.create(what, create)
+  }
+
+  /// Writes using `write`, which returns true on success. Returns whether the
+  /// sample was written, or Err(exit code) if the program should exit.
+  pub fn write(self, mut write: impl FnMut() -> bool) -> Result<bool, i32> {
+    if write() {
+      return Ok(true)
+    }
+    match self {
+      OnError::Continue => Ok(false),
+      OnError::Exit => {
+        println!("Exiting because of failed write.");
+        Err(EXIT_DDS_ERROR)
+      }
+      OnError::Retry => {
+        for attempt in 1..=WRITE_RETRIES {
+          println!("Retrying write, attempt {} of {}", attempt, WRITE_RETRIES);
+          if write() {
+            return Ok(true)
+          }
+        }
+        println!("Write failed {} times, skipping sample.", WRITE_RETRIES + 1);
+        Ok(false)
+      }
+    }
+  }
+}
+
+/// Reports a failed write. Returns true if the write succeeded.
+pub fn report_write(topic_name: &str, result: rustdds::dds::Result<()>) -> bool {
+  match result {
+    Ok(()) => true,
+    Err(e) => {
+      println!("Write on {} failed: {:?}", topic_name, e);
+      false
+    }
+  }
+}
@@ -0,0 +1,62 @@
+/// Following which remote writer each instance is received from.
+///
+/// With EXCLUSIVE ownership, a subscriber should accept samples of an instance
+/// only from the strongest live writer. We record the writer GUID of every
+/// accepted sample, and report whenever it changes for an instance, which is
+/// what an ownership test needs to check. Remote writer strengths are not
+/// visible through RustDDS, so they cannot be reported.
+use std::collections::BTreeMap;
+
+use rustdds::structure::guid::GUID;
+use serde_json::json;
+
+use crate::status_report::{self, format_guid, LogFormat};
+
+struct Owner {
+  writer: GUID,
+  samples: u64,
+}
+
+#[derive(Default)]
+pub struct OwnershipTracker {
+  /// instance ("topic/color") -> writer of latest sample
+  owners: BTreeMap<String, Owner>,
+}
+
+impl OwnershipTracker {
+  /// Records a sample of `instance` from `writer`, and reports if the writer changed.
+  pub fn record(&mut self, format: LogFormat, topic_name: &str, instance: &str, writer: GUID) {
+    let previous = match self.owners.get_mut(instance) {
+      Some(owner) if owner.writer == writer => {
+        owner.samples += 1;
+        return
+      }
+      Some(owner) => Some((owner.writer, owner.samples)),
+      None => None,
+    };
+    self.owners.insert(instance.to_string(), Owner { writer, samples: 1 });
+    match format {
+      LogFormat::Text | LogFormat::Compat => match previous {
+        Some((old, samples)) => println!(
+          "Instance {} now from writer {} (was {} for {} samples)",
+          instance,
+          format_guid(&writer),
+          format_guid(&old),
+          samples
+        ),
+        None => println!("Instance {} from writer {}", instance, format_guid(&writer)),
+      },
+      LogFormat::Json => status_report::print_json_event(
+        "Subscriber",
+        topic_name,
+        "owner_changed",
+        json!({
+          "instance": instance,
+          "writer": format_guid(&writer),
+          "previous_writer": previous.map(|(old, _)| format_guid(&old)),
+          "previous_samples": previous.map(|(_, samples)| samples),
+        }),
+      ),
+    }
+  }
+}
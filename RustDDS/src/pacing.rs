@@ -0,0 +1,93 @@
+/// Pacing writes to a bandwidth budget, like a flow controller.
+///
+/// Samples are queued and released when the budget allows, with bursts of at
+/// most BURST worth of data. Sizes are the approximate serialized sizes, not
+/// including RTPS headers. Fragmentation of large data is done by RustDDS
+/// below the DataWriter, so individual fragments cannot be paced from here.
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Budget may accumulate up to this much sending time.
+const BURST: Duration = Duration::from_millis(100);
+pub const RATE_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct Pacer<T> {
+  bytes_per_second: f64,
+  budget: f64,
+  updated: Instant,
+  /// (writer index, sample, size)
+  queue: VecDeque<(usize, T, usize)>,
+  sent_bytes: u64,
+  sent_samples: u64,
+  report_start: Instant,
+}
+
+impl<T> Pacer<T> {
+  pub fn new(kbps: f64) -> Pacer<T> {
+    let bytes_per_second = kbps * 1000.0 / 8.0;
+    Pacer {
+      bytes_per_second,
+      budget: bytes_per_second * BURST.as_secs_f64(),
+      updated: Instant::now(),
+      queue: VecDeque::new(),
+      sent_bytes: 0,
+      sent_samples: 0,
+      report_start: Instant::now(),
+    }
+  }
+
+  pub fn push(&mut self, writer: usize, sample: T, size: usize) {
+    self.queue.push_back((writer, sample, size));
+  }
+
+  fn refill(&mut self, now: Instant) {
+    let max_budget = self.bytes_per_second * BURST.as_secs_f64();
+    self.budget = (self.budget + self.bytes_per_second * (now - self.updated).as_secs_f64()).min(max_budget);
+    self.updated = now;
+  }
+
+  /// Next sample that fits in the budget, if any.
+  pub fn pop_ready(&mut self, now: Instant) -> Option<(usize, T)> {
+    self.refill(now);
+    let size = self.queue.front()?.2 as f64;
+    // A sample larger than the whole burst is sent when the budget is full.
+    if self.budget < size.min(self.bytes_per_second * BURST.as_secs_f64()) {
+      return None
+    }
+    let (writer, sample, size) = self.queue.pop_front()?;
+    self.budget -= size as f64;
+    self.sent_bytes += size as u64;
+    self.sent_samples += 1;
+    Some((writer, sample))
+  }
+
+  pub fn queued(&self) -> usize {
+    self.queue.len()
+  }
+
+  /// When the first queued sample fits in the budget.
+  pub fn next_ready(&self) -> Option<Instant> {
+    let size = self.queue.front()?.2 as f64;
+    let needed = size.min(self.bytes_per_second * BURST.as_secs_f64()) - self.budget;
+    Some(self.updated + Duration::from_secs_f64((needed / self.bytes_per_second).max(0.0)))
+  }
+
+  /// Achieved rate since the previous report, if a report is due.
+  pub fn report_if_due(&mut self, now: Instant) -> Option<String> {
+    let elapsed = now - self.report_start;
+    if elapsed < RATE_REPORT_INTERVAL {
+      return None
+    }
+    let report = format!(
+      "Sent {:.1} kbit/s ({} samples/s) of {:.1} kbit/s budget, {} samples queued",
+      self.sent_bytes as f64 * 8.0 / 1000.0 / elapsed.as_secs_f64(),
+      (self.sent_samples as f64 / elapsed.as_secs_f64()).round(),
+      self.bytes_per_second * 8.0 / 1000.0,
+      self.queue.len()
+    );
+    self.sent_bytes = 0;
+    self.sent_samples = 0;
+    self.report_start = now;
+    Some(report)
+  }
+}
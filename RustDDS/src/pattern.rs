@@ -0,0 +1,64 @@
+/// Publisher motion patterns.
+///
+/// Besides the usual bouncing, shapes can move on a circle or a Lissajous
+/// curve, which cross e.g. x = 100 at predictable times, wander randomly, or
+/// stay in the middle of the drawing area. Circle and Lissajous positions
+/// depend only on the sample number and a phase, so they are the same in
+/// every run.
+use std::f64::consts::PI;
+
+use crate::{move_shape, random, Shape, DA_HEIGHT, DA_WIDTH};
+
+/// Samples per full circle or Lissajous period
+const PERIOD: f64 = 100.0;
+const RANDOM_WALK_STEP: i32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+  Bounce,
+  Circle,
+  Lissajous,
+  RandomWalk,
+  Static,
+}
+
+impl Pattern {
+  pub const VARIANTS: &'static [&'static str] = &["bounce", "circle", "lissajous", "random-walk", "static"];
+
+  pub fn parse(s: &str) -> Result<Pattern, String> {
+    match s {
+      "bounce" => Ok(Pattern::Bounce),
+      "circle" => Ok(Pattern::Circle),
+      "lissajous" => Ok(Pattern::Lissajous),
+      "random-walk" => Ok(Pattern::RandomWalk),
+      "static" => Ok(Pattern::Static),
+      other => Err(format!("Unknown pattern \"{}\". Expected one of {}", other, Pattern::VARIANTS.join(" "))),
+    }
+  }
+
+  /// Next position of `shape` for sample number `step`. Velocities are used by
+  /// bouncing only. `phase` (0..1) separates shapes of several writers.
+  pub fn move_shape(self, shape: Shape, xv: i32, yv: i32, step: u32, phase: f64) -> (Shape, i32, i32) {
+    let half_size = shape.shapesize / 2 + 1;
+    let center_x = f64::from(DA_WIDTH) / 2.0;
+    let center_y = f64::from(DA_HEIGHT) / 2.0;
+    let radius_x = center_x - f64::from(half_size);
+    let radius_y = center_y - f64::from(half_size);
+    let t = 2.0 * PI * (f64::from(step) / PERIOD + phase);
+    let (x, y) = match self {
+      Pattern::Bounce => return move_shape(shape, xv, yv),
+      Pattern::Circle => {
+        let radius = radius_x.min(radius_y);
+        (center_x + radius * t.cos(), center_y + radius * t.sin())
+      }
+      Pattern::Lissajous => (center_x + radius_x * (3.0 * t).sin(), center_y + radius_y * (2.0 * t).sin()),
+      Pattern::RandomWalk => {
+        let x = shape.x + random::int_between(-RANDOM_WALK_STEP, RANDOM_WALK_STEP);
+        let y = shape.y + random::int_between(-RANDOM_WALK_STEP, RANDOM_WALK_STEP);
+        (f64::from(x.clamp(half_size, DA_WIDTH - half_size)), f64::from(y.clamp(half_size, DA_HEIGHT - half_size)))
+      }
+      Pattern::Static => (center_x, center_y),
+    };
+    (Shape { x: x.round() as i32, y: y.round() as i32, ..shape }, xv, yv)
+  }
+}
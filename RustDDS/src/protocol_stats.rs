@@ -0,0 +1,230 @@
+/// Reliability protocol statistics from RTPS multicast user traffic.
+///
+/// RustDDS does not give applications its heartbeat, acknack or retransmission
+/// counts. Like capture, we tap the user traffic multicast group of the domain
+/// and count submessages per writer: DATA, DATA repeated with an already seen
+/// sequence number (retransmissions), HEARTBEAT, and ACKNACK addressed to the
+/// writer. Unicast traffic is not visible, so counts cover only what remote
+/// vendors and RustDDS send to multicast. Per-reader counts are not available.
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::net::SocketAddrV4;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::error;
+
+use crate::capture::{multicast_socket, DEFAULT_MULTICAST_GROUP, DOMAIN_ID_GAIN, OFFSET_D2, PORT_BASE};
+
+const SUBMESSAGE_ACKNACK: u8 = 0x06;
+const SUBMESSAGE_HEARTBEAT: u8 = 0x07;
+const SUBMESSAGE_INFO_DST: u8 = 0x0e;
+pub const SUBMESSAGE_DATA: u8 = 0x15;
+const SUBMESSAGE_DATA_FRAG: u8 = 0x16;
+
+pub const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Sequence numbers remembered below the highest seen, per writer
+const WINDOW: u64 = 1024;
+
+/// Sequence numbers seen from one writer: the highest, and which of the
+/// WINDOW numbers below it, so memory stays constant however long we run.
+#[derive(Clone)]
+struct SeenSequenceNumbers {
+  highest: Option<u64>,
+  /// Bit sn % WINDOW is set if sn is seen
+  bits: [u64; (WINDOW / 64) as usize],
+}
+
+impl Default for SeenSequenceNumbers {
+  fn default() -> SeenSequenceNumbers {
+    SeenSequenceNumbers { highest: None, bits: [0; (WINDOW / 64) as usize] }
+  }
+}
+
+impl SeenSequenceNumbers {
+  fn bit(&mut self, sn: u64) -> (&mut u64, u64) {
+    let i = sn % WINDOW;
+    (&mut self.bits[(i / 64) as usize], 1 << (i % 64))
+  }
+
+  /// Records a sequence number, and tells if it was not seen before. Numbers
+  /// more than WINDOW below the highest count as seen: a writer sends them
+  /// again only as retransmissions.
+  fn insert(&mut self, sn: u64) -> bool {
+    match self.highest {
+      Some(highest) if sn <= highest => {
+        if highest - sn >= WINDOW {
+          return false
+        }
+        let (word, mask) = self.bit(sn);
+        let new = *word & mask == 0;
+        *word |= mask;
+        new
+      }
+      highest => {
+        // Forget the numbers that drop out of the window.
+        let first_new = highest.map_or(sn, |h| h + 1);
+        if sn - first_new >= WINDOW {
+          self.bits = [0; (WINDOW / 64) as usize];
+        } else {
+          for n in first_new ..= sn {
+            let (word, mask) = self.bit(n);
+            *word &= !mask;
+          }
+        }
+        let (word, mask) = self.bit(sn);
+        *word |= mask;
+        self.highest = Some(sn);
+        true
+      }
+    }
+  }
+}
+
+#[derive(Default, Clone)]
+struct WriterCounts {
+  data: u64,
+  retransmitted: u64,
+  heartbeats: u64,
+  acknacks: u64,
+  sequence_numbers: SeenSequenceNumbers,
+}
+
+/// Counts by writer GUID in hex
+#[derive(Clone, Default)]
+pub struct ProtocolStats {
+  writers: Arc<Mutex<BTreeMap<String, WriterCounts>>>,
+}
+
+impl ProtocolStats {
+  /// One line per writer
+  pub fn report(&self) -> Vec<String> {
+    let writers = self.writers.lock().unwrap();
+    if writers.is_empty() {
+      return vec!["Protocol: no writers seen in multicast user traffic.".to_string()]
+    }
+    writers.iter().map(|(guid, c)| {
+      format!("Protocol from writer {}: DATA {} ({} retransmitted), HEARTBEAT {}, ACKNACK received {}",
+        guid, c.data, c.retransmitted, c.heartbeats, c.acknacks)
+    }).collect()
+  }
+
+  fn record_message(&self, msg: &[u8]) {
+    if !msg.starts_with(b"RTPS") || msg.len() < 20 {
+      return
+    }
+    let source_prefix = hex(&msg[8..20]);
+    let mut destination_prefix: Option<String> = None;
+    let mut writers = self.writers.lock().unwrap();
+    let mut pos = 20;
+    while pos + 4 <= msg.len() {
+      let id = msg[pos];
+      let little_endian = msg[pos + 1] & 0x01 != 0;
+      let length = u16_at(msg, pos + 2, little_endian) as usize;
+      let body_start = pos + 4;
+      let body_end = if length == 0 { msg.len() } else { (body_start + length).min(msg.len()) };
+      let body = &msg[body_start..body_end];
+      match id {
+        SUBMESSAGE_INFO_DST if body.len() >= 12 => destination_prefix = Some(hex(&body[..12])),
+        SUBMESSAGE_DATA | SUBMESSAGE_DATA_FRAG if body.len() >= 20 => {
+          let w = writers.entry(format!("{}.{}", source_prefix, hex(&body[8..12]))).or_default();
+          let high = u32_at(body, 12, little_endian) as u64;
+          let low = u32_at(body, 16, little_endian) as u64;
+          w.data += 1;
+          // Fragments of one sample share the sequence number, so only whole DATA counts as repeated.
+          if !w.sequence_numbers.insert(high << 32 | low) && id == SUBMESSAGE_DATA {
+            w.retransmitted += 1;
+          }
+        }
+        SUBMESSAGE_HEARTBEAT if body.len() >= 8 =>
+          writers.entry(format!("{}.{}", source_prefix, hex(&body[4..8]))).or_default().heartbeats += 1,
+        SUBMESSAGE_ACKNACK if body.len() >= 8 => {
+          if let Some(prefix) = &destination_prefix {
+            writers.entry(format!("{}.{}", prefix, hex(&body[4..8]))).or_default().acknacks += 1;
+          }
+        }
+        _ => (),
+      }
+      pos = body_end;
+    }
+  }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn u16_at(b: &[u8], pos: usize, little_endian: bool) -> u16 {
+  let b: [u8; 2] = b[pos..pos + 2].try_into().unwrap();
+  if little_endian { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) }
+}
+
+pub fn u32_at(b: &[u8], pos: usize, little_endian: bool) -> u32 {
+  let b: [u8; 4] = b[pos..pos + 4].try_into().unwrap();
+  if little_endian { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) }
+}
+
+/// Start counting multicast user traffic of the domain in a background thread,
+/// which also prints the counts every REPORT_INTERVAL.
+pub fn start_protocol_stats(domain_id: u16) -> std::io::Result<ProtocolStats> {
+  let stats = ProtocolStats::default();
+  let port = PORT_BASE + DOMAIN_ID_GAIN * domain_id + OFFSET_D2;
+  let socket = multicast_socket(SocketAddrV4::new(DEFAULT_MULTICAST_GROUP, port))?;
+  socket.set_read_timeout(Some(Duration::from_secs(1)))?;
+  let s = stats.clone();
+  thread::Builder::new()
+    .name("protocol-stats".to_string())
+    .spawn(move || {
+      let mut buf = vec![0u8; 65536];
+      let mut next_report = Instant::now() + REPORT_INTERVAL;
+      loop {
+        match socket.recv_from(&mut buf) {
+          Ok((len, _src)) => s.record_message(&buf[..len]),
+          Err(e) if e.kind() == std::io::ErrorKind::WouldBlock || e.kind() == std::io::ErrorKind::TimedOut => (),
+          Err(e) => {
+            error!("Protocol stats socket receive failed, stopping: {:?}", e);
+            return
+          }
+        }
+        if Instant::now() >= next_report {
+          next_report += REPORT_INTERVAL;
+          for line in s.report() {
+            println!("{}", line);
+          }
+        }
+      }
+    })?;
+  Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn repeated_sequence_numbers() {
+    let mut seen = SeenSequenceNumbers::default();
+    let cases = [
+      (5, true),
+      (5, false),
+      (3, true),
+      (3, false),
+      (6, true),
+      (4, true),
+      (6, false),
+      (5 + WINDOW, true),
+      (6, false), // still in the window
+      (7, true),
+      (5, false), // out of the window
+      (10 * WINDOW, true),
+      (10 * WINDOW - 1, true),
+      (6 + WINDOW, false), // out of the window
+      (10 * WINDOW, false),
+    ];
+    for (sn, new) in &cases {
+      assert_eq!(seen.insert(*sn), *new, "{}", sn);
+    }
+  }
+}
@@ -0,0 +1,531 @@
+/// Publisher role: DataWriters moving shapes around, and everything that can
+/// be done to the samples on the way to DataWriter write.
+use std::cmp::max;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+use log::{debug, trace};
+use mio::{Events, PollOpt, Ready};
+use rustdds::dds::qos::QosPolicies;
+use rustdds::dds::statusevents::{DataWriterStatus, StatusEvented};
+use rustdds::dds::traits::{RTPSEntity, TopicDescription};
+use rustdds::dds::{DomainParticipant, Topic};
+
+use crate::ack_trace::AckTrace;
+use crate::backpressure::WriteMonitor;
+use crate::batch::{cdr_size, BatchLimit, Batcher};
+use crate::builtin_topics::BuiltinTopics;
+use crate::console::Command;
+use crate::impairment::{DelayQueue, Impairment};
+use crate::inconsistent_topic::InconsistentTopics;
+use crate::input::InputSample;
+use crate::match_wait::MatchWait;
+use crate::on_error::OnError;
+use crate::pacing::Pacer;
+use crate::pattern::Pattern;
+use crate::soak::{Soak, SoakStatus};
+use crate::source_time::SourceTimestamps;
+use crate::state::PublisherState;
+use crate::status_report::{self, LogFormat};
+use crate::{cdr_dump, filter, keyhash, timeline};
+use crate::{endpoint_token, random_velocity, shape_json,
+  split_endpoint_token, Control, Shape, ShapeData, COMMAND_READY, DEFAULT_WRITE_INTERVAL, EXIT_SOAK_FAILED,
+  RESTART_PARTICIPANT, SHAPE_COLORS, STATUS_READY, STOP_PROGRAM};
+
+#[derive(Debug, Clone)]
+pub struct PublisherOptions {
+  pub impairment: Impairment,
+  pub recreate_writer_every: Option<Duration>,
+  /// (after, duration)
+  pub simulate_hang: Option<(Duration, Duration)>,
+  pub writers_per_topic: usize,
+  pub pattern: Pattern,
+  pub batch: Option<BatchLimit>,
+  /// kbit/s
+  pub max_bandwidth: Option<f64>,
+  pub long_key: bool,
+  pub source_timestamps: Option<SourceTimestamps>,
+  /// (hours, max RSS growth in kB/h)
+  pub soak: Option<(f64, f64)>,
+  pub input: Option<Vec<InputSample>>,
+  pub no_writer_filter: bool,
+  pub wait_for_match: bool,
+  pub builtin_topics: bool,
+  /// Counter values not to write
+  pub skip_counters: BTreeSet<u32>,
+  pub ack_trace: bool,
+  /// Number of samples to hex-dump per writer
+  pub dump_cdr: usize,
+}
+
+impl Default for PublisherOptions {
+  fn default() -> PublisherOptions {
+    PublisherOptions {
+      impairment: Impairment::default(),
+      recreate_writer_every: None,
+      simulate_hang: None,
+      writers_per_topic: 1,
+      pattern: Pattern::Bounce,
+      batch: None,
+      max_bandwidth: None,
+      long_key: false,
+      source_timestamps: None,
+      soak: None,
+      input: None,
+      no_writer_filter: false,
+      wait_for_match: false,
+      builtin_topics: false,
+      skip_counters: BTreeSet::new(),
+      ack_trace: false,
+      dump_cdr: 0,
+    }
+  }
+}
+
+/// Colors for n writers: the given color first, then the other demo colors.
+/// If more are needed, they are numbered: RED2, GREEN2, ...
+fn writer_colors(color: &str, n: usize) -> Vec<String> {
+  let others = SHAPE_COLORS.iter().filter( |c| **c != color );
+  let palette: Vec<&str> = std::iter::once(color).chain(others.cloned()).collect();
+  (0..n).map( |i| match i / palette.len() {
+    0 => palette[i].to_string(),
+    round => format!("{}{}", palette[i % palette.len()], round + 1),
+  }).collect()
+}
+
+fn print_publisher_totals<W, T: ShapeData>(writers: &[ShapeWriter<W>], batcher: &Option<Batcher<T>>,
+    no_writer_filter: bool)
+{
+  if writers.len() > 1 {
+    print_writer_summary(writers);
+  }
+  if let Some(b) = batcher {
+    println!("{}", b.report());
+  }
+  let written = writers.iter().map( |w| w.state.samples_written ).sum();
+  let matched = writers.iter().map( |w| w.state.matched_readers ).max().unwrap_or(0);
+  println!("{}", filter::writer_side_report(written, matched, no_writer_filter));
+}
+
+fn print_writer_summary<W>(writers: &[ShapeWriter<W>]) {
+  for w in writers {
+    println!("Writer {} on {}: samples written {}, matched readers {}",
+      w.state.color, w.topic_name, w.state.samples_written, w.state.matched_readers);
+  }
+}
+
+/// A DataWriter together with the shape it is moving around.
+struct ShapeWriter<W> {
+  writer: W,
+  topic: Topic,
+  topic_name: String,
+  created: Instant,
+  matched_once: bool,
+  write_monitor: WriteMonitor,
+  shape: Shape,
+  x_vel: i32,
+  y_vel: i32,
+  /// Offset of this writer along the motion pattern, 0..1
+  phase: f64,
+  state: PublisherState,
+  /// Written samples still to hex-dump with --dump-cdr
+  cdr_dumps_left: usize,
+}
+
+/// Writes a sample with `write` on the DataWriter of `w`, following the
+/// --on-error policy. Returns whether the sample was written, or Err(exit code).
+fn write_shape<W, T: ShapeData>(w: &mut ShapeWriter<W>, sample: T, on_error: OnError,
+    write: impl Fn(&mut W, T) -> rustdds::dds::Result<()>) -> Result<bool, i32>
+{
+  let written = on_error.write( || {
+    let result = write(&mut w.writer, sample.clone());
+    w.write_monitor.record(&w.topic_name, &result, &mut w.state)
+  })?;
+  if written {
+    timeline::record("write", Some(&w.topic_name), shape_json(&sample));
+    w.state.samples_written += 1;
+    if w.cdr_dumps_left > 0 {
+      w.cdr_dumps_left -= 1;
+      cdr_dump::print_outgoing(&w.topic_name, &sample);
+    }
+  }
+  Ok(written)
+}
+
+pub fn run_publisher<T: ShapeData>(domain_participant: &DomainParticipant, topics: Vec<Topic>, qos: &QosPolicies,
+    color: &str, options: PublisherOptions, log_format: LogFormat, control: &Control) -> i32
+{
+  debug!("Publisher");
+  let started = Instant::now();
+  let impairment = options.impairment;
+  let mut events = Events::with_capacity(4);
+  let publisher = match control.on_error.create("Publisher", || domain_participant.create_publisher(qos)) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let create_writer = |topic: &Topic, i: usize| -> Result<_, i32> {
+    let mut writer = control.on_error.create("DataWriter", ||
+      publisher.create_datawriter_CDR::<T>( topic.clone(), None))?; // None = get qos policy from publisher
+    status_report::print_guid("DataWriter", &writer.get_guid(), Some(topic.get_name()));
+    control.poll.register(writer.as_status_evented(), endpoint_token(STATUS_READY, i),
+        Ready::readable(), PollOpt::edge())
+        .unwrap();
+    Ok(writer)
+  };
+  let colors: Vec<String> = writer_colors(color, options.writers_per_topic).into_iter()
+    .map( |c| if options.long_key { c + keyhash::LONG_KEY_SUFFIX } else { c } ).collect();
+  if options.long_key {
+    for c in &colors {
+      println!("Key hash of {} is {}", c, keyhash::format_hash(&keyhash::key_hash(c)));
+    }
+  }
+  let writer_topics = topics.iter().flat_map( |topic| colors.iter().map( move |c| (topic, c) ) );
+  let writers: Result<Vec<_>, i32> = writer_topics.enumerate().map( |(i, (topic, color))| {
+    let topic_name = topic.get_name().to_string();
+    if log_format == LogFormat::Compat {
+      println!("Create writer for topic: {} color: {}", topic_name, color);
+    }
+    Ok(ShapeWriter {
+      writer: create_writer(topic, i)?,
+      topic: topic.clone(),
+      created: Instant::now(),
+      matched_once: false,
+      write_monitor: WriteMonitor::default(),
+      state: PublisherState::new(&topic_name, color),
+      topic_name,
+      shape: Shape { color: color.to_string(), x: 0, y: 0, shapesize: 21 },
+      x_vel: random_velocity(),
+      y_vel: random_velocity(),
+      phase: (i % options.writers_per_topic) as f64 / options.writers_per_topic as f64,
+      cdr_dumps_left: options.dump_cdr,
+    })
+  }).collect();
+  let mut writers = match writers {
+    Ok(writers) => writers,
+    Err(code) => return code,
+  };
+  let multiple_writers = options.writers_per_topic > 1;
+  let endpoint_count = writers.len();
+  let mut unmatched = endpoint_count;
+  let mut sample_counter: u32 = 0;
+  let mut paused = false;
+  let mut write_interval = DEFAULT_WRITE_INTERVAL;
+  let mut next_write = Instant::now() + write_interval;
+  // (writer index, sample)
+  let mut delayed: DelayQueue<(usize, T)> = DelayQueue::new();
+  if impairment.is_active() {
+    println!("Impairment: {:?}", impairment);
+  }
+  if !options.skip_counters.is_empty() {
+    println!("Skipping counters: {:?}", options.skip_counters);
+  }
+  let mut ack_trace = options.ack_trace.then( || AckTrace::new(writers.len()) );
+  if options.ack_trace {
+    println!("Tracing acknowledgments. RustDDS does not show which reader acknowledged, only when all have.");
+  }
+  let mut next_recreate = options.recreate_writer_every.map( |interval| Instant::now() + interval );
+  // (start, end, announced)
+  let mut hang = options.simulate_hang.map( |(after, duration)| (started + after, started + after + duration, false) );
+  let mut batcher: Option<Batcher<T>> = options.batch.map(Batcher::new);
+  let mut ready_batches: Vec<Vec<(usize, T)>> = Vec::new();
+  let mut pacer: Option<Pacer<T>> = options.max_bandwidth.map(Pacer::new);
+  let mut source_timestamps = options.source_timestamps;
+  if let Some(s) = &source_timestamps {
+    println!("{}", s.describe());
+  }
+  let mut soak = options.soak.map( |(hours, growth)| Soak::new(hours, growth) );
+  if let Some(s) = &soak {
+    println!("{}", s.describe());
+  }
+  let mut inconsistent_topics = InconsistentTopics::new(&topics);
+  let mut match_wait = options.wait_for_match.then( || MatchWait::new(&topics) );
+  let mut builtin_topics = options.builtin_topics.then(BuiltinTopics::default);
+  let mut input = options.input.map(Vec::into_iter);
+  if let Some(limit) = options.batch {
+    println!("Batching {:?}. RustDDS does not batch samples, so each batch is written as a burst.", limit);
+  }
+  loop {
+    let wake_up = delayed.next_due().into_iter().chain(next_recreate)
+      .chain(pacer.as_ref().and_then(Pacer::next_ready))
+      .chain(soak.as_ref().map(Soak::next_due))
+      .chain(ack_trace.as_ref().and_then(AckTrace::next_check))
+      .fold(next_write, |earliest, t| earliest.min(t));
+    control.poll
+      .poll(&mut events, Some(wake_up.saturating_duration_since(Instant::now())))
+      .unwrap();
+    for event in &events {
+      match event.token() {
+        STOP_PROGRAM => {
+          if control.stop_requested() {
+            print_publisher_totals(&writers, &batcher, options.no_writer_filter);
+            println!("Done.");
+            return 0
+          }
+        }
+        COMMAND_READY => {
+          while let Ok(command) = control.command_receiver.try_recv() {
+            match command {
+              Command::Pause => { paused = true; println!("Paused.") }
+              Command::Resume => { paused = false; println!("Resumed.") }
+              Command::TogglePause => {
+                paused = ! paused;
+                println!("{}", if paused { "Paused." } else { "Resumed." });
+              }
+              Command::Snapshot => {
+                for w in writers.iter_mut() {
+                  w.state.paused = paused;
+                  match log_format {
+                    LogFormat::Text | LogFormat::Compat => println!("{}", w.state.text_snapshot()),
+                    LogFormat::Json => status_report::print_json_event("Publisher", &w.topic_name, "snapshot",
+                      serde_json::to_value(&w.state).unwrap()),
+                  }
+                }
+                if let Some(b) = &batcher {
+                  println!("{}", b.report());
+                }
+                let queued = delayed.len() + batcher.as_ref().map_or(0, Batcher::pending)
+                  + pacer.as_ref().map_or(0, Pacer::queued);
+                println!("Publish mode async: {} samples queued before DataWriter write. \
+                  Queue depth inside RustDDS is not visible.", queued);
+              }
+              Command::Dispose(c) => for w in writers.iter_mut() {
+                println!("Disposing {} on {}", c, w.topic_name);
+                let key = T::from_shape(Shape { color: c.clone(), ..w.shape.clone() }, 0).get_key();
+                match w.writer.dispose(key, None) {
+                  Ok(()) => w.state.disposed.push(c.clone()),
+                  Err(e) => println!("Dispose failed: {:?}", e),
+                }
+              }
+              Command::Unregister(_) =>
+                println!("unregister is not supported by RustDDS DataWriter."),
+              Command::Rate(interval) => {
+                write_interval = interval;
+                next_write = Instant::now() + write_interval;
+                println!("Writing every {:?}", write_interval);
+              }
+              Command::Color(c) => {
+                println!("Now publishing color {}", c);
+                // The first writer of each topic takes the new color, the others the rest of the palette.
+                let colors = writer_colors(&c, options.writers_per_topic);
+                for (w, c) in writers.iter_mut().zip(colors.iter().cycle()) {
+                  let c = if options.long_key { c.clone() + keyhash::LONG_KEY_SUFFIX } else { c.clone() };
+                  w.state.color = c.clone();
+                  w.shape.color = c;
+                }
+              }
+              Command::RestartParticipant => return RESTART_PARTICIPANT,
+              Command::Exit(code) => {
+                print_publisher_totals(&writers, &batcher, options.no_writer_filter);
+                println!("Done.");
+                return code
+              }
+            }
+          }
+        }
+        other_token => match split_endpoint_token(other_token) {
+          (STATUS_READY, i) if i < writers.len() => {
+            let w = &mut writers[i];
+            while let Some(status) = w.writer.try_recv_status() {
+              if multiple_writers && log_format == LogFormat::Text {
+                print!("Writer {}: ", w.state.color);
+              }
+              w.state.update_status(&status);
+              status_report::report_writer_status(log_format, &w.topic_name, &status);
+              if let DataWriterStatus::PublicationMatched { .. } = status {
+                if !w.matched_once {
+                  w.matched_once = true;
+                  println!("First match on {} {:.3} s after creating DataWriter", w.topic_name,
+                    w.created.elapsed().as_secs_f64());
+                  unmatched -= 1;
+                  if unmatched == 0 && endpoint_count > 1 {
+                    println!("All {} DataWriters matched {:.3} s after start", endpoint_count,
+                      started.elapsed().as_secs_f64());
+                  }
+                }
+                control.report_locators();
+              }
+            }
+          }
+          _ => println!("Polled event is {:?}. WTF?", other_token),
+        }
+      }
+    }
+
+    inconsistent_topics.check(log_format, domain_participant);
+    if let (Some(m), true) = (match_wait.as_mut(), unmatched > 0) {
+      let waiting: Vec<&str> = writers.iter().filter( |w| !w.matched_once ).map( |w| w.topic_name.as_str() ).collect();
+      m.report(&waiting, domain_participant, control.discovered.as_ref());
+    }
+    if let Some(b) = builtin_topics.as_mut() {
+      b.poll(domain_participant, control.discovered.as_ref());
+    }
+    let now = Instant::now();
+    while let Some((i, sample)) = delayed.pop_due(now) {
+      if let Some(p) = pacer.as_mut() {
+        let size = cdr_size(&sample);
+        p.push(i, sample, size);
+        continue
+      }
+      let timestamp = source_timestamps.as_mut().and_then(SourceTimestamps::next_timestamp);
+      if let Err(code) = write_shape(&mut writers[i], sample, control.on_error, |w, s| w.write(s, timestamp)) {
+        return code
+      }
+    }
+    if let Some(p) = pacer.as_mut() {
+      while let Some((i, sample)) = p.pop_ready(Instant::now()) {
+        let timestamp = source_timestamps.as_mut().and_then(SourceTimestamps::next_timestamp);
+        if let Err(code) = write_shape(&mut writers[i], sample, control.on_error, |w, s| w.write(s, timestamp)) {
+          return code
+        }
+      }
+      if let Some(report) = p.report_if_due(now) {
+        println!("{}", report);
+      }
+    }
+    if let Some(s) = soak.as_mut() {
+      let written: u64 = writers.iter().map( |w| w.state.samples_written ).sum();
+      let failed: u64 = writers.iter().map( |w| w.state.writes_failed ).sum();
+      let exit_code = match s.poll(now, &format!("samples written {}, writes failed {}", written, failed)) {
+        SoakStatus::Running => None,
+        SoakStatus::Passed(verdict) => { println!("{}", verdict); Some(0) }
+        SoakStatus::Failed(verdict) => { println!("{}", verdict); Some(EXIT_SOAK_FAILED) }
+      };
+      if let Some(code) = exit_code {
+        print_publisher_totals(&writers, &batcher, options.no_writer_filter);
+        println!("Done.");
+        return code
+      }
+    }
+    if let (Some(t), Some(interval)) = (next_recreate, options.recreate_writer_every) {
+      if t <= now {
+        next_recreate = Some(max(t + interval, now));
+        let recreated: Result<Vec<_>, i32> = writers.into_iter().enumerate().map( |(i, w)| {
+          drop(w.writer); // deletes the DataWriter
+          let writer = create_writer(&w.topic, i)?;
+          println!("Recreated DataWriter on {}.", w.topic_name);
+          Ok(ShapeWriter { writer, created: Instant::now(), matched_once: false, ..w })
+        }).collect();
+        writers = match recreated {
+          Ok(writers) => writers,
+          Err(code) => return code,
+        };
+        unmatched = endpoint_count;
+        if let Some(a) = ack_trace.as_mut() {
+          a.clear();
+        }
+      }
+    }
+    if let Some(a) = ack_trace.as_mut() {
+      a.record_writes(writers.iter().map( |w| w.state.samples_written ));
+      for (i, w) in writers.iter().enumerate() {
+        if a.is_pending(i) {
+          a.update(i, &w.topic_name, w.writer.wait_for_acknowledgments(Duration::ZERO));
+        }
+      }
+    }
+    if now < next_write {
+      continue // woke up early because of some event
+    }
+    next_write = max(next_write + write_interval, now);
+    if paused {
+      continue
+    }
+    if let Some((start, end, announced)) = hang.as_mut() {
+      if now >= *end {
+        let lost: i32 = writers.iter().map( |w| w.state.status_counts.liveliness_lost ).sum();
+        println!("Simulated hang over. Writing again, which asserts liveliness. Own LivelinessLost count is {}.",
+          lost);
+        hang = None;
+      } else if now >= *start {
+        if !*announced {
+          *announced = true;
+          println!("Simulating hang for {:.1} s: not writing or asserting liveliness.", (*end - *start).as_secs_f64());
+        }
+        continue
+      }
+    }
+
+    if options.long_key && sample_counter % keyhash::DISPOSE_EVERY == keyhash::DISPOSE_EVERY - 1 {
+      for w in writers.iter_mut() {
+        println!("Disposing {} on {}", w.shape.color, w.topic_name);
+        match w.writer.dispose(T::from_shape(w.shape.clone(), 0).get_key(), None) {
+          Ok(()) => w.state.disposed.push(w.shape.color.clone()),
+          Err(e) => println!("Dispose failed: {:?}", e),
+        }
+      }
+    }
+    let input_sample = match input.as_mut().map(Iterator::next) {
+      Some(None) => {
+        println!("All input samples written.");
+        input = Some(Vec::new().into_iter());
+        paused = true;
+        continue
+      }
+      Some(Some(s)) => Some(s),
+      None => None,
+    };
+    if let Some(delay) = input_sample.as_ref().and_then( |s| s.delay ) {
+      next_write = now + delay;
+    }
+    for (i, w) in writers.iter_mut().enumerate() {
+      if let Some(s) = &input_sample {
+        if i % options.writers_per_topic != 0 {
+          continue
+        }
+        w.shape = s.shape.clone();
+      } else {
+        let r = options.pattern.move_shape(w.shape.clone(), w.x_vel, w.y_vel, sample_counter, w.phase);
+        w.shape = r.0;
+        w.x_vel = r.1;
+        w.y_vel = r.2;
+      }
+
+      if impairment.should_drop() {
+        trace!("Dropping shape color {} on {}", &w.shape.color, &w.topic_name);
+        w.state.samples_dropped += 1;
+        continue
+      }
+      if options.skip_counters.contains(&sample_counter) {
+        println!("Skipped counter {} of {} on {}", sample_counter, w.shape.color, w.topic_name);
+        continue
+      }
+      let sample = T::from_shape(w.shape.clone(), sample_counter);
+      if impairment.is_active() {
+        delayed.push(now + impairment.next_delay(), (i, sample));
+        continue
+      }
+      if let Some(b) = batcher.as_mut() {
+        ready_batches.extend(b.push(i, sample));
+        continue
+      }
+      if let Some(p) = pacer.as_mut() {
+        let size = cdr_size(&sample);
+        p.push(i, sample, size);
+        continue
+      }
+      // write to DDS
+      trace!("Writing shape color {} on {}", &w.shape.color, &w.topic_name);
+      let timestamp = source_timestamps.as_mut().and_then(SourceTimestamps::next_timestamp);
+      if let Err(code) = write_shape(w, sample, control.on_error, |w, s| w.write(s, timestamp)) {
+        return code
+      }
+    }
+    for (i, sample) in ready_batches.drain(..).flatten() {
+      if let Some(p) = pacer.as_mut() {
+        let size = cdr_size(&sample);
+        p.push(i, sample, size);
+        continue
+      }
+      trace!("Writing batched sample on {}", &writers[i].topic_name);
+      let timestamp = source_timestamps.as_mut().and_then(SourceTimestamps::next_timestamp);
+      if let Err(code) = write_shape(&mut writers[i], sample, control.on_error, |w, s| w.write(s, timestamp)) {
+        return code
+      }
+    }
+    if let Some(a) = ack_trace.as_mut() {
+      a.record_writes(writers.iter().map( |w| w.state.samples_written ));
+    }
+    sample_counter = sample_counter.wrapping_add(1);
+  } // loop
+}
+
@@ -0,0 +1,85 @@
+/// QoS policies of the test, as typed configuration. The same policies are
+/// used for Topic, Publisher, Subscriber, DataWriter and DataReader.
+use rustdds::dds::data_types::DDSDuration;
+use rustdds::dds::qos::policy::{Deadline, Durability, History, Liveliness, Ownership, Reliability};
+use rustdds::dds::qos::{QosPolicies, QosPolicyBuilder};
+
+// Liveliness lease with --simulate-hang. Writes assert liveliness more often than this.
+const HANG_LEASE_DURATION: f64 = 1.0;
+
+#[derive(Debug, Clone)]
+pub struct QosConfig {
+  pub reliable: bool,
+  pub durability: Durability,
+  /// KeepLast depth. None or negative means KeepAll.
+  pub history_depth: Option<i32>,
+  /// Deadline period in seconds
+  pub deadline: Option<f64>,
+  /// ManualByTopic liveliness with a short lease, so that a simulated hang is noticed
+  pub manual_liveliness: bool,
+  /// EXCLUSIVE ownership with this strength. None means SHARED.
+  pub ownership_strength: Option<i32>,
+}
+
+impl Default for QosConfig {
+  fn default() -> QosConfig {
+    QosConfig { reliable: false, durability: Durability::Volatile, history_depth: None, deadline: None,
+      manual_liveliness: false, ownership_strength: None }
+  }
+}
+
+impl QosConfig {
+  pub fn policies(&self) -> QosPolicies {
+    let mut qos_b = QosPolicyBuilder::new()
+      .reliability(if self.reliable {
+        Reliability::Reliable { max_blocking_time: DDSDuration::DURATION_ZERO }
+      } else {
+        Reliability::BestEffort
+      })
+      .durability(self.durability)
+      .history(match self.history_depth {
+        Some(d) if d >= 0 => History::KeepLast { depth: d },
+        _ => History::KeepAll,
+      });
+    if let Some(d) = self.deadline {
+      qos_b = qos_b.deadline(Deadline(DDSDuration::from_frac_seconds(d)));
+    }
+    if self.manual_liveliness {
+      qos_b = qos_b.liveliness(
+        Liveliness::ManualByTopic { lease_duration: DDSDuration::from_frac_seconds(HANG_LEASE_DURATION) });
+    }
+    let qos = qos_b.build();
+    match self.ownership_strength {
+      Some(strength) => exclusive_ownership(&qos, strength),
+      None => qos,
+    }
+  }
+}
+
+/// `qos` with EXCLUSIVE ownership of given strength
+pub fn exclusive_ownership(qos: &QosPolicies, strength: i32) -> QosPolicies {
+  qos.modify_by(&QosPolicyBuilder::new().ownership(Ownership::Exclusive { strength }).build())
+}
+
+/// -D values
+pub const DURABILITY_LETTERS: &[&str] = &["v", "l", "t", "p"];
+
+/// Durability from its -D letter: v, l, t or p. Anything else is Volatile.
+pub fn durability_from_letter(letter: Option<&str>) -> Durability {
+  match letter {
+    Some("l") => Durability::TransientLocal,
+    Some("t") => Durability::Transient,
+    Some("p") => Durability::Persistent,
+    _ => Durability::Volatile,
+  }
+}
+
+/// Durability kind as named in --capabilities
+pub fn durability_name(durability: Durability) -> &'static str {
+  match durability {
+    Durability::Volatile => "volatile",
+    Durability::TransientLocal => "transient_local",
+    Durability::Transient => "transient",
+    Durability::Persistent => "persistent",
+  }
+}
@@ -0,0 +1,79 @@
+/// Random numbers for shape motion and impairment.
+///
+/// With the "rand" feature these come from rand's thread_rng. The slim build
+/// does without rand and uses a small xorshift generator seeded from the
+/// clock, which is plenty for moving shapes and dropping samples.
+#[cfg(feature = "rand")]
+use rand::prelude::*;
+
+/// True with probability p
+#[cfg(feature = "rand")]
+pub fn chance(p: f64) -> bool {
+  thread_rng().gen_bool(p)
+}
+
+/// Uniform in low..=high
+#[cfg(feature = "rand")]
+pub fn float_between(low: f64, high: f64) -> f64 {
+  thread_rng().gen_range(low..=high)
+}
+
+/// Uniform in low..=high
+#[cfg(feature = "rand")]
+pub fn int_between(low: i32, high: i32) -> i32 {
+  thread_rng().gen_range(low..=high)
+}
+
+#[cfg(not(feature = "rand"))]
+use std::cell::Cell;
+
+#[cfg(not(feature = "rand"))]
+thread_local! {
+  static STATE: Cell<u64> = Cell::new(seed());
+}
+
+#[cfg(not(feature = "rand"))]
+fn seed() -> u64 {
+  use std::time::{SystemTime, UNIX_EPOCH};
+  let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+  // xorshift must not start from zero
+  (nanos ^ u64::from(std::process::id()).rotate_left(32)) | 1
+}
+
+/// xorshift64*
+#[cfg(not(feature = "rand"))]
+fn next_u64() -> u64 {
+  STATE.with(|state| {
+    let mut x = state.get();
+    x ^= x >> 12;
+    x ^= x << 25;
+    x ^= x >> 27;
+    state.set(x);
+    x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+  })
+}
+
+/// Uniform in 0..1
+#[cfg(not(feature = "rand"))]
+fn unit() -> f64 {
+  (next_u64() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// True with probability p
+#[cfg(not(feature = "rand"))]
+pub fn chance(p: f64) -> bool {
+  unit() < p
+}
+
+/// Uniform in low..=high
+#[cfg(not(feature = "rand"))]
+pub fn float_between(low: f64, high: f64) -> f64 {
+  low + unit() * (high - low)
+}
+
+/// Uniform in low..=high
+#[cfg(not(feature = "rand"))]
+pub fn int_between(low: i32, high: i32) -> i32 {
+  let span = (i64::from(high) - i64::from(low) + 1) as u64;
+  (i64::from(low) + (next_u64() % span) as i64) as i32
+}
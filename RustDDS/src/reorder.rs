@@ -0,0 +1,49 @@
+/// Out-of-order reception per remote writer, based on the counter embedded in
+/// CountedShape.
+///
+/// A writer's counters increase with every write, so a sample with a counter
+/// below the highest one already received from the same writer arrived out of
+/// order. The reordering distance is how far below it was.
+use std::collections::BTreeMap;
+
+use rustdds::structure::guid::GUID;
+
+use crate::status_report::format_guid;
+
+#[derive(Default)]
+struct WriterOrder {
+  highest: u32,
+  received: u64,
+  out_of_order: u64,
+  max_distance: u32,
+}
+
+#[derive(Default)]
+pub struct ReorderStats {
+  /// by writer GUID in hex
+  writers: BTreeMap<String, WriterOrder>,
+}
+
+impl ReorderStats {
+  pub fn record(&mut self, writer: &GUID, counter: u32) {
+    let w = self.writers.entry(format_guid(writer)).or_default();
+    if w.received > 0 && counter < w.highest {
+      w.out_of_order += 1;
+      w.max_distance = w.max_distance.max(w.highest - counter);
+    }
+    w.highest = w.highest.max(counter);
+    w.received += 1;
+  }
+
+  pub fn is_active(&self) -> bool {
+    !self.writers.is_empty()
+  }
+
+  /// One line per writer
+  pub fn report(&self) -> Vec<String> {
+    self.writers.iter().map(|(guid, w)| {
+      format!("Order from writer {}: {} of {} samples out of order, max reordering distance {}",
+        guid, w.out_of_order, w.received, w.max_distance)
+    }).collect()
+  }
+}
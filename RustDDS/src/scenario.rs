@@ -0,0 +1,97 @@
+/// Canned interop test scenarios: named sets of options for the publisher and
+/// subscriber roles, so that a test against another vendor needs only the
+/// scenario name on the RustDDS side.
+///
+/// The remote end runs the matching options in its own Shapes demo, as told
+/// in the description.
+pub struct Scenario {
+  pub name: &'static str,
+  pub description: &'static str,
+  /// Options of each role, or None if RustDDS cannot take that role in the scenario
+  pub publisher: Option<&'static [&'static str]>,
+  pub subscriber: Option<&'static [&'static str]>,
+}
+
+pub const SCENARIOS: &[Scenario] = &[
+  Scenario {
+    name: "reliable-basic",
+    description: "RELIABLE, VOLATILE. Every sample written after matching should reach the subscriber.",
+    publisher: Some(&["-r"]),
+    subscriber: Some(&["-r"]),
+  },
+  Scenario {
+    name: "best-effort-reader",
+    description: "RELIABLE writer and BEST_EFFORT reader. These match, as the reader requests less than \
+                  the writer offers.",
+    publisher: Some(&["-r"]),
+    subscriber: Some(&["-b"]),
+  },
+  Scenario {
+    name: "reliable-durability-latejoin",
+    description: "RELIABLE, TRANSIENT_LOCAL, KEEP_LAST 5. Start the publisher first and the subscriber a few \
+                  seconds later. On joining, the subscriber should get the last 5 samples of each instance. \
+                  With RustDDS at both ends, add -- --counter to the publisher and -- --counter --check-history \
+                  to the subscriber to check this automatically.",
+    publisher: Some(&["-r", "-D", "l", "-k", "5"]),
+    subscriber: Some(&["-r", "-D", "l", "-k", "5"]),
+  },
+  Scenario {
+    name: "deadline-met",
+    description: "Writer offers a 0.5 s deadline and reader requests 1 s. The publisher writes every 200 ms, \
+                  so neither end should report a missed deadline.",
+    publisher: Some(&["-r", "-f", "0.5"]),
+    subscriber: Some(&["-r", "-f", "1"]),
+  },
+  Scenario {
+    name: "ownership-failover",
+    description: "Two EXCLUSIVE ownership writers of strength 10 and 20 publish one instance, with shape size \
+                  equal to strength. The stronger one is deleted after 50 samples, and the remote subscriber \
+                  (EXCLUSIVE ownership) should switch from size 20 to size 10. Publisher only: RustDDS readers \
+                  cannot request EXCLUSIVE ownership.",
+    publisher: Some(&["-r", "--ownership-failover"]),
+    subscriber: None,
+  },
+  Scenario {
+    name: "content-filter-x",
+    description: "Subscriber with content filter \"x > 100\". The filter report at exit tells where samples \
+                  were filtered: none rejected at the reader means the remote writer filtered, rejected samples \
+                  mean it sent them all. A RustDDS publisher does not filter, so with it samples are rejected.",
+    publisher: Some(&["-r"]),
+    subscriber: Some(&["-r", "--filter", "x > 100"]),
+  },
+];
+
+pub fn find(name: &str) -> Result<&'static Scenario, String> {
+  SCENARIOS.iter().find( |s| s.name == name ).ok_or_else( || format!("Unknown scenario {}. Known scenarios: {}",
+    name, SCENARIOS.iter().map( |s| s.name ).collect::<Vec<_>>().join(", ")) )
+}
+
+impl Scenario {
+  /// Role options as a command line without the program name, or an error
+  /// if RustDDS cannot take the role.
+  pub fn command_args(&self, publisher: bool, topic: &str, domain_id: &str, options: &[String])
+    -> Result<Vec<String>, String>
+  {
+    let (role_flag, role_options) = if publisher { ("-P", self.publisher) } else { ("-S", self.subscriber) };
+    let role_options = role_options.ok_or_else( || format!("Scenario {} has no {} role in RustDDS. {}", self.name,
+      if publisher { "publisher" } else { "subscriber" }, self.description) )?;
+    let mut args: Vec<String> = [role_flag, "-t", topic, "-d", domain_id].iter().map( |a| a.to_string() ).collect();
+    args.extend(role_options.iter().map( |a| a.to_string() ));
+    args.extend(options.iter().cloned());
+    Ok(args)
+  }
+}
+
+/// Print the scenarios with the options of each role
+pub fn print_scenarios() {
+  let quoted = |a: &&str| if a.contains(' ') { format!("\"{}\"", a) } else { a.to_string() };
+  let options = |o: Option<&[&str]>|
+    o.map_or("(not available)".to_string(), |o| o.iter().map(quoted).collect::<Vec<_>>().join(" "));
+  for s in SCENARIOS {
+    println!("{}", s.name);
+    println!("  {}", s.description);
+    println!("  publisher:  {}", options(s.publisher));
+    println!("  subscriber: {}", options(s.subscriber));
+  }
+  println!("Run one with: run-scenario <name> -P|-S [-t <topic>] [-d <id>] [-- <more options>]");
+}
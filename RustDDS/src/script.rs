@@ -0,0 +1,160 @@
+/// Timed scenario scripts.
+///
+/// A script is a list of lines like
+///
+///   at 5s dispose RED
+///   at 10s set-rate 50ms
+///   at 20s exit 0
+///
+/// Times are relative to program start. Empty lines and lines starting with '#'
+/// are ignored. Actions are the same as the interactive console commands, plus
+/// `set-rate <duration>`.
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mio_extras::channel;
+
+use crate::console::Command;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptAction {
+  pub at: Duration,
+  pub command: Command,
+}
+
+/// Parses durations like "500ms", "5s", "1.5s", "2m". A bare number is seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+  let (number, scale) = if let Some(n) = s.strip_suffix("ms") {
+    (n, 0.001)
+  } else if let Some(n) = s.strip_suffix('s') {
+    (n, 1.0)
+  } else if let Some(n) = s.strip_suffix('m') {
+    (n, 60.0)
+  } else {
+    (s, 1.0)
+  };
+  match number.parse::<f64>() {
+    Ok(x) if x >= 0.0 => Ok(Duration::from_secs_f64(x * scale)),
+    _ => Err(format!("Bad duration '{}'", s)),
+  }
+}
+
+fn parse_line(line: &str, any_color: bool) -> Result<ScriptAction, String> {
+  let words: Vec<&str> = line.split_whitespace().collect();
+  match words.as_slice() {
+    ["at", time, action @ ..] if !action.is_empty() => {
+      let at = parse_duration(time)?;
+      let command = match action {
+        ["set-rate", interval] => match parse_duration(interval)? {
+          d if d > Duration::from_millis(0) => Command::Rate(d),
+          _ => return Err("Rate must be positive".to_string()),
+        },
+        other => Command::parse(&other.join(" "), any_color)?,
+      };
+      Ok(ScriptAction { at, command })
+    }
+    _ => Err("Expected 'at <time> <action>'".to_string()),
+  }
+}
+
+pub fn parse_script(text: &str, any_color: bool) -> Result<Vec<ScriptAction>, String> {
+  let mut actions = Vec::new();
+  for (i, line) in text.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue
+    }
+    actions.push(parse_line(line, any_color).map_err(|e| format!("Script line {}: {}", i + 1, e))?);
+  }
+  // Stable sort keeps the file order of actions with the same time.
+  actions.sort_by_key(|a| a.at);
+  Ok(actions)
+}
+
+pub fn load_script(path: &str, any_color: bool) -> Result<Vec<ScriptAction>, String> {
+  let text = fs::read_to_string(path).map_err(|e| format!("Cannot read script {}: {}", path, e))?;
+  parse_script(&text, any_color)
+}
+
+/// Run the script in a background thread, sending each command at its time.
+pub fn spawn_script_runner(actions: Vec<ScriptAction>, command_sender: channel::Sender<Command>) {
+  let start = Instant::now();
+  thread::Builder::new()
+    .name("script".to_string())
+    .spawn(move || {
+      for action in actions {
+        let now = Instant::now();
+        if start + action.at > now {
+          thread::sleep(start + action.at - now);
+        }
+        if command_sender.send(action.command).is_err() {
+          break // main loop is gone
+        }
+      }
+    })
+    .expect("Cannot start script thread");
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn durations() {
+    let cases = [
+      ("500ms", Some(Duration::from_millis(500))),
+      ("5s", Some(Duration::from_secs(5))),
+      ("1.5s", Some(Duration::from_millis(1500))),
+      ("2m", Some(Duration::from_secs(120))),
+      ("3", Some(Duration::from_secs(3))),
+      ("0", Some(Duration::from_secs(0))),
+      ("-1s", None),
+      ("s", None),
+      ("5h", None),
+      ("", None),
+    ];
+    for (text, expected) in &cases {
+      assert_eq!(parse_duration(text).ok(), *expected, "{}", text);
+    }
+  }
+
+  #[test]
+  fn script() {
+    let actions = parse_script("
+      # comment
+      at 10s exit 3
+      at 5s dispose red
+      at 500ms set-rate 50ms
+
+      at 5s color BLUE
+    ", false).unwrap();
+    let expected = vec![
+      (Duration::from_millis(500), Command::Rate(Duration::from_millis(50))),
+      (Duration::from_secs(5), Command::Dispose("RED".to_string())),
+      (Duration::from_secs(5), Command::Color("BLUE".to_string())),
+      (Duration::from_secs(10), Command::Exit(3)),
+    ];
+    let actions: Vec<(Duration, Command)> = actions.into_iter().map( |a| (a.at, a.command) ).collect();
+    assert_eq!(actions, expected);
+  }
+
+  #[test]
+  fn errors() {
+    let cases = [
+      ("pause", false),
+      ("at 5s", false),
+      ("at x pause", false),
+      ("at 5s jump", false),
+      ("at 5s set-rate 0ms", false),
+      ("at 5s set-rate fast", false),
+      ("at 5s color teal", false),
+      ("at 5s color teal", true),
+    ];
+    for (line, any_color) in &cases {
+      assert_eq!(parse_script(line, *any_color).is_err(), !any_color, "{}", line);
+    }
+    let e = parse_script("at 1s pause\nat 2s jump", false).unwrap_err();
+    assert!(e.starts_with("Script line 2:"), "{}", e);
+  }
+}
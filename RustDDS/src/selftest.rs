@@ -0,0 +1,60 @@
+/// One-command smoke test: a publisher and a subscriber of this same program,
+/// as child processes with the same options, on one machine.
+///
+/// The test passes if the subscriber prints samples and both children exit
+/// with status 0. This checks the stack on a new machine before any
+/// cross-vendor runs.
+use std::thread;
+use std::time::Duration;
+
+use crate::matrix::{spawn_child, SUBSCRIBER_HEAD_START};
+
+/// Runs the self test and prints a summary. Returns exit code: 0 if passed.
+pub fn run_selftest(topic: &str, domain_id: &str, seconds: f64, options: &[String]) -> i32 {
+  let mut args = vec!["-t".to_string(), topic.to_string(), "-d".to_string(), domain_id.to_string()];
+  args.extend(options.iter().cloned());
+  println!("Self test on topic {}, domain {}, for {} s, options: {}", topic, domain_id, seconds, options.join(" "));
+
+  let mut sub = match spawn_child("-S", &args) {
+    Ok(s) => s,
+    Err(e) => {
+      println!("Cannot start subscriber: {}", e);
+      return 1
+    }
+  };
+  thread::sleep(SUBSCRIBER_HEAD_START);
+  let mut publ = match spawn_child("-P", &args) {
+    Ok(p) => p,
+    Err(e) => {
+      println!("Cannot start publisher: {}", e);
+      return 1
+    }
+  };
+  thread::sleep(Duration::from_secs_f64(seconds));
+  publ.stop();
+  sub.stop();
+
+  let results = publ.finish().map_err(|e| format!("Publisher: {}", e))
+    .and_then(|p| sub.finish().map_err(|e| format!("Subscriber: {}", e)).map(|s| (p, s)));
+  let (publ, sub) = match results {
+    Ok(r) => r,
+    Err(e) => {
+      println!("Self test: ERROR {}", e);
+      return 1
+    }
+  };
+  let received = sub.sample_count(topic);
+  for line in sub.stdout.lines().filter(|l| l.starts_with("Loss: total")) {
+    println!("Subscriber: {}", line);
+  }
+  for (name, child) in [("Publisher", &publ), ("Subscriber", &sub)].iter() {
+    if let Some(failure) = child.failure() {
+      println!("{} {}", name, failure);
+    }
+  }
+  let passed = received > 0 && publ.failure().is_none() && sub.failure().is_none();
+  println!("Publisher exit status {:?}, subscriber exit status {:?}, samples received {}", publ.status, sub.status,
+    received);
+  println!("Self test: {}", if passed { "PASS" } else { "FAIL" });
+  if passed { 0 } else { 1 }
+}
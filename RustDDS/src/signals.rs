@@ -0,0 +1,42 @@
+/// Unix signals as control commands: SIGUSR1 toggles pause, SIGUSR2 prints a
+/// state snapshot. SIGTERM stops the program the same way as Ctrl-C.
+use std::io;
+
+use mio_extras::channel;
+
+use crate::console::Command;
+
+#[cfg(unix)]
+pub fn spawn_signal_handler(command_sender: channel::Sender<Command>, stop_sender: channel::Sender<()>)
+  -> io::Result<()>
+{
+  use signal_hook::{consts::{SIGTERM, SIGUSR1, SIGUSR2}, iterator::Signals};
+
+  let mut signals = Signals::new([SIGTERM, SIGUSR1, SIGUSR2])?;
+  std::thread::Builder::new()
+    .name("signals".to_string())
+    .spawn(move || {
+      for signal in signals.forever() {
+        let command = match signal {
+          SIGTERM => {
+            stop_sender.send(()).unwrap_or(()); // main loop may be gone already
+            continue
+          }
+          SIGUSR1 => Command::TogglePause,
+          SIGUSR2 => Command::Snapshot,
+          _ => continue,
+        };
+        if command_sender.send(command).is_err() {
+          break // main loop is gone
+        }
+      }
+    })?;
+  Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn spawn_signal_handler(_command_sender: channel::Sender<Command>, _stop_sender: channel::Sender<()>)
+  -> io::Result<()>
+{
+  Ok(())
+}
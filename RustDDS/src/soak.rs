@@ -0,0 +1,142 @@
+/// Soak testing: long runs with memory and CPU self-monitoring.
+///
+/// Every RECORD_INTERVAL we record the resident set size and CPU time of this
+/// process from /proc, together with sample counters. Memory growth is the
+/// least-squares slope of RSS over time. Once the records span MIN_SLOPE_SPAN,
+/// a slope above the limit fails the run, so that leaks are caught without
+/// waiting for the whole soak to end.
+use std::fs;
+use std::time::{Duration, Instant};
+
+pub const RECORD_INTERVAL: Duration = Duration::from_secs(60);
+const MIN_SLOPE_SPAN: Duration = Duration::from_secs(600);
+/// Kernel clock ticks per second, for CPU times in /proc/self/stat
+const CLOCK_TICKS: f64 = 100.0;
+
+pub enum SoakStatus {
+  Running,
+  Passed(String),
+  Failed(String),
+}
+
+pub struct Soak {
+  started: Instant,
+  /// None = run until stopped
+  end: Option<Instant>,
+  max_growth_kb_per_hour: f64,
+  next_record: Instant,
+  /// (hours since start, RSS in kB)
+  rss: Vec<(f64, f64)>,
+  previous_cpu: Option<(Instant, f64)>,
+}
+
+impl Soak {
+  /// Zero hours runs until stopped.
+  pub fn new(hours: f64, max_growth_kb_per_hour: f64) -> Soak {
+    let now = Instant::now();
+    Soak {
+      started: now,
+      end: if hours > 0.0 { Some(now + Duration::from_secs_f64(hours * 3600.0)) } else { None },
+      max_growth_kb_per_hour,
+      next_record: now,
+      rss: Vec::new(),
+      previous_cpu: None,
+    }
+  }
+
+  pub fn describe(&self) -> String {
+    let duration = match self.end {
+      Some(end) => format!("for {:.2} h", (end - self.started).as_secs_f64() / 3600.0),
+      None => "until stopped".to_string(),
+    };
+    format!("Soak test {}, failing if RSS grows more than {} kB/h", duration, self.max_growth_kb_per_hour)
+  }
+
+  pub fn next_due(&self) -> Instant {
+    self.end.map_or(self.next_record, |end| end.min(self.next_record))
+  }
+
+  /// Records memory, CPU and the given counters, if a record is due.
+  pub fn poll(&mut self, now: Instant, counters: &str) -> SoakStatus {
+    if now >= self.next_record {
+      self.next_record += RECORD_INTERVAL;
+      self.record(now, counters);
+      if let Some(slope) = self.growth() {
+        if (self.rss.last().unwrap().0 - self.rss[0].0) * 3600.0 >= MIN_SLOPE_SPAN.as_secs_f64()
+          && slope > self.max_growth_kb_per_hour
+        {
+          return SoakStatus::Failed(self.verdict(slope))
+        }
+      }
+    }
+    match self.end {
+      Some(end) if now >= end => match self.growth() {
+        Some(slope) if slope > self.max_growth_kb_per_hour => SoakStatus::Failed(self.verdict(slope)),
+        Some(slope) => SoakStatus::Passed(self.verdict(slope)),
+        None => SoakStatus::Passed("Soak test done. Too few records for RSS growth.".to_string()),
+      },
+      _ => SoakStatus::Running,
+    }
+  }
+
+  fn record(&mut self, now: Instant, counters: &str) {
+    let hours = (now - self.started).as_secs_f64() / 3600.0;
+    let rss = resident_kb();
+    let cpu = cpu_seconds().and_then(|cpu| {
+      let usage = self.previous_cpu
+        .map(|(t, previous)| 100.0 * (cpu - previous) / (now - t).as_secs_f64().max(f64::EPSILON));
+      self.previous_cpu = Some((now, cpu));
+      usage
+    });
+    if let Some(rss) = rss {
+      self.rss.push((hours, rss as f64));
+    }
+    println!(
+      "Soak {:.3} h: RSS {} kB, CPU {}, {}",
+      hours,
+      rss.map_or("unknown".to_string(), |r| r.to_string()),
+      cpu.map_or("-".to_string(), |c| format!("{:.1}%", c)),
+      counters
+    );
+  }
+
+  /// Least-squares slope of RSS in kB/h
+  fn growth(&self) -> Option<f64> {
+    if self.rss.len() < 2 {
+      return None
+    }
+    let n = self.rss.len() as f64;
+    let mean_t = self.rss.iter().map(|(t, _)| t).sum::<f64>() / n;
+    let mean_m = self.rss.iter().map(|(_, m)| m).sum::<f64>() / n;
+    let covariance: f64 = self.rss.iter().map(|(t, m)| (t - mean_t) * (m - mean_m)).sum();
+    let variance: f64 = self.rss.iter().map(|(t, _)| (t - mean_t) * (t - mean_t)).sum();
+    if variance > 0.0 { Some(covariance / variance) } else { None }
+  }
+
+  fn verdict(&self, slope: f64) -> String {
+    format!(
+      "Soak test {}: RSS grew {:.1} kB/h over {} records, limit {} kB/h",
+      if slope > self.max_growth_kb_per_hour { "FAILED" } else { "passed" },
+      slope,
+      self.rss.len(),
+      self.max_growth_kb_per_hour
+    )
+  }
+}
+
+/// VmRSS from /proc/self/status
+fn resident_kb() -> Option<u64> {
+  let status = fs::read_to_string("/proc/self/status").ok()?;
+  let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+  line.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// User + system CPU time from /proc/self/stat
+fn cpu_seconds() -> Option<f64> {
+  let stat = fs::read_to_string("/proc/self/stat").ok()?;
+  // Fields after the command name, which is in parentheses and may contain spaces
+  let fields: Vec<&str> = stat.get(stat.rfind(')')? + 2..)?.split_whitespace().collect();
+  let utime: f64 = fields.get(11)?.parse().ok()?;
+  let stime: f64 = fields.get(12)?.parse().ok()?;
+  Some((utime + stime) / CLOCK_TICKS)
+}
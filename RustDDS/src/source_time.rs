@@ -0,0 +1,52 @@
+/// Artificial source timestamps for written samples.
+///
+/// Samples are written with source timestamp = now + offset, where the offset
+/// may be negative. With mode "alternate" only every other sample is shifted,
+/// so e.g. a negative offset makes every second sample older than the one
+/// before it. A remote reader with BY_SOURCE_TIMESTAMP destination order
+/// should then drop those, and a reader with Lifespan shorter than the offset
+/// should expire them.
+use rustdds::dds::data_types::{DDSDuration, Timestamp};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimestampMode {
+  Fixed,
+  Alternate,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SourceTimestamps {
+  offset: f64,
+  mode: TimestampMode,
+  written: u64,
+}
+
+impl SourceTimestamps {
+  pub fn new(offset: f64, mode: &str) -> Result<SourceTimestamps, String> {
+    let mode = match mode {
+      "fixed" => TimestampMode::Fixed,
+      "alternate" => TimestampMode::Alternate,
+      other => return Err(format!("Unknown source timestamp mode \"{}\". Expected fixed or alternate", other)),
+    };
+    Ok(SourceTimestamps { offset, mode, written: 0 })
+  }
+
+  pub fn describe(&self) -> String {
+    match self.mode {
+      TimestampMode::Fixed => format!("Source timestamps shifted by {} s", self.offset),
+      TimestampMode::Alternate => format!("Source timestamps of every other sample shifted by {} s", self.offset),
+    }
+  }
+
+  /// Source timestamp for the next write.
+  pub fn next_timestamp(&mut self) -> Option<Timestamp> {
+    let shifted = self.mode == TimestampMode::Fixed || self.written % 2 == 1;
+    self.written += 1;
+    let now = Timestamp::now();
+    Some(match (shifted, self.offset < 0.0) {
+      (false, _) => now,
+      (true, false) => now + DDSDuration::from_frac_seconds(self.offset),
+      (true, true) => now - DDSDuration::from_frac_seconds(-self.offset),
+    })
+  }
+}
@@ -0,0 +1,442 @@
+/// Watching participant discovery (SPDP) traffic of the domain.
+///
+/// RustDDS does not tell applications what remote participants and endpoints
+/// have advertised, nor which locator it sends to. We can still listen to the
+/// SPDP multicast announcements ourselves, like capture does, and record the
+/// locators each remote participant advertises. Endpoint discovery (SEDP) goes
+/// over unicast, so endpoint-specific locators are not visible. Endpoints
+/// without their own locators use the participant default locators.
+///
+/// The same announcements tell the vendor and RTPS protocol version of each
+/// participant, which is the provenance interop reports need, and which
+/// parameters it sends that RustDDS ignores: vendor-specific ones and the
+/// standard ones in UNSUPPORTED_PARAMETERS.
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryInto;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use log::error;
+use serde_json::json;
+
+use crate::capture::{multicast_socket, DEFAULT_MULTICAST_GROUP, DOMAIN_ID_GAIN, OFFSET_D0, PORT_BASE};
+use crate::timeline;
+
+const SUBMESSAGE_DATA: u8 = 0x15;
+const SPDP_WRITER_ID: [u8; 4] = [0x00, 0x01, 0x00, 0xc2];
+
+const PID_SENTINEL: u16 = 0x0001;
+const PID_PROTOCOL_VERSION: u16 = 0x0015;
+const PID_VENDOR_ID: u16 = 0x0016;
+const PID_DEFAULT_UNICAST_LOCATOR: u16 = 0x0031;
+const PID_METATRAFFIC_UNICAST_LOCATOR: u16 = 0x0032;
+const PID_METATRAFFIC_MULTICAST_LOCATOR: u16 = 0x0033;
+const PID_DEFAULT_MULTICAST_LOCATOR: u16 = 0x0048;
+
+/// Standard parameters of participant data that RustDDS does not implement
+const UNSUPPORTED_PARAMETERS: &[(u16, &str)] = &[
+  (0x002c, "USER_DATA"),
+  (0x0059, "PROPERTY_LIST"),
+  (0x1001, "IDENTITY_TOKEN"),
+  (0x1002, "PERMISSIONS_TOKEN"),
+  (0x1005, "PARTICIPANT_SECURITY_INFO"),
+  (0x4014, "DOMAIN_TAG"),
+];
+const PID_VENDOR_SPECIFIC: u16 = 0x8000;
+const PID_MUST_UNDERSTAND: u16 = 0x4000;
+
+const LOCATOR_KIND_UDPV4: i32 = 1;
+const LOCATOR_KIND_UDPV6: i32 = 2;
+
+/// Vendor ids assigned by the OMG
+const VENDORS: &[([u8; 2], &str)] = &[
+  ([0x01, 0x01], "RTI Connext DDS"),
+  ([0x01, 0x02], "ADLINK OpenSplice DDS"),
+  ([0x01, 0x03], "OCI OpenDDS"),
+  ([0x01, 0x04], "MilSoft"),
+  ([0x01, 0x05], "Kongsberg InterCOM DDS"),
+  ([0x01, 0x06], "TwinOaks CoreDX DDS"),
+  ([0x01, 0x07], "Lakota Technical Solutions"),
+  ([0x01, 0x08], "ICOUP Consulting"),
+  ([0x01, 0x09], "ETRI Diamond DDS"),
+  ([0x01, 0x0a], "RTI Connext DDS Micro"),
+  ([0x01, 0x0b], "ADLINK Vortex Cafe"),
+  ([0x01, 0x0c], "PrismTech"),
+  ([0x01, 0x0d], "ADLINK Vortex Lite"),
+  ([0x01, 0x0e], "Technicolor Qeo"),
+  ([0x01, 0x0f], "eProsima Fast DDS"),
+  ([0x01, 0x10], "Eclipse Cyclone DDS"),
+  ([0x01, 0x11], "GurumNetworks GurumDDS"),
+  ([0x01, 0x12], "Atostek RustDDS"),
+];
+
+pub fn vendor_name(vendor_id: [u8; 2]) -> &'static str {
+  VENDORS.iter()
+    .find(|(id, _)| *id == vendor_id)
+    .map_or("unknown vendor", |(_, name)| name)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParticipantInfo {
+  pub vendor_id: [u8; 2],
+  /// (major, minor)
+  pub protocol_version: (u8, u8),
+  pub default_unicast: Vec<String>,
+  pub default_multicast: Vec<String>,
+  pub metatraffic_unicast: Vec<String>,
+  pub metatraffic_multicast: Vec<String>,
+  /// Parameter ids RustDDS ignores, without the must-understand bit
+  pub unsupported_parameters: BTreeSet<u16>,
+}
+
+/// Name of a parameter in UNSUPPORTED_PARAMETERS, or vendor-specific
+pub fn parameter_name(pid: u16) -> String {
+  if pid & PID_VENDOR_SPECIFIC != 0 {
+    return format!("vendor-specific 0x{:04x}", pid)
+  }
+  UNSUPPORTED_PARAMETERS.iter()
+    .find(|(id, _)| *id == pid)
+    .map_or_else(|| format!("0x{:04x}", pid), |(_, name)| format!("{} (0x{:04x})", name, pid))
+}
+
+/// Remote participants seen on SPDP, by GUID prefix in hex.
+#[derive(Clone, Default)]
+pub struct DiscoveredParticipants {
+  participants: Arc<Mutex<BTreeMap<String, ParticipantInfo>>>,
+}
+
+impl DiscoveredParticipants {
+  pub fn snapshot(&self) -> BTreeMap<String, ParticipantInfo> {
+    self.participants.lock().unwrap().clone()
+  }
+
+  /// Lines describing the locators of each known participant.
+  pub fn locator_report(&self) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (guid_prefix, p) in self.snapshot() {
+      lines.push(format!("Participant {}:", guid_prefix));
+      lines.push(format!("  default unicast       {}", p.default_unicast.join(" ")));
+      lines.push(format!("  default multicast     {}", p.default_multicast.join(" ")));
+      lines.push(format!("  metatraffic unicast   {}", p.metatraffic_unicast.join(" ")));
+      lines.push(format!("  metatraffic multicast {}", p.metatraffic_multicast.join(" ")));
+    }
+    if lines.is_empty() {
+      lines.push("No remote participants seen on SPDP multicast.".to_string());
+    }
+    lines.push("Locator selected by RustDDS is not visible to applications.".to_string());
+    lines
+  }
+
+  /// Summary of the vendors of remote participants seen, excluding our own
+  /// participant.
+  pub fn vendor_report(&self, own_guid_prefix: &str) -> String {
+    // vendor -> protocol version of each participant
+    let mut vendors: BTreeMap<[u8; 2], Vec<(u8, u8)>> = BTreeMap::new();
+    for (_, p) in self.snapshot().into_iter().filter(|(prefix, _)| prefix != own_guid_prefix) {
+      vendors.entry(p.vendor_id).or_default().push(p.protocol_version);
+    }
+    if vendors.is_empty() {
+      return "Vendors tested against: none seen on SPDP multicast.".to_string()
+    }
+    let list: Vec<String> = vendors.iter().map(|(id, participants)| {
+      let versions: BTreeSet<String> =
+        participants.iter().map(|(major, minor)| format!("{}.{}", major, minor)).collect();
+      format!("{} ({}) with {} participants, RTPS {}", vendor_name(*id), format_vendor_id(*id), participants.len(),
+        versions.into_iter().collect::<Vec<_>>().join(" "))
+    }).collect();
+    format!("Vendors tested against: {}", list.join(", "))
+  }
+
+  /// Which unsupported parameters remote participants sent, by vendor.
+  /// Endpoint QoS goes over unicast SEDP, so it is not covered.
+  pub fn unsupported_report(&self, own_guid_prefix: &str) -> Vec<String> {
+    let mut vendors: BTreeMap<[u8; 2], BTreeSet<u16>> = BTreeMap::new();
+    for (_, p) in self.snapshot().into_iter().filter(|(prefix, _)| prefix != own_guid_prefix) {
+      vendors.entry(p.vendor_id).or_default().extend(p.unsupported_parameters);
+    }
+    if vendors.values().all(BTreeSet::is_empty) {
+      return vec!["Unsupported parameters: none in participant data seen on SPDP multicast.".to_string()]
+    }
+    vendors.iter().filter(|(_, pids)| !pids.is_empty()).map(|(id, pids)| {
+      let names: Vec<String> = pids.iter().map(|pid| parameter_name(*pid)).collect();
+      format!("Unsupported parameters from {}: {}", vendor_name(*id), names.join(", "))
+    }).collect()
+  }
+}
+
+fn format_vendor_id(id: [u8; 2]) -> String {
+  format!("{:02x}{:02x}", id[0], id[1])
+}
+
+fn describe_unsupported(guid_prefix: &str, p: &ParticipantInfo) -> Vec<String> {
+  p.unsupported_parameters.iter().map(|pid| {
+    format!("Participant {} ({}) sent parameter {}: ignored, not implemented in RustDDS", guid_prefix,
+      vendor_name(p.vendor_id), parameter_name(*pid))
+  }).collect()
+}
+
+fn describe_participant(guid_prefix: &str, p: &ParticipantInfo) -> String {
+  format!("Discovered participant {}: vendor {} ({}), RTPS {}.{}", guid_prefix, vendor_name(p.vendor_id),
+    format_vendor_id(p.vendor_id), p.protocol_version.0, p.protocol_version.1)
+}
+
+/// Start listening to SPDP multicast of the domain in a background thread.
+/// With announce, newly seen participants are printed with vendor and protocol version.
+/// With announce_unsupported, the parameters they sent that RustDDS ignores are printed.
+pub fn start_spdp_watch(domain_id: u16, announce: bool, announce_unsupported: bool)
+  -> std::io::Result<DiscoveredParticipants>
+{
+  let discovered = DiscoveredParticipants::default();
+  let port = PORT_BASE + DOMAIN_ID_GAIN * domain_id + OFFSET_D0;
+  let socket = multicast_socket(SocketAddrV4::new(DEFAULT_MULTICAST_GROUP, port))?;
+  let d = discovered.clone();
+  thread::Builder::new()
+    .name("spdp-watch".to_string())
+    .spawn(move || {
+      let mut buf = vec![0u8; 65536];
+      loop {
+        match socket.recv_from(&mut buf) {
+          Ok((len, _src)) => {
+            if let Some((guid_prefix, info)) = parse_spdp_message(&buf[..len]) {
+              if !d.participants.lock().unwrap().contains_key(&guid_prefix) {
+                timeline::record("participant_discovered", None, json!({
+                  "guid_prefix": guid_prefix,
+                  "vendor": vendor_name(info.vendor_id),
+                }));
+                if announce {
+                  println!("{}", describe_participant(&guid_prefix, &info));
+                }
+                if announce_unsupported {
+                  for line in describe_unsupported(&guid_prefix, &info) {
+                    println!("{}", line);
+                  }
+                }
+              }
+              d.participants.lock().unwrap().insert(guid_prefix, info);
+            }
+          }
+          Err(e) => {
+            error!("SPDP watch socket receive failed, stopping: {:?}", e);
+            return
+          }
+        }
+      }
+    })?;
+  Ok(discovered)
+}
+
+fn read_u16(b: &[u8], little_endian: bool) -> Option<u16> {
+  let b: [u8; 2] = b.get(..2)?.try_into().ok()?;
+  Some(if little_endian { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) })
+}
+
+fn read_u32(b: &[u8], little_endian: bool) -> Option<u32> {
+  let b: [u8; 4] = b.get(..4)?.try_into().ok()?;
+  Some(if little_endian { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+}
+
+fn format_locator(value: &[u8], little_endian: bool) -> Option<String> {
+  let kind = read_u32(value, little_endian)? as i32;
+  let port = read_u32(value.get(4..)?, little_endian)?;
+  let address: [u8; 16] = value.get(8..24)?.try_into().ok()?;
+  Some(match kind {
+    LOCATOR_KIND_UDPV4 => format!("udp4://{}:{}", Ipv4Addr::new(address[12], address[13], address[14], address[15]),
+      port),
+    LOCATOR_KIND_UDPV6 => format!("udp6://[{}]:{}", Ipv6Addr::from(address), port),
+    other => format!("kind{}:{}", other, port),
+  })
+}
+
+/// Returns GUID prefix and participant info, if the message contains SPDP participant data.
+pub fn parse_spdp_message(msg: &[u8]) -> Option<(String, ParticipantInfo)> {
+  if !msg.starts_with(b"RTPS") || msg.len() < 20 {
+    return None
+  }
+  let guid_prefix: String = msg[8..20].iter().map(|b| format!("{:02x}", b)).collect();
+  // Header protocol version and vendor id, unless participant data says otherwise
+  let header = ((msg[4], msg[5]), [msg[6], msg[7]]);
+  let mut pos = 20;
+  while pos + 4 <= msg.len() {
+    let id = msg[pos];
+    let flags = msg[pos + 1];
+    let little_endian = flags & 0x01 != 0;
+    let length = read_u16(&msg[pos + 2..], little_endian)? as usize;
+    let body_start = pos + 4;
+    let body_end = if length == 0 { msg.len() } else { (body_start + length).min(msg.len()) };
+    let body = &msg[body_start..body_end];
+    if id == SUBMESSAGE_DATA && body.get(8..12) == Some(&SPDP_WRITER_ID[..]) {
+      return parse_participant_data(body, flags, header).map(|info| (guid_prefix, info))
+    }
+    pos = body_end;
+  }
+  None
+}
+
+fn parse_participant_data(body: &[u8], flags: u8, header: ((u8, u8), [u8; 2])) -> Option<ParticipantInfo> {
+  let little_endian = flags & 0x01 != 0;
+  let inline_qos = flags & 0x02 != 0;
+  let has_data = flags & 0x04 != 0;
+  if !has_data {
+    return None // unregister or dispose
+  }
+  let octets_to_inline_qos = read_u16(body.get(2..)?, little_endian)? as usize;
+  let mut pos = 4 + octets_to_inline_qos;
+  if inline_qos {
+    pos += parameter_list_length(body.get(pos..)?, little_endian)?;
+  }
+  let payload = body.get(pos..)?;
+  let pl_little_endian = match payload.get(..2)? {
+    [0x00, 0x03] => true,  // PL_CDR_LE
+    [0x00, 0x02] => false, // PL_CDR_BE
+    _ => return None,
+  };
+  let mut info = ParticipantInfo { protocol_version: header.0, vendor_id: header.1, ..ParticipantInfo::default() };
+  let mut params = payload.get(4..)?;
+  loop {
+    let pid = read_u16(params, pl_little_endian)?;
+    let length = read_u16(params.get(2..)?, pl_little_endian)? as usize;
+    if pid == PID_SENTINEL {
+      break
+    }
+    // Vendor-specific parameters (high bit set) are ignored. The next bit is "must understand".
+    let pid = pid & !PID_MUST_UNDERSTAND;
+    if pid & PID_VENDOR_SPECIFIC != 0 || UNSUPPORTED_PARAMETERS.iter().any(|(id, _)| *id == pid) {
+      info.unsupported_parameters.insert(pid);
+    }
+    let pid = if pid & PID_VENDOR_SPECIFIC != 0 { 0 } else { pid };
+    let value = params.get(4..4 + length)?;
+    let locator = || format_locator(value, pl_little_endian);
+    match pid {
+      PID_PROTOCOL_VERSION if length >= 2 => info.protocol_version = (value[0], value[1]),
+      PID_VENDOR_ID if length >= 2 => info.vendor_id = [value[0], value[1]],
+      PID_DEFAULT_UNICAST_LOCATOR => info.default_unicast.extend(locator()),
+      PID_DEFAULT_MULTICAST_LOCATOR => info.default_multicast.extend(locator()),
+      PID_METATRAFFIC_UNICAST_LOCATOR => info.metatraffic_unicast.extend(locator()),
+      PID_METATRAFFIC_MULTICAST_LOCATOR => info.metatraffic_multicast.extend(locator()),
+      _ => (),
+    }
+    params = params.get(4 + length..)?;
+  }
+  Some(info)
+}
+
+/// Length in bytes of a parameter list, including the sentinel.
+fn parameter_list_length(params: &[u8], little_endian: bool) -> Option<usize> {
+  let mut pos = 0;
+  loop {
+    let pid = read_u16(params.get(pos..)?, little_endian)?;
+    let length = read_u16(params.get(pos + 2..)?, little_endian)? as usize;
+    pos += 4 + length;
+    if pid == PID_SENTINEL {
+      return Some(pos)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parameter(pid: u16, value: &[u8]) -> Vec<u8> {
+    let mut p = pid.to_le_bytes().to_vec();
+    p.extend((value.len() as u16).to_le_bytes());
+    p.extend(value);
+    p
+  }
+
+  fn udpv4_locator(port: u32, address: [u8; 4]) -> Vec<u8> {
+    let mut l = LOCATOR_KIND_UDPV4.to_le_bytes().to_vec();
+    l.extend(port.to_le_bytes());
+    l.extend([0; 12]);
+    l.extend(address);
+    l
+  }
+
+  /// RTPS message from vendor 01.12 with one little-endian DATA submessage
+  fn message(flags: u8, writer_id: [u8; 4], parameters: &[Vec<u8>]) -> Vec<u8> {
+    let mut body = vec![0, 0, 16, 0, 0, 0, 0, 0];
+    body.extend(writer_id);
+    body.extend([0, 0, 0, 0, 1, 0, 0, 0]);
+    body.extend([0x00, 0x03, 0, 0]);
+    for p in parameters {
+      body.extend(p);
+    }
+    body.extend(parameter(PID_SENTINEL, &[]));
+    let mut msg = b"RTPS".to_vec();
+    msg.extend([2, 3, 0x01, 0x12]);
+    msg.extend(1..=12);
+    msg.extend([SUBMESSAGE_DATA, flags]);
+    msg.extend((body.len() as u16).to_le_bytes());
+    msg.extend(body);
+    msg
+  }
+
+  fn participant_data() -> Vec<u8> {
+    message(0x05, SPDP_WRITER_ID, &[
+      parameter(PID_VENDOR_ID, &[0x01, 0x10, 0, 0]),
+      parameter(PID_PROTOCOL_VERSION, &[2, 4, 0, 0]),
+      parameter(PID_DEFAULT_UNICAST_LOCATOR, &udpv4_locator(7410, [192, 168, 1, 2])),
+      parameter(PID_METATRAFFIC_MULTICAST_LOCATOR, &udpv4_locator(7400, [239, 255, 0, 1])),
+      parameter(0x002c | PID_MUST_UNDERSTAND, &[1, 2, 3, 4]),
+      parameter(0x8001, &[0; 4]),
+    ])
+  }
+
+  #[test]
+  fn participant() {
+    let (guid_prefix, info) = parse_spdp_message(&participant_data()).unwrap();
+    assert_eq!(guid_prefix, "0102030405060708090a0b0c");
+    assert_eq!(info.vendor_id, [0x01, 0x10]);
+    assert_eq!(vendor_name(info.vendor_id), "Eclipse Cyclone DDS");
+    assert_eq!(info.protocol_version, (2, 4));
+    assert_eq!(info.default_unicast, vec!["udp4://192.168.1.2:7410"]);
+    assert_eq!(info.metatraffic_multicast, vec!["udp4://239.255.0.1:7400"]);
+    assert!(info.default_multicast.is_empty() && info.metatraffic_unicast.is_empty());
+    assert_eq!(info.unsupported_parameters.into_iter().collect::<Vec<_>>(), vec![0x002c, 0x8001]);
+  }
+
+  #[test]
+  fn header_values_by_default() {
+    let (_, info) = parse_spdp_message(&message(0x05, SPDP_WRITER_ID, &[])).unwrap();
+    assert_eq!((info.vendor_id, info.protocol_version), ([0x01, 0x12], (2, 3)));
+    assert_eq!(vendor_name([0x7f, 0x7f]), "unknown vendor");
+  }
+
+  #[test]
+  fn not_participant_data() {
+    let mut not_rtps = participant_data();
+    not_rtps[0] = b'X';
+    let cases = [
+      not_rtps,
+      message(0x01, SPDP_WRITER_ID, &[]), // no data: dispose or unregister
+      message(0x05, [0x00, 0x00, 0x03, 0xc2], &[]), // SEDP publications writer
+      b"RTPS".to_vec(),
+    ];
+    for msg in &cases {
+      assert!(parse_spdp_message(msg).is_none(), "{:?}", msg);
+    }
+  }
+
+  #[test]
+  fn truncated_messages() {
+    let msg = participant_data();
+    // The sentinel is the last 4 bytes, so any shorter message lacks it.
+    for len in 0..msg.len() - 4 {
+      assert!(parse_spdp_message(&msg[..len]).is_none(), "length {}", len);
+    }
+  }
+
+  #[test]
+  fn parameter_names() {
+    let cases = [
+      (0x002c, "USER_DATA (0x002c)"),
+      (0x4014, "DOMAIN_TAG (0x4014)"),
+      (0x8001, "vendor-specific 0x8001"),
+      (0x0077, "0x0077"),
+    ];
+    for (pid, name) in &cases {
+      assert_eq!(parameter_name(*pid), *name);
+    }
+  }
+}
@@ -0,0 +1,229 @@
+/// Running state of publisher and subscriber, for snapshots.
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use rustdds::dds::statusevents::{DataReaderStatus, DataWriterStatus};
+use serde::Serialize;
+
+use crate::Shape;
+
+/// Status event counters common to both roles.
+#[derive(Serialize, Default)]
+pub struct StatusCounts {
+  pub deadline_missed: i32,
+  pub incompatible_qos: i32,
+  pub liveliness_lost: i32,
+  pub liveliness_changed: i32,
+  pub samples_lost: i32,
+  pub samples_rejected: i32,
+}
+
+#[derive(Serialize)]
+pub struct PublisherState {
+  pub topic: String,
+  pub color: String,
+  pub paused: bool,
+  pub matched_readers: i32,
+  pub samples_written: u64,
+  pub samples_dropped: u64,
+  pub writes_failed: u64,
+  pub disposed: Vec<String>,
+  pub status_counts: StatusCounts,
+}
+
+impl PublisherState {
+  pub fn new(topic: &str, color: &str) -> PublisherState {
+    PublisherState {
+      topic: topic.to_string(),
+      color: color.to_string(),
+      paused: false,
+      matched_readers: 0,
+      samples_written: 0,
+      samples_dropped: 0,
+      writes_failed: 0,
+      disposed: Vec::new(),
+      status_counts: StatusCounts::default(),
+    }
+  }
+
+  pub fn update_status(&mut self, status: &DataWriterStatus) {
+    match status {
+      DataWriterStatus::LivelinessLost { count } => self.status_counts.liveliness_lost = count.count(),
+      DataWriterStatus::OfferedDeadlineMissed { count } => self.status_counts.deadline_missed = count.count(),
+      DataWriterStatus::OfferedIncompatibleQos { count, .. } => self.status_counts.incompatible_qos = count.count(),
+      DataWriterStatus::PublicationMatched { current, .. } => self.matched_readers = current.count(),
+    }
+  }
+
+  pub fn text_snapshot(&self) -> String {
+    let mut s = format!(
+      "--- Publisher snapshot: topic {} ---\n\
+       color {} {}, samples written {}, matched readers {}\n",
+      self.topic,
+      self.color,
+      if self.paused { "(paused)" } else { "(publishing)" },
+      self.samples_written,
+      self.matched_readers,
+    );
+    if self.samples_dropped > 0 {
+      s += &format!("samples dropped by impairment {}\n", self.samples_dropped);
+    }
+    if self.writes_failed > 0 {
+      s += &format!("writes failed {}\n", self.writes_failed);
+    }
+    if !self.disposed.is_empty() {
+      s += &format!("disposed: {}\n", self.disposed.join(" "));
+    }
+    s += &self.status_counts.text();
+    s
+  }
+}
+
+#[derive(Serialize)]
+pub struct InstanceInfo {
+  pub alive: bool,
+  pub samples: u64,
+  pub last: Option<Shape>,
+}
+
+/// Reception of one instance from one writer, for the table at exit.
+pub struct WriterInstance {
+  pub samples: u64,
+  /// First and last sample counter, with --counter
+  pub counters: Option<(u32, u32)>,
+  pub disposals: u64,
+  first_time: Instant,
+  last_time: Instant,
+}
+
+impl WriterInstance {
+  /// Average samples per second between first and last sample
+  pub fn rate(&self) -> f64 {
+    let span = (self.last_time - self.first_time).as_secs_f64();
+    if span > 0.0 { (self.samples - 1) as f64 / span } else { 0.0 }
+  }
+}
+
+#[derive(Serialize)]
+pub struct SubscriberState {
+  pub topic: String,
+  pub matched_writers: i32,
+  pub samples_received: u64,
+  pub instances: BTreeMap<String, InstanceInfo>,
+  pub status_counts: StatusCounts,
+  /// (color, writer GUID) -> reception
+  #[serde(skip)]
+  pub writer_instances: BTreeMap<(String, String), WriterInstance>,
+}
+
+impl SubscriberState {
+  pub fn new(topic: &str) -> SubscriberState {
+    SubscriberState {
+      topic: topic.to_string(),
+      matched_writers: 0,
+      samples_received: 0,
+      instances: BTreeMap::new(),
+      status_counts: StatusCounts::default(),
+      writer_instances: BTreeMap::new(),
+    }
+  }
+
+  pub fn record_sample(&mut self, shape: &Shape, writer: &str, counter: Option<u32>) {
+    let now = Instant::now();
+    let w = self.writer_instances.entry((shape.color.clone(), writer.to_string())).or_insert(WriterInstance {
+      samples: 0,
+      counters: None,
+      disposals: 0,
+      first_time: now,
+      last_time: now,
+    });
+    w.samples += 1;
+    w.last_time = now;
+    if let Some(c) = counter {
+      w.counters = Some(w.counters.map_or((c, c), |(first, _)| (first, c)));
+    }
+    self.samples_received += 1;
+    let instance = self.instances.entry(shape.color.clone()).or_insert(InstanceInfo {
+      alive: true,
+      samples: 0,
+      last: None,
+    });
+    instance.alive = true;
+    instance.samples += 1;
+    instance.last = Some(shape.clone());
+  }
+
+  pub fn record_not_alive(&mut self, key: &str, writer: &str) {
+    if let Some(instance) = self.instances.get_mut(key) {
+      instance.alive = false;
+    }
+    if let Some(w) = self.writer_instances.get_mut(&(key.to_string(), writer.to_string())) {
+      w.disposals += 1;
+    }
+  }
+
+  /// Table of instances by writer. Deadline misses are counted per reader, as
+  /// RustDDS does not tell the instance.
+  pub fn instance_table(&self) -> String {
+    let mut s = format!("--- Instances on {} ---\n{:10} {:33} {:>8} {:>8} {:>8} {:>9} {:>8}\n",
+      self.topic, "color", "writer", "samples", "first", "last", "disposals", "rate/s");
+    for ((color, writer), w) in &self.writer_instances {
+      let (first, last) = w.counters
+        .map_or(("-".to_string(), "-".to_string()), |(f, l)| (f.to_string(), l.to_string()));
+      s += &format!("{:10.10} {:33} {:>8} {:>8} {:>8} {:>9} {:>8.1}\n",
+        color, writer, w.samples, first, last, w.disposals, w.rate());
+    }
+    s += &format!("deadline missed {}", self.status_counts.deadline_missed);
+    s
+  }
+
+  pub fn update_status(&mut self, status: &DataReaderStatus) {
+    match status {
+      DataReaderStatus::SampleRejected { count, .. } => self.status_counts.samples_rejected = count.count(),
+      DataReaderStatus::LivelinessChanged { not_alive_total, .. } =>
+        self.status_counts.liveliness_changed = not_alive_total.count(),
+      DataReaderStatus::RequestedDeadlineMissed { count } => self.status_counts.deadline_missed = count.count(),
+      DataReaderStatus::RequestedIncompatibleQos { count, .. } =>
+        self.status_counts.incompatible_qos = count.count(),
+      DataReaderStatus::SampleLost { count } => self.status_counts.samples_lost = count.count(),
+      DataReaderStatus::SubscriptionMatched { current, .. } => self.matched_writers = current.count(),
+    }
+  }
+
+  pub fn text_snapshot(&self) -> String {
+    let mut s = format!(
+      "--- Subscriber snapshot: topic {} ---\n\
+       samples received {}, matched writers {}\n",
+      self.topic, self.samples_received, self.matched_writers,
+    );
+    for (color, i) in &self.instances {
+      s += &format!(
+        "instance {:10.10} {:9} samples {}",
+        color,
+        if i.alive { "alive" } else { "not alive" },
+        i.samples
+      );
+      if let Some(last) = &i.last {
+        s += &format!(" last at {} {}", last.x, last.y);
+      }
+      s += "\n";
+    }
+    s += &self.status_counts.text();
+    s
+  }
+}
+
+impl StatusCounts {
+  fn text(&self) -> String {
+    format!(
+      "deadline missed {}, incompatible QoS {}, liveliness lost {}, liveliness changed {}, \
+       samples lost {}, samples rejected {}",
+      self.deadline_missed,
+      self.incompatible_qos,
+      self.liveliness_lost,
+      self.liveliness_changed,
+      self.samples_lost,
+      self.samples_rejected
+    )
+  }
+}
@@ -0,0 +1,324 @@
+/// Reporting of DataWriter and DataReader status events, and of console
+/// commands a mode does not handle.
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rustdds::dds::qos::QosPolicyId;
+use rustdds::dds::statusevents::{CountWithChange, DataReaderStatus, DataWriterStatus, QosPolicyCount};
+use rustdds::structure::guid::GUID;
+use serde_json::{json, Value};
+
+use crate::console::Command;
+use crate::{timeline, Shape};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LogFormat {
+  Text,
+  Json,
+  /// Lines as printed by the reference shape_main applications of the OMG
+  /// interop suite, so that its verification scripts can grade us.
+  Compat,
+}
+
+impl LogFormat {
+  pub fn from_arg(arg: Option<&str>) -> LogFormat {
+    match arg {
+      Some("json") => LogFormat::Json,
+      _ => LogFormat::Text,
+    }
+  }
+}
+
+/// Type name in Compat event lines. The status reports do not carry the type,
+/// and the shape applications use only this one.
+const COMPAT_TYPE_NAME: &str = "ShapeType";
+
+/// Sample line: topic, color, x, y and size.
+pub fn sample_line(format: LogFormat, topic_name: &str, shape: &Shape) -> String {
+  match format {
+    LogFormat::Compat => format!("{:<10} {:<10} {:03} {:03} [{}]", topic_name, shape.color, shape.x, shape.y,
+      shape.shapesize),
+    _ => format!("{:10.10} {:10.10} {:3.3} {:3.3} [{}]", topic_name, shape.color, shape.x, shape.y,
+      shape.shapesize),
+  }
+}
+
+/// Event line in the shape_main listener callback format.
+fn compat_event_line(callback: &str, topic_name: &str, details: &str) -> String {
+  format!("{}() topic: '{}'  type: '{}' : {}", callback, topic_name, COMPAT_TYPE_NAME, details)
+}
+
+fn print_compat_event(callback: &str, topic_name: &str, details: String) {
+  println!("{}", compat_event_line(callback, topic_name, &details))
+}
+
+/// Incompatible QoS details as shape_main prints them: the standard
+/// QosPolicyId_t number and the policy name.
+fn compat_policy(policy_id: &QosPolicyId) -> String {
+  let (id, name) = match policy_id {
+    QosPolicyId::Invalid => (0, "Unknown"),
+    QosPolicyId::UserData => (1, "USERDATA"),
+    QosPolicyId::Durability => (2, "DURABILITY"),
+    QosPolicyId::Presentation => (3, "PRESENTATION"),
+    QosPolicyId::Deadline => (4, "DEADLINE"),
+    QosPolicyId::LatencyBudget => (5, "LATENCYBUDGET"),
+    QosPolicyId::Ownership => (6, "OWNERSHIP"),
+    QosPolicyId::OwnershipStrength => (7, "OWNERSHIPSTRENGTH"),
+    QosPolicyId::Liveliness => (8, "LIVELINESS"),
+    QosPolicyId::TimeBasedFilter => (9, "TIMEBASEDFILTER"),
+    QosPolicyId::Partition => (10, "PARTITION"),
+    QosPolicyId::Reliability => (11, "RELIABILITY"),
+    QosPolicyId::DestinationOrder => (12, "DESTINATIONORDER"),
+    QosPolicyId::History => (13, "HISTORY"),
+    QosPolicyId::ResourceLimits => (14, "RESOURCELIMITS"),
+    QosPolicyId::EntityFactory => (15, "ENTITYFACTORY"),
+    QosPolicyId::WriterDataLifeCycle => (16, "WRITERDATALIFECYCLE"),
+    QosPolicyId::ReaderDataLifeCycle => (17, "READERDATALIFECYCLE"),
+    QosPolicyId::TopicData => (18, "TOPICDATA"),
+    QosPolicyId::GroupData => (19, "GROUPDATA"),
+    QosPolicyId::TransportPriority => (20, "TRANSPORTPRIORITY"),
+    QosPolicyId::Lifespan => (21, "LIFESPAN"),
+    QosPolicyId::DurabilityService => (22, "DURABILITYSERVICE"),
+  };
+  format!("{} ({})", id, name)
+}
+
+/// Current numbers of alive and not alive writers of a reader.
+///
+/// RustDDS reports liveliness as running totals of writers that became alive
+/// and not alive, but shape_main prints the current counts, so Compat output
+/// keeps them per topic from the changes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct LivelinessCounts {
+  alive: i32,
+  not_alive: i32,
+}
+
+impl LivelinessCounts {
+  fn apply(&mut self, alive_change: i32, not_alive_change: i32) {
+    let lost = not_alive_change.min(self.alive);
+    self.alive -= lost;
+    self.not_alive += lost;
+    // Writers becoming alive again were counted as not alive.
+    let recovered = alive_change.min(self.not_alive);
+    self.not_alive -= recovered;
+    self.alive += alive_change;
+  }
+}
+
+static LIVELINESS: Mutex<BTreeMap<String, LivelinessCounts>> = Mutex::new(BTreeMap::new());
+
+/// RustDDS has only the UDPv4 transport, so every matched endpoint uses that.
+pub const TRANSPORT: &str = "UDPv4";
+
+pub fn report_writer_status(format: LogFormat, topic_name: &str, status: &DataWriterStatus) {
+  report_named_writer_status(format, "DataWriter", topic_name, status)
+}
+
+/// Like report_writer_status, for a mode with several DataWriters on one
+/// topic. The entity name tells them apart, e.g. "DataWriter strength 10".
+pub fn report_named_writer_status(format: LogFormat, entity: &str, topic_name: &str, status: &DataWriterStatus) {
+  timeline::record("writer_status", Some(topic_name), json!(format!("{:?}", status)));
+  match format {
+    LogFormat::Text => match status {
+      DataWriterStatus::PublicationMatched { .. } =>
+        println!("{} status: {:?} transport {}", entity, status, TRANSPORT),
+      _ => println!("{} status: {:?}", entity, status),
+    },
+    LogFormat::Json => {
+      let (event, details) = match status {
+        DataWriterStatus::LivelinessLost { count } =>
+          ("liveliness_lost", json!({ "count": count_json(count) })),
+        DataWriterStatus::OfferedDeadlineMissed { count } =>
+          ("offered_deadline_missed", json!({ "count": count_json(count) })),
+        DataWriterStatus::OfferedIncompatibleQos { count, last_policy_id, policies } =>
+          ("offered_incompatible_qos", json!({
+            "count": count_json(count),
+            "last_policy_id": format!("{:?}", last_policy_id),
+            "policies": policies_json(policies),
+          })),
+        DataWriterStatus::PublicationMatched { total, current } =>
+          ("publication_matched", json!({
+            "total": count_json(total),
+            "current": count_json(current),
+            "transport": TRANSPORT,
+          })),
+      };
+      print_json_event(entity, topic_name, event, details)
+    }
+    LogFormat::Compat => match status {
+      DataWriterStatus::LivelinessLost { count } =>
+        print_compat_event("on_liveliness_lost", topic_name,
+          format!("(total = {}, change = {})", count.count(), count.count_change())),
+      DataWriterStatus::OfferedDeadlineMissed { count } =>
+        print_compat_event("on_offered_deadline_missed", topic_name,
+          format!("(total = {}, change = {})", count.count(), count.count_change())),
+      DataWriterStatus::OfferedIncompatibleQos { last_policy_id, .. } =>
+        print_compat_event("on_offered_incompatible_qos", topic_name, compat_policy(last_policy_id)),
+      DataWriterStatus::PublicationMatched { current, .. } =>
+        print_compat_event("on_publication_matched", topic_name,
+          format!("matched readers {} (change = {})", current.count(), current.count_change())),
+    },
+  }
+}
+
+pub fn report_reader_status(format: LogFormat, topic_name: &str, status: &DataReaderStatus) {
+  report_named_reader_status(format, "DataReader", topic_name, status)
+}
+
+/// Like report_reader_status, for a mode with several DataReaders on one
+/// topic. The entity name tells them apart, e.g. "DataReader B".
+pub fn report_named_reader_status(format: LogFormat, entity: &str, topic_name: &str, status: &DataReaderStatus) {
+  timeline::record("reader_status", Some(topic_name), json!(format!("{:?}", status)));
+  match format {
+    LogFormat::Text => match status {
+      DataReaderStatus::SubscriptionMatched { .. } =>
+        println!("{} status: {:?} transport {}", entity, status, TRANSPORT),
+      _ => println!("{} status: {:?}", entity, status),
+    },
+    LogFormat::Json => {
+      let (event, details) = match status {
+        DataReaderStatus::SampleRejected { count, last_reason } =>
+          ("sample_rejected", json!({
+            "count": count_json(count),
+            "last_reason": format!("{:?}", last_reason),
+          })),
+        DataReaderStatus::LivelinessChanged { alive_total, not_alive_total } =>
+          ("liveliness_changed", json!({
+            "alive_total": count_json(alive_total),
+            "not_alive_total": count_json(not_alive_total),
+          })),
+        DataReaderStatus::RequestedDeadlineMissed { count } =>
+          ("requested_deadline_missed", json!({ "count": count_json(count) })),
+        DataReaderStatus::RequestedIncompatibleQos { count, last_policy_id, policies } =>
+          ("requested_incompatible_qos", json!({
+            "count": count_json(count),
+            "last_policy_id": format!("{:?}", last_policy_id),
+            "policies": policies_json(policies),
+          })),
+        DataReaderStatus::SampleLost { count } =>
+          ("sample_lost", json!({ "count": count_json(count) })),
+        DataReaderStatus::SubscriptionMatched { total, current } =>
+          ("subscription_matched", json!({
+            "total": count_json(total),
+            "current": count_json(current),
+            "transport": TRANSPORT,
+          })),
+      };
+      print_json_event(entity, topic_name, event, details)
+    }
+    LogFormat::Compat => match status {
+      // shape_main prints nothing on these.
+      DataReaderStatus::SampleRejected { .. } | DataReaderStatus::SampleLost { .. } => (),
+      DataReaderStatus::LivelinessChanged { alive_total, not_alive_total } => {
+        let counts = {
+          let mut liveliness = LIVELINESS.lock().unwrap();
+          let counts = liveliness.entry(topic_name.to_string()).or_default();
+          counts.apply(alive_total.count_change(), not_alive_total.count_change());
+          *counts
+        };
+        print_compat_event("on_liveliness_changed", topic_name,
+          format!("(alive = {}, not_alive = {})", counts.alive, counts.not_alive))
+      }
+      DataReaderStatus::RequestedDeadlineMissed { count } =>
+        print_compat_event("on_requested_deadline_missed", topic_name,
+          format!("(total = {}, change = {})", count.count(), count.count_change())),
+      DataReaderStatus::RequestedIncompatibleQos { last_policy_id, .. } =>
+        print_compat_event("on_requested_incompatible_qos", topic_name, compat_policy(last_policy_id)),
+      DataReaderStatus::SubscriptionMatched { current, .. } =>
+        print_compat_event("on_subscription_matched", topic_name,
+          format!("matched writers {} (change = {})", current.count(), current.count_change())),
+    },
+  }
+}
+
+/// Reports a console command that the given mode does not handle. There is no
+/// topic, so the JSON record has an empty one.
+pub fn report_unsupported_command(format: LogFormat, mode: &str, command: &Command) {
+  match format {
+    LogFormat::Json => print_json_event("Console", "", "unsupported_command",
+      json!({ "command": format!("{:?}", command), "mode": mode })),
+    _ => println!("{:?} is not supported in {} mode.", command, mode),
+  }
+}
+
+/// Prints one event record as a single line of JSON. Timestamp is in seconds
+/// since Unix epoch.
+pub fn print_json_event(entity: &str, topic_name: &str, event: &str, details: Value) {
+  let timestamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs_f64())
+    .unwrap_or(0.0);
+  let record = json!({
+    "timestamp": timestamp,
+    "entity": entity,
+    "topic": topic_name,
+    "event": event,
+    "details": details,
+  });
+  println!("{}", record);
+}
+
+/// GUID in the usual hex notation: prefix, then entity id, separated by a dot.
+pub fn format_guid(guid: &GUID) -> String {
+  let prefix: String = guid.guidPrefix.entityKey.iter().map(|b| format!("{:02x}", b)).collect();
+  let key: String = guid.entityId.entityKey.iter().map(|b| format!("{:02x}", b)).collect();
+  format!("{}.{}{:02x}", prefix, key, guid.entityId.entityKind)
+}
+
+/// Parseable line with the GUID of a created entity, e.g.
+/// "GUID DataWriter 0102030405060708090a0b0c.00000102 Square"
+pub fn print_guid(entity: &str, guid: &GUID, topic: Option<&str>) {
+  timeline::record("created", topic, json!({ "entity": entity, "guid": format_guid(guid) }));
+  match topic {
+    Some(topic) => println!("GUID {} {} {}", entity, format_guid(guid), topic),
+    None => println!("GUID {} {}", entity, format_guid(guid)),
+  }
+}
+
+fn count_json(c: &CountWithChange) -> Value {
+  json!({ "count": c.count(), "change": c.count_change() })
+}
+
+fn policies_json(policies: &[QosPolicyCount]) -> Value {
+  policies
+    .iter()
+    .map(|p| json!({ "policy_id": format!("{:?}", p.policy_id), "count": p.count }))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn compat_lines_match_shape_main() {
+    // Expected lines as printed by the printf formats of srcCxx/shape_main.cxx
+    let cases = [
+      (compat_event_line("on_offered_incompatible_qos", "Square", &compat_policy(&QosPolicyId::Reliability)),
+        "on_offered_incompatible_qos() topic: 'Square'  type: 'ShapeType' : 11 (RELIABILITY)"),
+      (compat_event_line("on_requested_incompatible_qos", "Circle", &compat_policy(&QosPolicyId::Durability)),
+        "on_requested_incompatible_qos() topic: 'Circle'  type: 'ShapeType' : 2 (DURABILITY)"),
+      (compat_event_line("on_requested_incompatible_qos", "Square", &compat_policy(&QosPolicyId::Deadline)),
+        "on_requested_incompatible_qos() topic: 'Square'  type: 'ShapeType' : 4 (DEADLINE)"),
+      (compat_event_line("on_publication_matched", "Square", "matched readers 1 (change = 1)"),
+        "on_publication_matched() topic: 'Square'  type: 'ShapeType' : matched readers 1 (change = 1)"),
+    ];
+    for (line, expected) in cases.iter() {
+      assert_eq!(line, expected);
+    }
+  }
+
+  #[test]
+  fn liveliness_counts_are_current_counts() {
+    let mut counts = LivelinessCounts::default();
+    counts.apply(2, 0);
+    assert_eq!(counts, LivelinessCounts { alive: 2, not_alive: 0 });
+    counts.apply(0, 1);
+    assert_eq!(counts, LivelinessCounts { alive: 1, not_alive: 1 });
+    // The lost writer comes back
+    counts.apply(1, 0);
+    assert_eq!(counts, LivelinessCounts { alive: 2, not_alive: 0 });
+  }
+}
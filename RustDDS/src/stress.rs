@@ -0,0 +1,143 @@
+/// Many-instances stress test: one DataWriter publishing a large number of
+/// keys, i.e. colors "<color>_1" .. "<color>_n".
+///
+/// All instances are first written once as fast as possible, to measure
+/// instance creation rate. Then we wait for matched reliable readers to
+/// acknowledge them, and after that keep updating all instances every write
+/// interval.
+use std::time::{Duration, Instant};
+
+use log::debug;
+use mio::{Events, PollOpt, Ready};
+use rustdds::dds::qos::QosPolicies;
+use rustdds::dds::statusevents::StatusEvented;
+use rustdds::dds::traits::TopicDescription;
+use rustdds::dds::{DomainParticipant, Topic};
+
+use crate::console::Command;
+use crate::on_error::report_write;
+use crate::status_report::{self, LogFormat};
+use crate::{move_shape, random, random_velocity, Control, Shape, ShapeData, COMMAND_READY, DA_HEIGHT, DA_WIDTH,
+  DEFAULT_WRITE_INTERVAL, STATUS_READY, STOP_PROGRAM};
+
+const ACKNOWLEDGMENT_TIMEOUT: Duration = Duration::from_secs(10);
+const RATE_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+pub fn run_instance_stress<T: ShapeData>(domain_participant: &DomainParticipant, topic: Topic, qos: &QosPolicies,
+    color: &str, instance_count: usize, log_format: LogFormat, control: &Control) -> i32
+{
+  debug!("Instance stress publisher");
+  let mut events = Events::with_capacity(4);
+  let topic_name = topic.get_name().to_string();
+  let publisher = match control.on_error.create("Publisher", || domain_participant.create_publisher(qos)) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let mut writer = match control.on_error.create("DataWriter", ||
+    publisher.create_datawriter_CDR::<T>(topic.clone(), None))
+  {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  control.poll.register(writer.as_status_evented(), STATUS_READY, Ready::readable(), PollOpt::edge())
+    .unwrap();
+
+  let mut instances: Vec<(Shape, i32, i32)> = (1..=instance_count)
+    .map(|i| {
+      let shape = Shape {
+        color: format!("{}_{}", color, i),
+        x: random::int_between(0, DA_WIDTH - 1),
+        y: random::int_between(0, DA_HEIGHT - 1),
+        shapesize: 21,
+      };
+      (shape, random_velocity(), random_velocity())
+    })
+    .collect();
+
+  let start = Instant::now();
+  for (shape, _, _) in &instances {
+    let sample = T::from_shape(shape.clone(), 0);
+    if let Err(code) = control.on_error.write(|| report_write(&topic_name, writer.write(sample.clone(), None))) {
+      return code
+    }
+  }
+  let elapsed = start.elapsed().as_secs_f64();
+  println!(
+    "Created {} instances in {:.3} s ({:.0} instances/s)",
+    instance_count,
+    elapsed,
+    instance_count as f64 / elapsed.max(f64::EPSILON)
+  );
+
+  let ack_start = Instant::now();
+  match writer.wait_for_acknowledgments(ACKNOWLEDGMENT_TIMEOUT) {
+    Ok(true) => println!("All instances acknowledged in {:.3} s", ack_start.elapsed().as_secs_f64()),
+    Ok(false) => println!("Instances not acknowledged within {:?}", ACKNOWLEDGMENT_TIMEOUT),
+    Err(e) => println!("Waiting for acknowledgments failed: {:?}", e),
+  }
+
+  let mut counter: u32 = 1;
+  let mut updates: u64 = 0;
+  let mut next_report = Instant::now() + RATE_REPORT_INTERVAL;
+  let mut next_write = Instant::now() + DEFAULT_WRITE_INTERVAL;
+  loop {
+    control.poll
+      .poll(&mut events, Some(next_write.saturating_duration_since(Instant::now())))
+      .unwrap();
+    for event in &events {
+      match event.token() {
+        STOP_PROGRAM => {
+          if control.stop_requested() {
+            println!("Done.");
+            return 0
+          }
+        }
+        COMMAND_READY => {
+          while let Ok(command) = control.command_receiver.try_recv() {
+            match command {
+              Command::Exit(code) => {
+                println!("Done.");
+                return code
+              }
+              other => status_report::report_unsupported_command(log_format, "instance stress", &other),
+            }
+          }
+        }
+        STATUS_READY => {
+          while let Some(status) = writer.try_recv_status() {
+            status_report::report_writer_status(log_format, &topic_name, &status);
+          }
+        }
+        other_token => {
+          println!("Polled event is {:?}. WTF?", other_token);
+        }
+      }
+    }
+
+    let now = Instant::now();
+    if now < next_write {
+      continue
+    }
+    next_write = std::cmp::max(next_write + DEFAULT_WRITE_INTERVAL, now);
+
+    for instance in instances.iter_mut() {
+      *instance = move_shape(instance.0.clone(), instance.1, instance.2);
+      let sample = T::from_shape(instance.0.clone(), counter);
+      if let Err(code) = control.on_error.write(|| report_write(&topic_name, writer.write(sample.clone(), None))) {
+        return code
+      }
+    }
+    counter = counter.wrapping_add(1);
+    updates += instance_count as u64;
+
+    if now >= next_report {
+      println!(
+        "Updated {} instances: {:.0} samples/s",
+        instance_count,
+        updates as f64 / (now - next_report + RATE_REPORT_INTERVAL).as_secs_f64()
+      );
+      updates = 0;
+      next_report = now + RATE_REPORT_INTERVAL;
+    }
+  }
+}
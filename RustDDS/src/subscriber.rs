@@ -0,0 +1,439 @@
+/// Subscriber role: DataReaders printing, checking and counting what they
+/// receive.
+use std::collections::BTreeSet;
+use std::sync::mpsc;
+use std::time::Instant;
+
+use log::{debug, trace};
+use mio::{Events, PollOpt, Ready};
+use rustdds::dds::qos::QosPolicies;
+use rustdds::dds::statusevents::{DataReaderStatus, StatusEvented};
+use rustdds::dds::traits::{RTPSEntity, TopicDescription};
+use rustdds::dds::{DomainParticipant, Topic};
+
+use crate::builtin_topics::BuiltinTopics;
+use crate::console::Command;
+use crate::expect::Expectation;
+use crate::filter::Filter;
+use crate::history_check::HistoryCheck;
+use crate::inconsistent_topic::InconsistentTopics;
+use crate::keyhash::{self, DisposeCheck};
+use crate::latency::LatencyStats;
+use crate::loss_stats::LossStats;
+use crate::match_wait::MatchWait;
+use crate::ownership::OwnershipTracker;
+use crate::reorder::ReorderStats;
+use crate::soak::{Soak, SoakStatus};
+use crate::state::SubscriberState;
+use crate::status_report::{self, LogFormat};
+use crate::timeline;
+use crate::visual::Visualizer;
+use crate::{endpoint_token, shape_json, split_endpoint_token, Control,
+  Shape, ShapeData, COMMAND_READY, EXIT_EXPECTATION_FAILED, EXIT_SOAK_FAILED, LOSS_REPORT_INTERVAL, READER_READY,
+  RESTART_PARTICIPANT, STATUS_READY, STOP_PROGRAM};
+
+#[derive(Clone)]
+pub struct SubscriberOptions {
+  pub readers_per_topic: usize,
+  pub expectation: Option<Expectation>,
+  pub visual: bool,
+  pub follow_owner: bool,
+  pub filter: Option<Filter>,
+  pub long_key: bool,
+  /// Check that disposed keys match received instances, when the key or its
+  /// hash is under test (--long-key or --key-fields)
+  pub check_disposals: bool,
+  /// (hours, max RSS growth in kB/h)
+  pub soak: Option<(f64, f64)>,
+  /// KeepLast depth to check on late join
+  pub history_check: Option<usize>,
+  /// Print every nth sample. 0 = none.
+  pub print_every: u64,
+  pub wait_for_match: bool,
+  pub builtin_topics: bool,
+  /// Some(clock offset in us, if known) to measure one-way latency
+  pub latency: Option<Option<f64>>,
+  /// Receives what the first reader of each topic sees, for harnesses running the subscriber in-process
+  pub events: Option<mpsc::Sender<SubscriberEvent>>,
+}
+
+impl Default for SubscriberOptions {
+  fn default() -> SubscriberOptions {
+    SubscriberOptions { readers_per_topic: 1, expectation: None, visual: false, follow_owner: false, filter: None,
+      long_key: false, check_disposals: false, soak: None, history_check: None, print_every: 1, wait_for_match: false,
+      builtin_topics: false, latency: None, events: None }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub enum SubscriberEvent {
+  /// A sample that passed the filter
+  Sample { topic: String, shape: Shape, counter: Option<u32> },
+  /// Instance disposed or unregistered, by color
+  NotAlive { topic: String, color: String },
+  Status { topic: String, status: DataReaderStatus },
+}
+
+fn send_event(events: &Option<mpsc::Sender<SubscriberEvent>>, event: impl FnOnce() -> SubscriberEvent) {
+  if let Some(sender) = events {
+    // The receiving harness may have stopped listening already
+    sender.send(event()).unwrap_or(());
+  }
+}
+
+/// A DataReader and what it has received.
+struct ShapeReader<R> {
+  reader: R,
+  /// Number of this reader among the readers of the same topic
+  index: usize,
+  topic_name: String,
+  created: Instant,
+  matched_once: bool,
+  state: SubscriberState,
+}
+
+/// Sample counts of each reader, and whether they differ, when there are
+/// several readers per topic.
+fn print_reader_summary<R>(readers: &[ShapeReader<R>]) {
+  let mut topics: Vec<&str> = readers.iter().map( |r| r.topic_name.as_str() ).collect();
+  topics.dedup();
+  for topic in topics {
+    let counts: Vec<u64> = readers.iter().filter( |r| r.topic_name == topic )
+      .map( |r| r.state.samples_received ).collect();
+    let min = counts.iter().min().cloned().unwrap_or(0);
+    let max = counts.iter().max().cloned().unwrap_or(0);
+    let counts_text: Vec<String> = counts.iter().map( |c| c.to_string() ).collect();
+    println!("Readers on {}: samples received {}{}", topic, counts_text.join(" "),
+      if min == max { String::new() } else { format!(" (diverged by {})", max - min) });
+  }
+}
+
+/// Totals printed when the subscriber stops, and in snapshots.
+fn print_subscriber_totals<R>(readers: &[ShapeReader<R>], loss_stats: &LossStats, reorder_stats: &ReorderStats,
+    latency: &Option<LatencyStats>, filter: &Option<Filter>, dispose_check: Option<&DisposeCheck>)
+{
+  if readers.iter().any( |r| r.index > 0 ) {
+    print_reader_summary(readers);
+  }
+  for r in readers.iter().filter( |r| r.index == 0 ) {
+    println!("{}", r.state.instance_table());
+  }
+  if loss_stats.is_active() {
+    println!("{}", loss_stats.total_report());
+  }
+  if reorder_stats.is_active() {
+    for line in reorder_stats.report() {
+      println!("{}", line);
+    }
+  }
+  if let Some(l) = latency {
+    println!("{}", l.report());
+  }
+  if let Some(f) = filter {
+    println!("{}", f.report());
+  }
+  if let Some(d) = dispose_check {
+    println!("{}", d.report());
+  }
+}
+
+pub fn run_subscriber<T: ShapeData>(domain_participant: &DomainParticipant, topics: Vec<Topic>, qos: &QosPolicies,
+    options: SubscriberOptions, log_format: LogFormat, control: &Control) -> i32
+{
+  debug!("Subscriber");
+  let readers_per_topic = options.readers_per_topic;
+  let expectation = options.expectation;
+  let started = Instant::now();
+  let mut events = Events::with_capacity(4);
+  let subscriber = match control.on_error.create("Subscriber", || domain_participant.create_subscriber(qos)) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let reader_topics = topics.iter().flat_map( |topic| (0..readers_per_topic).map( move |k| (topic, k) ) );
+  let readers: Result<Vec<_>, i32> = reader_topics.enumerate().map( |(i, (topic, index))| {
+    let topic_name = topic.get_name().to_string();
+    if log_format == LogFormat::Compat {
+      println!("Create reader for topic: {}", topic_name);
+    }
+    let mut reader = control.on_error.create("DataReader", ||
+      subscriber.create_datareader_CDR::<T>( topic.clone(), Some(qos.clone()) ))?;
+    status_report::print_guid("DataReader", &reader.get_guid(), Some(&topic_name));
+    control.poll.register(&reader, endpoint_token(READER_READY, i), Ready::readable(),PollOpt::edge())
+      .unwrap();
+    control.poll.register(reader.as_status_evented(), endpoint_token(STATUS_READY, i),
+        Ready::readable(), PollOpt::edge())
+      .unwrap();
+    Ok(ShapeReader {
+      reader,
+      index,
+      state: SubscriberState::new(&topic_name),
+      topic_name,
+      created: Instant::now(),
+      matched_once: false,
+    })
+  }).collect();
+  let mut readers = match readers {
+    Ok(readers) => readers,
+    Err(code) => return code,
+  };
+  debug!("Created DataReaders");
+  let multiple_readers = readers_per_topic > 1;
+  let endpoint_count = readers.len();
+  let mut unmatched = endpoint_count;
+  let mut loss_stats = LossStats::new();
+  let mut reorder_stats = ReorderStats::default();
+  let mut latency = options.latency.map(LatencyStats::new);
+  let mut paused = false;
+  let mut visual = if options.visual {
+    let topic_names: Vec<String> = topics.iter().map( |t| t.get_name().to_string() ).collect();
+    Some(Visualizer::new(&topic_names))
+  } else { None };
+  let mut filter = options.filter;
+  if let Some(f) = &filter {
+    println!("{}", f.report());
+  }
+  // (reader, instance key name) of received samples, to check that disposals match them
+  let mut seen_instances: BTreeSet<(usize, String)> = BTreeSet::new();
+  let mut dispose_check = DisposeCheck::default();
+  let long_key = options.long_key;
+  let check_disposals = options.check_disposals;
+  let mut soak = options.soak.map( |(hours, growth)| Soak::new(hours, growth) );
+  if let Some(s) = &soak {
+    println!("{}", s.describe());
+  }
+  let mut inconsistent_topics = InconsistentTopics::new(&topics);
+  let mut match_wait = options.wait_for_match.then( || MatchWait::new(&topics) );
+  let mut builtin_topics = options.builtin_topics.then(BuiltinTopics::default);
+  let mut history_check = options.history_check.map(HistoryCheck::new);
+  let print_every = options.print_every;
+  // samples to print over the whole run, for --print-every
+  let mut sample_number: u64 = 0;
+  // samples to print and printed since the rate was last reported
+  let mut printable_samples: u64 = 0;
+  let mut printed_samples: u64 = 0;
+  let mut rate_report_start = Instant::now();
+  let mut owners = if options.follow_owner { Some(OwnershipTracker::default()) } else { None };
+  loop {
+    let timeout = visual.as_ref().map_or(LOSS_REPORT_INTERVAL, |v| v.redraw_timeout().min(LOSS_REPORT_INTERVAL))
+      .min(soak.as_ref().map_or(LOSS_REPORT_INTERVAL, |s| s.next_due().saturating_duration_since(Instant::now())));
+    control.poll.poll(&mut events, Some(timeout)).unwrap();
+    for event in &events {
+      match event.token() {
+        STOP_PROGRAM => {
+          if control.stop_requested() {
+            print_subscriber_totals(&readers, &loss_stats, &reorder_stats, &latency, &filter,
+              check_disposals.then_some(&dispose_check));
+            println!("Done.");
+            return 0
+          }
+        }
+        COMMAND_READY => {
+          while let Ok(command) = control.command_receiver.try_recv() {
+            match command {
+              Command::Pause => { paused = true; println!("Paused printing samples.") }
+              Command::Resume => { paused = false; println!("Resumed printing samples.") }
+              Command::TogglePause => {
+                paused = ! paused;
+                println!("{}", if paused { "Paused printing samples." } else { "Resumed printing samples." });
+              }
+              Command::Snapshot => {
+                for r in &readers {
+                  match log_format {
+                    LogFormat::Text | LogFormat::Compat => println!("{}", r.state.text_snapshot()),
+                    LogFormat::Json => status_report::print_json_event("Subscriber", &r.topic_name, "snapshot",
+                      serde_json::to_value(&r.state).unwrap()),
+                  }
+                }
+                print_subscriber_totals(&readers, &loss_stats, &reorder_stats, &latency, &filter,
+                  check_disposals.then_some(&dispose_check));
+              }
+              Command::Exit(code) => {
+                print_subscriber_totals(&readers, &loss_stats, &reorder_stats, &latency, &filter,
+                  check_disposals.then_some(&dispose_check));
+                println!("Done.");
+                return code
+              }
+              Command::RestartParticipant => return RESTART_PARTICIPANT,
+              other => println!("{:?} applies to publisher only.", other),
+            }
+          }
+        }
+        other_token => match split_endpoint_token(other_token) {
+          (READER_READY, i) if i < readers.len() => {
+            let r = &mut readers[i];
+            loop {
+              trace!("DataReader triggered");
+              match r.reader.take_next_sample() {
+                Ok(Some(sample)) => {
+                  let writer = sample.sample_info().publication_handle;
+                  if let Some(l) = latency.as_mut() {
+                    l.record(sample.sample_info().source_timestamp);
+                  }
+                  match sample.into_value() {
+                    Ok(sample) => {
+                      let mut details = shape_json(&sample);
+                      details["writer"] = status_report::format_guid(&writer).into();
+                      timeline::record("receive", Some(&r.topic_name), details);
+                      let key = T::key_name(&sample.get_key());
+                      if r.index == 0 && !seen_instances.contains(&(i, key.clone())) {
+                        let color = &sample.shape().color;
+                        if long_key {
+                          println!("New instance {} on {}, key hash {}", key, r.topic_name,
+                            keyhash::format_hash(&keyhash::key_hash(color)));
+                        } else if &key != color {
+                          println!("New instance {} on {}", key, r.topic_name);
+                        }
+                        seen_instances.insert((i, key));
+                      }
+                      if let (Some(o), 0) = (owners.as_mut(), r.index) {
+                        let instance = format!("{}/{}", r.topic_name, sample.shape().color);
+                        o.record(log_format, &r.topic_name, &instance, writer);
+                      }
+                      if let Some(c) = sample.counter() {
+                        let instance = if multiple_readers {
+                          format!("{}#{}/{}", r.topic_name, r.index, sample.shape().color)
+                        } else {
+                          format!("{}/{}", r.topic_name, sample.shape().color)
+                        };
+                        loss_stats.record(&instance, c);
+                        if r.index == 0 {
+                          reorder_stats.record(&writer, c);
+                        }
+                        if let Some(Err(violation)) = history_check.as_mut()
+                          .map( |h| h.record(&instance, c, Instant::now()) )
+                        {
+                          println!("History check failed: {}", violation);
+                          print_subscriber_totals(&readers, &loss_stats, &reorder_stats, &latency, &filter,
+                            check_disposals.then_some(&dispose_check));
+                          return EXIT_EXPECTATION_FAILED
+                        }
+                      }
+                      if let Some(f) = filter.as_mut() {
+                        if !f.accept(sample.shape()) {
+                          continue
+                        }
+                      }
+                      r.state.record_sample(sample.shape(), &status_report::format_guid(&writer), sample.counter());
+                      if r.index == 0 {
+                        send_event(&options.events, || SubscriberEvent::Sample {
+                          topic: r.topic_name.clone(), shape: sample.shape().clone(), counter: sample.counter() });
+                      }
+                      if let Some(Err(violation)) = expectation.as_ref().map( |e| e.check(sample.shape()) ) {
+                        let s = sample.shape();
+                        println!("Expectation failed: {}. Sample: {} {} {} {} [{}]",
+                          violation, r.topic_name, s.color, s.x, s.y, s.shapesize);
+                        print_subscriber_totals(&readers, &loss_stats, &reorder_stats, &latency, &filter,
+                          check_disposals.then_some(&dispose_check));
+                        return EXIT_EXPECTATION_FAILED
+                      }
+                      if paused || r.index > 0 {
+                        continue // only the first reader of each topic prints samples
+                      }
+                      let sample = sample.shape();
+                      if let Some(v) = visual.as_mut() {
+                        v.record(&r.topic_name, sample);
+                        continue
+                      }
+                      printable_samples += 1;
+                      sample_number += 1;
+                      // print_every 0 prints nothing
+                      if sample_number.checked_rem(print_every) != Some(0) {
+                        continue
+                      }
+                      printed_samples += 1;
+                      println!("{}", status_report::sample_line(log_format, &r.topic_name, sample))
+                    }
+                    Err(key) => {
+                      let color = T::key_color(&key);
+                      r.state.record_not_alive(&color, &status_report::format_guid(&writer));
+                      if r.index > 0 {
+                        continue
+                      }
+                      send_event(&options.events,
+                        || SubscriberEvent::NotAlive { topic: r.topic_name.clone(), color: color.clone() });
+                      let name = T::key_name(&key);
+                      if seen_instances.contains(&(i, name.clone())) {
+                        dispose_check.matched += 1;
+                      } else if check_disposals {
+                        dispose_check.unmatched += 1;
+                        println!("Disposed key {} matches no received instance. \
+                          Remote key hash or key serialization may differ.", name);
+                      }
+                      match visual.as_mut() {
+                        Some(v) => v.remove(&r.topic_name, &color),
+                        None => println!("Disposed key {:?}", name),
+                      }
+                    }
+                  }
+                }
+                Ok(None) => break, // no more data
+                Err(e) => println!("DataReader error {:?}", e),
+              } // match
+            }
+          }
+          (STATUS_READY, i) if i < readers.len() => {
+            let r = &mut readers[i];
+            while let Some(status) = r.reader.try_recv_status() {
+              r.state.update_status(&status);
+              status_report::report_reader_status(log_format, &r.topic_name, &status);
+              if r.index == 0 {
+                send_event(&options.events,
+                  || SubscriberEvent::Status { topic: r.topic_name.clone(), status: status.clone() });
+              }
+              if let DataReaderStatus::SubscriptionMatched { .. } = status {
+                if !r.matched_once {
+                  r.matched_once = true;
+                  println!("First match on {} {:.3} s after creating DataReader", r.topic_name,
+                    r.created.elapsed().as_secs_f64());
+                  unmatched -= 1;
+                  if unmatched == 0 && endpoint_count > 1 {
+                    println!("All {} DataReaders matched {:.3} s after start", endpoint_count,
+                      started.elapsed().as_secs_f64());
+                  }
+                }
+                control.report_locators();
+              }
+            }
+          }
+          _ => println!("Polled event is {:?}. WTF?", other_token),
+        }
+      } // match
+    } // for
+    if let Some(v) = visual.as_mut() {
+      v.redraw_if_due();
+    }
+    if loss_stats.is_active() && loss_stats.report_due(LOSS_REPORT_INTERVAL) {
+      println!("{}", loss_stats.interval_report());
+    }
+    inconsistent_topics.check(log_format, domain_participant);
+    if let (Some(m), true) = (match_wait.as_mut(), unmatched > 0) {
+      let waiting: Vec<&str> = readers.iter().filter( |r| !r.matched_once ).map( |r| r.topic_name.as_str() ).collect();
+      m.report(&waiting, domain_participant, control.discovered.as_ref());
+    }
+    if let Some(b) = builtin_topics.as_mut() {
+      b.poll(domain_participant, control.discovered.as_ref());
+    }
+    if print_every != 1 && rate_report_start.elapsed() >= LOSS_REPORT_INTERVAL {
+      let elapsed = rate_report_start.elapsed().as_secs_f64();
+      println!("Received {} samples in {:.1} s ({:.0} samples/s), printed {}",
+        printable_samples, elapsed, printable_samples as f64 / elapsed, printed_samples);
+      printable_samples = 0;
+      printed_samples = 0;
+      rate_report_start = Instant::now();
+    }
+    if let Some(s) = soak.as_mut() {
+      let received: u64 = readers.iter().map( |r| r.state.samples_received ).sum();
+      let exit_code = match s.poll(Instant::now(), &format!("samples received {}", received)) {
+        SoakStatus::Running => None,
+        SoakStatus::Passed(verdict) => { println!("{}", verdict); Some(0) }
+        SoakStatus::Failed(verdict) => { println!("{}", verdict); Some(EXIT_SOAK_FAILED) }
+      };
+      if let Some(code) = exit_code {
+        print_subscriber_totals(&readers, &loss_stats, &reorder_stats, &latency, &filter,
+          check_disposals.then_some(&dispose_check));
+        println!("Done.");
+        return code
+      }
+    }
+  } // loop
+}
@@ -0,0 +1,70 @@
+/// Timeline of notable events, written to a JSON file for post-mortem
+/// analysis together with the timeline of the remote side.
+///
+/// The file is one JSON object with the wall clock time at start and an array
+/// of events in the order they happened. Each event has "t", seconds on the
+/// monotonic clock since start, and "wall", the wall clock time computed from
+/// it, so that timelines from two hosts can be merged. Events are written as
+/// they are recorded, so the file is complete up to a crash except for the
+/// closing brackets.
+use std::fs::File;
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use log::error;
+use serde_json::{json, Value};
+
+struct Timeline {
+  file: File,
+  start: Instant,
+  start_wall: f64,
+  events: u64,
+}
+
+// Events come from all over the program and from background threads, so the
+// timeline is global, like the generic type schema.
+static TIMELINE: OnceLock<Mutex<Timeline>> = OnceLock::new();
+
+/// Starts recording to `path`.
+pub fn start(path: &str) -> std::io::Result<()> {
+  let mut file = File::create(path)?;
+  let start_wall = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or(0.0);
+  write!(file, "{{\"start_wall_clock\": {}, \"events\": [", start_wall)?;
+  let timeline = Timeline { file, start: Instant::now(), start_wall, events: 0 };
+  if TIMELINE.set(Mutex::new(timeline)).is_err() {
+    error!("Timeline already started");
+  }
+  Ok(())
+}
+
+/// Records an event, if the timeline has been started.
+pub fn record(kind: &str, topic: Option<&str>, details: Value) {
+  let mut timeline = match TIMELINE.get() {
+    Some(t) => t.lock().unwrap(),
+    None => return,
+  };
+  let t = timeline.start.elapsed().as_secs_f64();
+  let event = json!({
+    "t": t,
+    "wall": timeline.start_wall + t,
+    "event": kind,
+    "topic": topic,
+    "details": details,
+  });
+  let separator = if timeline.events == 0 { "\n" } else { ",\n" };
+  timeline.events += 1;
+  if let Err(e) = write!(timeline.file, "{}{}", separator, event) {
+    error!("Cannot write timeline: {:?}", e);
+  }
+}
+
+/// Closes the event array. Call before exit.
+pub fn finish() {
+  if let Some(t) = TIMELINE.get() {
+    let mut timeline = t.lock().unwrap();
+    if let Err(e) = writeln!(timeline.file, "\n]}}") {
+      error!("Cannot write timeline: {:?}", e);
+    }
+  }
+}
@@ -0,0 +1,95 @@
+/// Watch mode: survey the topics remote participants announce, without
+/// creating any DataReaders or DataWriters.
+///
+/// Discovered topics are polled from the DomainParticipant, and differences to
+/// the previous poll are printed. A topic disappears when the discovery data
+/// of its participant expires.
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use mio::Events;
+use rustdds::discovery::data_types::topic_data::TopicBuiltinTopicData;
+use rustdds::dds::DomainParticipant;
+
+use crate::console::Command;
+use crate::status_report::{self, format_guid, LogFormat};
+use crate::{Control, COMMAND_READY, STOP_PROGRAM};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Announced QoS policies of a topic
+pub fn describe_qos(data: &TopicBuiltinTopicData) -> String {
+  let policies: Vec<String> = [
+    data.reliability.map(|p| format!("{:?}", p)),
+    data.durability.map(|p| format!("{:?}", p)),
+    data.history.map(|p| format!("{:?}", p)),
+    data.deadline.map(|p| format!("{:?}", p)),
+    data.liveliness.map(|p| format!("{:?}", p)),
+    data.ownership.map(|p| format!("{:?}", p)),
+  ].iter().flatten().cloned().collect();
+  if policies.is_empty() { "defaults".to_string() } else { policies.join(", ") }
+}
+
+/// (topic name, type name, participant GUID prefix) -> QoS description
+fn discovered_topics(domain_participant: &DomainParticipant) -> BTreeMap<(String, String, String), String> {
+  domain_participant.get_discovered_topics().into_iter().filter_map(|discovered| {
+    let data = discovered.topic_data;
+    let participant = data.key
+      .map_or_else(|| "unknown".to_string(), |guid| format_guid(&guid)[..24].to_string());
+    let qos = describe_qos(&data);
+    Some(((data.name?, data.type_name?, participant), qos))
+  }).collect()
+}
+
+pub fn run_topic_watch(domain_participant: &DomainParticipant, log_format: LogFormat, control: &Control) -> i32 {
+  println!("Watching discovered topics.");
+  let mut events = Events::with_capacity(4);
+  let mut known = BTreeMap::new();
+  let mut next_poll = Instant::now();
+  loop {
+    control.poll
+      .poll(&mut events, Some(next_poll.saturating_duration_since(Instant::now())))
+      .unwrap();
+    for event in &events {
+      match event.token() {
+        STOP_PROGRAM => {
+          if control.stop_requested() {
+            println!("Done.");
+            return 0
+          }
+        }
+        COMMAND_READY => {
+          while let Ok(command) = control.command_receiver.try_recv() {
+            match command {
+              Command::Exit(code) => {
+                println!("Done.");
+                return code
+              }
+              other => status_report::report_unsupported_command(log_format, "topic watch", &other),
+            }
+          }
+        }
+        other_token => println!("Polled event is {:?}. WTF?", other_token),
+      }
+    }
+
+    if Instant::now() < next_poll {
+      continue
+    }
+    next_poll += POLL_INTERVAL;
+
+    let current = discovered_topics(domain_participant);
+    for ((name, type_name, participant), qos) in &current {
+      match known.get(&(name.clone(), type_name.clone(), participant.clone())) {
+        None => println!("Topic appeared: {} type {} from participant {}, QoS: {}", name, type_name, participant, qos),
+        Some(old_qos) if old_qos != qos =>
+          println!("Topic changed: {} type {} from participant {}, QoS: {}", name, type_name, participant, qos),
+        _ => (),
+      }
+    }
+    for (name, type_name, participant) in known.keys().filter(|k| !current.contains_key(*k)) {
+      println!("Topic disappeared: {} type {} from participant {}", name, type_name, participant);
+    }
+    known = current;
+  }
+}
@@ -0,0 +1,127 @@
+/// Live ASCII rendering of received shapes.
+///
+/// Each topic is drawn as a character grid scaled down from the Shapes demo
+/// drawing area. An instance is shown by the first letter of its color, and
+/// its recent positions as a trail of dots. A legend below the grid lists the
+/// instances, i.e. the writers, with their latest position and sample count.
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::{Shape, DA_HEIGHT, DA_WIDTH};
+
+const GRID_WIDTH: usize = 48;
+const GRID_HEIGHT: usize = 18;
+const TRAIL_LENGTH: usize = 12;
+pub const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+#[derive(Default)]
+struct InstanceView {
+  /// Latest position last
+  trail: VecDeque<(i32, i32)>,
+  size: i32,
+  samples: u64,
+}
+
+pub struct Visualizer {
+  /// topic name -> color -> view
+  topics: BTreeMap<String, BTreeMap<String, InstanceView>>,
+  next_redraw: Instant,
+}
+
+impl Visualizer {
+  pub fn new(topic_names: &[String]) -> Visualizer {
+    Visualizer {
+      topics: topic_names.iter().map(|t| (t.clone(), BTreeMap::new())).collect(),
+      next_redraw: Instant::now(),
+    }
+  }
+
+  pub fn record(&mut self, topic_name: &str, shape: &Shape) {
+    let view = self.topics.entry(topic_name.to_string()).or_default().entry(shape.color.clone()).or_default();
+    if view.trail.len() == TRAIL_LENGTH {
+      view.trail.pop_front();
+    }
+    view.trail.push_back((shape.x, shape.y));
+    view.size = shape.shapesize;
+    view.samples += 1;
+  }
+
+  /// Removes a disposed or unregistered instance.
+  pub fn remove(&mut self, topic_name: &str, color: &str) {
+    if let Some(instances) = self.topics.get_mut(topic_name) {
+      instances.remove(color);
+    }
+  }
+
+  /// Time until the next redraw is due.
+  pub fn redraw_timeout(&self) -> Duration {
+    self.next_redraw.saturating_duration_since(Instant::now())
+  }
+
+  /// Redraws the screen, if it is time for that.
+  pub fn redraw_if_due(&mut self) {
+    let now = Instant::now();
+    if now < self.next_redraw {
+      return
+    }
+    self.next_redraw = now + REDRAW_INTERVAL;
+    print!("{}{}", CLEAR_SCREEN, self.render());
+  }
+
+  fn render(&self) -> String {
+    let mut out = String::new();
+    let border = format!("+{}+", "-".repeat(GRID_WIDTH));
+    for (topic_name, instances) in &self.topics {
+      let mut grid = vec![vec![' '; GRID_WIDTH]; GRID_HEIGHT];
+      for view in instances.values() {
+        for &(x, y) in view.trail.iter().rev().skip(1) {
+          let (col, row) = cell(x, y);
+          if grid[row][col] == ' ' {
+            grid[row][col] = '.';
+          }
+        }
+      }
+      // Shapes are drawn after all trails, so that a trail does not hide a shape.
+      for (color, view) in instances {
+        if let Some(&(x, y)) = view.trail.back() {
+          let (col, row) = cell(x, y);
+          grid[row][col] = color.chars().next().unwrap_or('?');
+        }
+      }
+      writeln!(out, "{}", topic_name).unwrap();
+      writeln!(out, "{}", border).unwrap();
+      for row in grid {
+        writeln!(out, "|{}|", row.into_iter().collect::<String>()).unwrap();
+      }
+      writeln!(out, "{}", border).unwrap();
+      if instances.is_empty() {
+        writeln!(out, "  (no samples)").unwrap();
+      }
+      for (color, view) in instances {
+        let (x, y) = view.trail.back().cloned().unwrap_or((0, 0));
+        writeln!(
+          out,
+          "  {} {:10.10} x {:3} y {:3} [{}] samples {}",
+          color.chars().next().unwrap_or('?'),
+          color,
+          x,
+          y,
+          view.size,
+          view.samples
+        )
+        .unwrap();
+      }
+    }
+    out
+  }
+}
+
+/// Grid cell (column, row) of a position in the drawing area.
+fn cell(x: i32, y: i32) -> (usize, usize) {
+  let col = x.clamp(0, DA_WIDTH - 1) as usize * GRID_WIDTH / DA_WIDTH as usize;
+  let row = y.clamp(0, DA_HEIGHT - 1) as usize * GRID_HEIGHT / DA_HEIGHT as usize;
+  (col, row)
+}
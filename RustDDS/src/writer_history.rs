@@ -0,0 +1,160 @@
+/// Writer history verification: what a late-joining reliable reader can still
+/// get from a TRANSIENT_LOCAL writer with KeepLast(N) history.
+///
+/// The writer writes M samples to each of INSTANCE_COUNT instances as fast as
+/// it can, one instance after the other, and then pauses. A reader in a
+/// separate DomainParticipant joins after that, and we compare the counters
+/// it receives to the last N of each instance. If the writer applied the depth
+/// to all instances together, only the last instance would keep samples.
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use log::debug;
+use mio::Events;
+use rustdds::dds::data_types::{DDSDuration, TopicKind};
+use rustdds::dds::qos::policy::{Durability, History, Reliability};
+use rustdds::dds::qos::{QosPolicies, QosPolicyBuilder};
+use rustdds::dds::traits::{Keyed, TopicDescription};
+use rustdds::dds::{DomainParticipant, Topic};
+
+use crate::console::Command;
+use crate::on_error::report_write;
+use crate::status_report::{self, LogFormat};
+use crate::{Control, CountedShape, Shape, ShapeData, COMMAND_READY, EXIT_EXPECTATION_FAILED, STOP_PROGRAM};
+
+const INSTANCE_COUNT: usize = 2;
+const PAUSE_BEFORE_JOIN: Duration = Duration::from_secs(1);
+const RECEIVE_TIME: Duration = Duration::from_secs(5);
+const DRAIN_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriterHistoryConfig {
+  /// Samples written to each instance before the late reader joins
+  pub samples_per_instance: u32,
+  /// KeepLast depth of the writer
+  pub depth: u32,
+}
+
+/// Counters the late reader should get for one instance
+fn expected_counters(samples_per_instance: u32, depth: u32) -> Vec<u32> {
+  (samples_per_instance.saturating_sub(depth)..samples_per_instance).collect()
+}
+
+/// Describes the received counters of each instance against the last `depth`
+/// ones. Returns whether all instances got exactly those.
+fn report(received: &BTreeMap<String, Vec<u32>>, instances: &[String], samples_per_instance: u32, depth: u32)
+  -> bool
+{
+  let expected = expected_counters(samples_per_instance, depth);
+  let mut all_ok = true;
+  for instance in instances {
+    let counters = received.get(instance).cloned().unwrap_or_default();
+    let ok = counters == expected;
+    all_ok &= ok;
+    println!("Instance {}: received {} samples {:?}, expected last {} {:?}: {}", instance, counters.len(), counters,
+      expected.len(), expected, if ok { "OK" } else { "MISMATCH" });
+  }
+  let total: usize = received.values().map(Vec::len).sum();
+  let only_last = instances.iter().rev().skip(1).all(|i| received.get(i).is_none_or(Vec::is_empty));
+  let effective = if all_ok {
+    format!("KeepLast({}) per instance", depth)
+  } else if total == 0 {
+    "no history delivered to the late reader".to_string()
+  } else if only_last && instances.len() > 1 {
+    format!("history shared by all instances of the writer, {} samples kept", total)
+  } else {
+    format!("{} samples kept over {} instances", total, instances.len())
+  };
+  println!("Effective writer history: {}", effective);
+  all_ok
+}
+
+pub fn run_writer_history_check(domain_participant: &DomainParticipant, topic: Topic, qos: &QosPolicies,
+    color: &str, config: WriterHistoryConfig, log_format: LogFormat, control: &Control) -> i32
+{
+  let WriterHistoryConfig { samples_per_instance, depth } = config;
+  debug!("Writer history check");
+  let topic_name = topic.get_name().to_string();
+  let publisher = match control.on_error.create("Publisher", || domain_participant.create_publisher(qos)) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let mut writer = match control.on_error.create("DataWriter", ||
+    publisher.create_datawriter_CDR::<CountedShape>(topic.clone(), None)) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let instances: Vec<String> = (1..=INSTANCE_COUNT).map(|i| format!("{}_{}", color, i)).collect();
+  for instance in &instances {
+    for counter in 0..samples_per_instance {
+      let shape = Shape { color: instance.clone(), x: 0, y: 0, shapesize: 21 };
+      let sample = CountedShape::from_shape(shape, counter);
+      if let Err(code) = control.on_error.write(|| report_write(&topic_name, writer.write(sample.clone(), None))) {
+        return code
+      }
+    }
+  }
+  println!("Wrote {} samples to each of {} instances with history depth {}. Late reader joins in {:?}.",
+    samples_per_instance, INSTANCE_COUNT, depth, PAUSE_BEFORE_JOIN);
+  std::thread::sleep(PAUSE_BEFORE_JOIN);
+
+  let late_participant = match control.on_error.create("DomainParticipant", ||
+    DomainParticipant::new(domain_participant.domain_id())) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let late_topic = match control.on_error.create("Topic", || late_participant.create_topic(&topic_name,
+    topic.get_type().name(), qos, TopicKind::WithKey)) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let reader_qos = qos.modify_by(&QosPolicyBuilder::new()
+    .reliability(Reliability::Reliable { max_blocking_time: DDSDuration::DURATION_ZERO })
+    .durability(Durability::TransientLocal)
+    .history(History::KeepAll)
+    .build());
+  let subscriber = match control.on_error.create("Subscriber", || late_participant.create_subscriber(&reader_qos)) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+  let mut reader = match control.on_error.create("DataReader", ||
+    subscriber.create_datareader_CDR::<CountedShape>(late_topic.clone(), Some(reader_qos.clone()))) {
+    Ok(entity) => entity,
+    Err(code) => return code,
+  };
+
+  let mut received: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+  let mut events = Events::with_capacity(4);
+  let receive_end = Instant::now() + RECEIVE_TIME;
+  while Instant::now() < receive_end {
+    control.poll.poll(&mut events, Some(DRAIN_INTERVAL)).unwrap();
+    for event in &events {
+      match event.token() {
+        STOP_PROGRAM => {
+          if control.stop_requested() {
+            println!("Done.");
+            return 0
+          }
+        }
+        COMMAND_READY => {
+          while let Ok(command) = control.command_receiver.try_recv() {
+            match command {
+              Command::Exit(code) => {
+                println!("Done.");
+                return code
+              }
+              other => status_report::report_unsupported_command(log_format, "writer history check", &other),
+            }
+          }
+        }
+        other_token => println!("Polled event is {:?}. WTF?", other_token),
+      }
+    }
+    while let Ok(Some(sample)) = reader.take_next_sample() {
+      if let Ok(s) = sample.into_value() {
+        received.entry(s.get_key()).or_default().push(s.counter);
+      }
+    }
+  }
+  if report(&received, &instances, samples_per_instance, depth) { 0 } else { EXIT_EXPECTATION_FAILED }
+}
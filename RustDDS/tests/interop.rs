@@ -0,0 +1,173 @@
+/// Publisher and subscriber roles running in-process, each on a DomainParticipant
+/// and thread of its own. Every test uses a domain of its own, so that tests can
+/// run in parallel without seeing each other's samples.
+use std::collections::BTreeSet;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mio_extras::channel;
+use rustdds::dds::data_types::TopicKind;
+use rustdds::dds::qos::policy::Durability;
+use rustdds::dds::qos::QosPolicies;
+use rustdds::dds::statusevents::DataReaderStatus;
+use rustdds::dds::{DomainParticipant, Topic};
+
+use rustdds_interop_test::console::Command;
+use rustdds_interop_test::on_error::OnError;
+use rustdds_interop_test::publisher::{run_publisher, PublisherOptions};
+use rustdds_interop_test::qos::QosConfig;
+use rustdds_interop_test::status_report::LogFormat;
+use rustdds_interop_test::subscriber::{run_subscriber, SubscriberEvent, SubscriberOptions};
+use rustdds_interop_test::{Control, CountedShape};
+
+const TOPIC: &str = "Square";
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A publisher or subscriber running in a thread
+struct Role {
+  stop_sender: channel::Sender<()>,
+  command_sender: channel::Sender<Command>,
+  thread: thread::JoinHandle<i32>,
+}
+
+impl Role {
+  fn start(domain_id: u16, qos: QosConfig,
+      run: impl FnOnce(&DomainParticipant, Vec<Topic>, &QosPolicies, &Control) -> i32 + Send + 'static) -> Role
+  {
+    let (senders_tx, senders_rx) = mpsc::channel();
+    let thread = thread::spawn(move || {
+      let (control, stop_sender, command_sender) = Control::new(OnError::Exit).unwrap();
+      senders_tx.send((stop_sender, command_sender)).unwrap();
+      let participant = DomainParticipant::new(domain_id).unwrap();
+      let qos = qos.policies();
+      let topic = participant.create_topic(TOPIC, "ShapeType", &qos, TopicKind::WithKey).unwrap();
+      run(&participant, vec![topic], &qos, &control)
+    });
+    let (stop_sender, command_sender) = senders_rx.recv().unwrap();
+    Role { stop_sender, command_sender, thread }
+  }
+
+  /// Stops the role and returns its exit code
+  fn stop(self) -> i32 {
+    self.stop_sender.send(()).unwrap();
+    self.thread.join().unwrap()
+  }
+}
+
+fn start_publisher(domain_id: u16, qos: QosConfig, options: PublisherOptions) -> Role {
+  Role::start(domain_id, qos, move |participant, topics, qos, control|
+    run_publisher::<CountedShape>(participant, topics, qos, "BLUE", options, LogFormat::Text, control))
+}
+
+fn start_subscriber(domain_id: u16, qos: QosConfig, options: SubscriberOptions)
+  -> (Role, mpsc::Receiver<SubscriberEvent>)
+{
+  let (events_sender, events) = mpsc::channel();
+  let options = SubscriberOptions { events: Some(events_sender), ..options };
+  let role = Role::start(domain_id, qos, move |participant, topics, qos, control|
+    run_subscriber::<CountedShape>(participant, topics, qos, options, LogFormat::Text, control));
+  (role, events)
+}
+
+/// Subscriber events until `done` is satisfied or TIMEOUT passes
+fn events_until(events: &mpsc::Receiver<SubscriberEvent>, done: impl Fn(&[SubscriberEvent]) -> bool)
+  -> Vec<SubscriberEvent>
+{
+  let deadline = Instant::now() + TIMEOUT;
+  let mut received = Vec::new();
+  while !done(&received) {
+    match events.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+      Ok(event) => received.push(event),
+      Err(_) => break,
+    }
+  }
+  received
+}
+
+/// (color, counter) of received samples
+fn samples(events: &[SubscriberEvent]) -> Vec<(String, u32)> {
+  events.iter().filter_map( |e| match e {
+    SubscriberEvent::Sample { shape, counter, .. } => Some((shape.color.clone(), counter.unwrap())),
+    _ => None,
+  }).collect()
+}
+
+fn reliable() -> QosConfig {
+  QosConfig { reliable: true, ..QosConfig::default() }
+}
+
+#[test]
+fn reliable_samples_arrive_in_order_without_loss() {
+  let publisher = start_publisher(41, reliable(), PublisherOptions::default());
+  let (subscriber, events) = start_subscriber(41, reliable(), SubscriberOptions::default());
+  let received = samples(&events_until(&events, |e| samples(e).len() >= 10));
+  assert_eq!(subscriber.stop(), 0);
+  assert_eq!(publisher.stop(), 0);
+
+  assert_eq!(received.len(), 10, "received {:?}", received);
+  assert!(received.iter().all( |(color, _)| color == "BLUE" ));
+  let counters: Vec<u32> = received.iter().map( |(_, c)| *c ).collect();
+  assert!(counters.windows(2).all( |w| w[1] == w[0] + 1 ), "counters {:?}", counters);
+}
+
+#[test]
+fn subscription_matched_status_is_reported() {
+  let publisher = start_publisher(42, reliable(), PublisherOptions::default());
+  let (subscriber, events) = start_subscriber(42, reliable(), SubscriberOptions::default());
+  let matched = |e: &SubscriberEvent|
+    matches!(e, SubscriberEvent::Status { status: DataReaderStatus::SubscriptionMatched { .. }, .. });
+  let received = events_until(&events, |e| e.iter().any(matched));
+  subscriber.stop();
+  publisher.stop();
+
+  assert!(received.iter().any(matched), "events {:?}", received);
+}
+
+#[test]
+fn late_joiner_gets_transient_local_history() {
+  let depth = 3;
+  let qos = QosConfig { durability: Durability::TransientLocal, history_depth: Some(depth as i32), ..reliable() };
+  let publisher = start_publisher(43, qos.clone(), PublisherOptions::default());
+  // Long enough to write more samples than the history keeps
+  thread::sleep(Duration::from_secs(2));
+  let options = SubscriberOptions { history_check: Some(depth), ..SubscriberOptions::default() };
+  let (subscriber, events) = start_subscriber(43, qos, options);
+  let received = samples(&events_until(&events, |e| samples(e).len() >= depth + 2));
+  // The subscriber itself fails if the history it got on joining was wrong.
+  assert_eq!(subscriber.stop(), 0);
+  publisher.stop();
+
+  assert!(received.len() >= depth, "received {:?}", received);
+  assert!(received[0].1 > 0, "history should not start from the first sample: {:?}", received);
+}
+
+#[test]
+fn each_writer_updates_its_own_instance() {
+  let options = PublisherOptions { writers_per_topic: 2, ..PublisherOptions::default() };
+  let publisher = start_publisher(44, reliable(), options);
+  let (subscriber, events) = start_subscriber(44, reliable(), SubscriberOptions::default());
+  let colors = |e: &[SubscriberEvent]| samples(e).into_iter().map( |(color, _)| color ).collect::<BTreeSet<_>>();
+  let received = events_until(&events, |e| colors(e).len() >= 2);
+  subscriber.stop();
+  publisher.stop();
+
+  // The second writer gets the next demo color after the given one.
+  let expected: BTreeSet<String> = ["BLUE", "PURPLE"].iter().map( |c| c.to_string() ).collect();
+  assert_eq!(colors(&received), expected);
+}
+
+#[test]
+fn dispose_reaches_subscriber() {
+  let publisher = start_publisher(45, reliable(), PublisherOptions::default());
+  let (subscriber, events) = start_subscriber(45, reliable(), SubscriberOptions::default());
+  let first = events_until(&events, |e| !samples(e).is_empty());
+  assert!(!samples(&first).is_empty(), "no samples received: {:?}", first);
+  publisher.command_sender.send(Command::Dispose("BLUE".to_string())).unwrap();
+  let disposed = |e: &SubscriberEvent| matches!(e, SubscriberEvent::NotAlive { color, .. } if color == "BLUE");
+  let received = events_until(&events, |e| e.iter().any(disposed));
+  subscriber.stop();
+  publisher.stop();
+
+  assert!(received.iter().any(disposed), "events {:?}", received);
+}